@@ -1,113 +1,485 @@
+use std::{fmt::Debug, future::Future};
+
 use anyhow::Result;
 use aws_sdk_ec2::{
+    error::{ProvideErrorMetadata, SdkError},
     operation::{
         authorize_security_group_ingress::AuthorizeSecurityGroupIngressOutput,
-        create_security_group::CreateSecurityGroupOutput,
-        describe_launch_templates::{
-            builders::DescribeLaunchTemplatesFluentBuilder, DescribeLaunchTemplatesOutput,
-        },
-        describe_security_groups::{
-            builders::DescribeSecurityGroupsFluentBuilder, DescribeSecurityGroupsOutput,
-        },
-        describe_vpcs::DescribeVpcsOutput,
+        create_launch_template::CreateLaunchTemplateOutput,
+        create_launch_template_version::CreateLaunchTemplateVersionOutput,
+        create_security_group::CreateSecurityGroupOutput, create_tags::CreateTagsOutput,
+        delete_tags::DeleteTagsOutput,
+        describe_instances::builders::DescribeInstancesFluentBuilder,
+        describe_launch_templates::builders::DescribeLaunchTemplatesFluentBuilder,
+        describe_security_group_rules::builders::DescribeSecurityGroupRulesFluentBuilder,
+        describe_security_groups::builders::DescribeSecurityGroupsFluentBuilder,
+        describe_volumes::builders::DescribeVolumesFluentBuilder,
+        describe_vpcs::builders::DescribeVpcsFluentBuilder,
+        modify_launch_template::ModifyLaunchTemplateOutput,
         modify_security_group_rules::ModifySecurityGroupRulesOutput,
         revoke_security_group_ingress::RevokeSecurityGroupIngressOutput,
     },
-    types::{SecurityGroupRuleRequest, SecurityGroupRuleUpdate},
+    types::{
+        Filter, Instance, LaunchTemplate, LaunchTemplateVersion, RequestLaunchTemplateData,
+        Reservation, ResourceType, SecurityGroup, SecurityGroupRule, SecurityGroupRuleRequest,
+        SecurityGroupRuleUpdate, Tag, TagSpecification, Volume, Vpc,
+    },
 };
-use log::debug;
+use log::{debug, info};
 
+use crate::amazon_web_services::tagging::TagPolicy;
+
+/// One criterion of an EC2 describe call. A call returns the resources matching every criterion
+/// of its query, and every resource when the query is empty. IDs, and names, given in several
+/// criteria are combined into one list matching any of them.
+#[derive(Debug, Clone, PartialEq)]
 pub enum AwsQueryType {
-    Id,
-    Name,
-    None,
+    Id(Vec<String>),
+    /// The resource's name: the group name of a security group, the template name of a launch
+    /// template and the `Name` tag of anything else.
+    Name(Vec<String>),
+    /// Resources carrying the tag `key`, with one of `values` unless it is empty.
+    Tag {
+        key: String,
+        values: Vec<String>,
+    },
+    /// Any filter the describe call supports.
+    Filter(Filter),
 }
 
-pub async fn create_ec2_launch_template_version(client: aws_sdk_ec2::Client) -> Result<()> {
-    client.create_launch_template_version().send().await?;
+const LAUNCH_TEMPLATE_NAME_FILTER: &str = "launch-template-name";
+const SECURITY_GROUP_NAME_FILTER: &str = "group-name";
+const NAME_TAG_FILTER: &str = "tag:Name";
+const TAG_KEY_FILTER: &str = "tag-key";
+
+/// Whether mutating EC2 calls are allowed to change anything.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExecutionMode {
+    #[default]
+    Apply,
+    /// Requests are logged and sent with `DryRun`, so EC2 only checks permissions and parameters.
+    DryRun,
+}
 
-    Ok(())
+/// EC2 error code for a `DryRun` request that would have succeeded.
+const DRY_RUN_OPERATION_ERROR_CODE: &str = "DryRunOperation";
+const LAUNCH_TEMPLATE_NOT_FOUND_ERROR_CODE: &str = "InvalidLaunchTemplateName.NotFoundException";
+
+/// Sends a mutating request built with `.dry_run(mode == ExecutionMode::DryRun)`.
+///
+/// In dry-run mode the request is logged first, and EC2's `DryRunOperation` answer, which means
+/// the call would have been allowed, becomes `Ok(None)`. Any other error, such as
+/// `UnauthorizedOperation`, is returned as usual.
+pub async fn send_mutation<O, E, F>(
+    mode: ExecutionMode,
+    operation: &str,
+    request: &dyn Debug,
+    response: F,
+) -> Result<Option<O>>
+where
+    E: ProvideErrorMetadata + std::error::Error + Send + Sync + 'static,
+    F: Future<Output = Result<O, SdkError<E>>>,
+{
+    match mode {
+        ExecutionMode::Apply => Ok(Some(response.await?)),
+        ExecutionMode::DryRun => {
+            info!("[dry run] {} {:?}", operation, request);
+            match response.await {
+                Ok(output) => Ok(Some(output)),
+                Err(error) if error.code() == Some(DRY_RUN_OPERATION_ERROR_CODE) => {
+                    info!("[dry run] {} would succeed", operation);
+                    Ok(None)
+                }
+                Err(error) => Err(error.into()),
+            }
+        }
+    }
 }
 
-pub async fn get_ec2_launch_templates(
+/// Logs a request that can't be checked with `DryRun`, typically because it depends on a
+/// resource that a dry run didn't create.
+pub fn log_unsent_mutation(operation: &str, request: &dyn Debug) {
+    info!("[dry run] {} (not sent) {:?}", operation, request);
+}
+
+pub async fn create_ec2_launch_template(
+    client: &aws_sdk_ec2::Client,
+    name: &str,
+    data: RequestLaunchTemplateData,
+    description: Option<String>,
+    tag_policy: &TagPolicy,
+    mode: ExecutionMode,
+) -> Result<Option<CreateLaunchTemplateOutput>> {
+    let query_builder = client
+        .create_launch_template()
+        .launch_template_name(name)
+        .launch_template_data(data)
+        .set_version_description(description)
+        .set_tag_specifications(tag_policy.tag_specifications(&[ResourceType::LaunchTemplate], &[]))
+        .dry_run(mode == ExecutionMode::DryRun);
+
+    let result: Option<CreateLaunchTemplateOutput> = send_mutation(
+        mode,
+        "CreateLaunchTemplate",
+        query_builder.as_input(),
+        query_builder.clone().send(),
+    )
+    .await?;
+
+    Ok(result)
+}
+
+/// Creates a new version of the template `template_id`. With a `source_version`, the new version
+/// starts as a copy of it and `data` only overrides the fields it sets.
+pub async fn create_ec2_launch_template_version(
     client: &aws_sdk_ec2::Client,
-    query_type: AwsQueryType,
-    query: Vec<String>,
-) -> Result<DescribeLaunchTemplatesOutput> {
-    let mut query_builder: DescribeLaunchTemplatesFluentBuilder =
-        client.describe_launch_templates();
+    template_id: &str,
+    source_version: Option<i64>,
+    data: RequestLaunchTemplateData,
+    description: Option<String>,
+    mode: ExecutionMode,
+) -> Result<Option<CreateLaunchTemplateVersionOutput>> {
+    let query_builder = client
+        .create_launch_template_version()
+        .launch_template_id(template_id)
+        .set_source_version(source_version.map(|version| version.to_string()))
+        .launch_template_data(data)
+        .set_version_description(description)
+        .dry_run(mode == ExecutionMode::DryRun);
+
+    let result: Option<CreateLaunchTemplateVersionOutput> = send_mutation(
+        mode,
+        "CreateLaunchTemplateVersion",
+        query_builder.as_input(),
+        query_builder.clone().send(),
+    )
+    .await?;
+
+    Ok(result)
+}
 
-    match query_type {
-        AwsQueryType::Id => query_builder = query_builder.set_launch_template_names(Some(query)),
-        AwsQueryType::Name => query_builder = query_builder.set_launch_template_names(Some(query)),
-        AwsQueryType::None => (),
+/// Describes the given versions of the template `name`, such as `$Latest` and `$Default`.
+/// Returns `None` when the template doesn't exist.
+pub async fn get_ec2_launch_template_versions(
+    client: &aws_sdk_ec2::Client,
+    name: &str,
+    versions: Vec<String>,
+) -> Result<Option<Vec<LaunchTemplateVersion>>> {
+    let result = client
+        .describe_launch_template_versions()
+        .launch_template_name(name)
+        .set_versions(Some(versions))
+        .send()
+        .await;
+
+    match result {
+        Ok(output) => {
+            output
+                .launch_template_versions()
+                .iter()
+                .for_each(|version| debug!("Launch Template Version: {:?}", version));
+            Ok(Some(output.launch_template_versions().to_vec()))
+        }
+        Err(error) if error.code() == Some(LAUNCH_TEMPLATE_NOT_FOUND_ERROR_CODE) => Ok(None),
+        Err(error) => Err(error.into()),
     }
+}
+
+pub async fn set_ec2_launch_template_default_version(
+    client: &aws_sdk_ec2::Client,
+    template_id: &str,
+    version: i64,
+    mode: ExecutionMode,
+) -> Result<Option<ModifyLaunchTemplateOutput>> {
+    let query_builder = client
+        .modify_launch_template()
+        .launch_template_id(template_id)
+        .default_version(version.to_string())
+        .dry_run(mode == ExecutionMode::DryRun);
+
+    let result: Option<ModifyLaunchTemplateOutput> = send_mutation(
+        mode,
+        "ModifyLaunchTemplate",
+        query_builder.as_input(),
+        query_builder.clone().send(),
+    )
+    .await?;
+
+    Ok(result)
+}
+
+/// Adds or overwrites tags on any taggable EC2 resources.
+pub async fn create_tags(
+    client: &aws_sdk_ec2::Client,
+    resource_ids: Vec<String>,
+    tags: Vec<(String, String)>,
+    mode: ExecutionMode,
+) -> Result<Option<CreateTagsOutput>> {
+    let query_builder = client
+        .create_tags()
+        .set_resources(Some(resource_ids))
+        .set_tags(Some(
+            tags.into_iter()
+                .map(|(key, value)| Tag::builder().key(key).value(value).build())
+                .collect(),
+        ))
+        .dry_run(mode == ExecutionMode::DryRun);
+
+    let result: Option<CreateTagsOutput> = send_mutation(
+        mode,
+        "CreateTags",
+        query_builder.as_input(),
+        query_builder.clone().send(),
+    )
+    .await?;
+
+    Ok(result)
+}
+
+/// Removes the tags with the given keys, whatever their values.
+pub async fn delete_tags(
+    client: &aws_sdk_ec2::Client,
+    resource_ids: Vec<String>,
+    keys: Vec<String>,
+    mode: ExecutionMode,
+) -> Result<Option<DeleteTagsOutput>> {
+    let query_builder = client
+        .delete_tags()
+        .set_resources(Some(resource_ids))
+        .set_tags(Some(
+            keys.into_iter()
+                .map(|key| Tag::builder().key(key).build())
+                .collect(),
+        ))
+        .dry_run(mode == ExecutionMode::DryRun);
+
+    let result: Option<DeleteTagsOutput> = send_mutation(
+        mode,
+        "DeleteTags",
+        query_builder.as_input(),
+        query_builder.clone().send(),
+    )
+    .await?;
+
+    Ok(result)
+}
 
-    let result: DescribeLaunchTemplatesOutput = query_builder.send().await?;
+pub async fn get_ec2_launch_templates(
+    client: &aws_sdk_ec2::Client,
+    query: Vec<AwsQueryType>,
+) -> Result<Vec<LaunchTemplate>> {
+    let (ids, filters): (Option<Vec<String>>, Option<Vec<Filter>>) =
+        split_query(query, LAUNCH_TEMPLATE_NAME_FILTER);
+    let query_builder: DescribeLaunchTemplatesFluentBuilder = client
+        .describe_launch_templates()
+        .set_launch_template_ids(ids)
+        .set_filters(filters);
+
+    let result: Vec<LaunchTemplate> = query_builder
+        .into_paginator()
+        .items()
+        .send()
+        .try_collect()
+        .await?;
 
     result
-        .launch_templates()
         .iter()
         .for_each(|template| debug!("Launch Template: {:?}", template));
 
     Ok(result)
 }
 
-pub async fn get_vpcs(
+pub async fn get_vpcs(client: &aws_sdk_ec2::Client, query: Vec<AwsQueryType>) -> Result<Vec<Vpc>> {
+    let (ids, filters): (Option<Vec<String>>, Option<Vec<Filter>>) =
+        split_query(query, NAME_TAG_FILTER);
+    let query_builder: DescribeVpcsFluentBuilder =
+        client.describe_vpcs().set_vpc_ids(ids).set_filters(filters);
+
+    let result: Vec<Vpc> = query_builder
+        .into_paginator()
+        .items()
+        .send()
+        .try_collect()
+        .await?;
+
+    result.iter().for_each(|vpc| debug!("VPC: {:?}", vpc));
+
+    Ok(result)
+}
+
+pub async fn get_security_groups(
+    client: &aws_sdk_ec2::Client,
+    query: Vec<AwsQueryType>,
+) -> Result<Vec<SecurityGroup>> {
+    let (ids, filters): (Option<Vec<String>>, Option<Vec<Filter>>) =
+        split_query(query, SECURITY_GROUP_NAME_FILTER);
+    let query_builder: DescribeSecurityGroupsFluentBuilder = client
+        .describe_security_groups()
+        .set_group_ids(ids)
+        .set_filters(filters);
+
+    let result: Vec<SecurityGroup> = query_builder
+        .into_paginator()
+        .items()
+        .send()
+        .try_collect()
+        .await?;
+
+    result
+        .iter()
+        .for_each(|security_group| debug!("Security Group: {:?}", security_group));
+
+    Ok(result)
+}
+
+/// Instances matching `query`, including terminated ones unless a filter on
+/// `instance-state-name` excludes them.
+pub async fn get_instances(
     client: &aws_sdk_ec2::Client,
-    vpc_ids_filter: Option<Vec<String>>,
-) -> Result<DescribeVpcsOutput> {
-    let result: DescribeVpcsOutput = client
-        .describe_vpcs()
-        .set_vpc_ids(vpc_ids_filter)
+    query: Vec<AwsQueryType>,
+) -> Result<Vec<Instance>> {
+    let (ids, filters): (Option<Vec<String>>, Option<Vec<Filter>>) =
+        split_query(query, NAME_TAG_FILTER);
+    let query_builder: DescribeInstancesFluentBuilder = client
+        .describe_instances()
+        .set_instance_ids(ids)
+        .set_filters(filters);
+
+    let reservations: Vec<Reservation> = query_builder
+        .into_paginator()
+        .items()
         .send()
+        .try_collect()
         .await?;
+    let result: Vec<Instance> = reservations
+        .iter()
+        .flat_map(|reservation| reservation.instances())
+        .cloned()
+        .collect();
 
     result
-        .vpcs()
         .iter()
-        .for_each(|vpc| debug!("VPC: {:?}", vpc));
+        .for_each(|instance| debug!("Instance: {:?}", instance));
 
     Ok(result)
 }
 
-pub async fn get_security_groups(
+pub async fn get_volumes(
     client: &aws_sdk_ec2::Client,
-    query_type: AwsQueryType,
-    query: Vec<String>,
-) -> Result<DescribeSecurityGroupsOutput> {
-    let mut query_builder: DescribeSecurityGroupsFluentBuilder = client.describe_security_groups();
+    query: Vec<AwsQueryType>,
+) -> Result<Vec<Volume>> {
+    let (ids, filters): (Option<Vec<String>>, Option<Vec<Filter>>) =
+        split_query(query, NAME_TAG_FILTER);
+    let query_builder: DescribeVolumesFluentBuilder = client
+        .describe_volumes()
+        .set_volume_ids(ids)
+        .set_filters(filters);
+
+    let result: Vec<Volume> = query_builder
+        .into_paginator()
+        .items()
+        .send()
+        .try_collect()
+        .await?;
 
-    match query_type {
-        AwsQueryType::Id => query_builder = query_builder.set_group_ids(Some(query)),
-        AwsQueryType::Name => query_builder = query_builder.set_group_names(Some(query)),
-        AwsQueryType::None => (),
-    }
+    result
+        .iter()
+        .for_each(|volume| debug!("Volume: {:?}", volume));
 
-    let result: DescribeSecurityGroupsOutput = query_builder.send().await?;
+    Ok(result)
+}
+
+/// Security group rules matching `query`. Rules have no name, so `AwsQueryType::Name` matches
+/// their `Name` tag.
+pub async fn get_security_group_rules(
+    client: &aws_sdk_ec2::Client,
+    query: Vec<AwsQueryType>,
+) -> Result<Vec<SecurityGroupRule>> {
+    let (ids, filters): (Option<Vec<String>>, Option<Vec<Filter>>) =
+        split_query(query, NAME_TAG_FILTER);
+    let query_builder: DescribeSecurityGroupRulesFluentBuilder = client
+        .describe_security_group_rules()
+        .set_security_group_rule_ids(ids)
+        .set_filters(filters);
+
+    let result: Vec<SecurityGroupRule> = query_builder
+        .into_paginator()
+        .items()
+        .send()
+        .try_collect()
+        .await?;
 
     result
-        .security_groups()
         .iter()
-        .for_each(|security_group| debug!("Security Group: {:?}", security_group));
+        .for_each(|rule| debug!("Security Group Rule: {:?}", rule));
 
     Ok(result)
 }
 
+/// Turns `query` into the IDs parameter and the filters of a describe call. Names are matched
+/// with `name_filter`, which unlike the names parameters some describe calls have, works across
+/// VPCs and doesn't fail for names that don't exist.
+fn split_query(
+    query: Vec<AwsQueryType>,
+    name_filter: &str,
+) -> (Option<Vec<String>>, Option<Vec<Filter>>) {
+    let mut ids: Vec<String> = vec![];
+    let mut names: Vec<String> = vec![];
+    let mut filters: Vec<Filter> = vec![];
+
+    for criterion in query {
+        match criterion {
+            AwsQueryType::Id(values) => ids.extend(values),
+            AwsQueryType::Name(values) => names.extend(values),
+            AwsQueryType::Tag { key, values } => filters.push(match values.is_empty() {
+                true => Filter::builder().name(TAG_KEY_FILTER).values(key).build(),
+                false => Filter::builder()
+                    .name(format!("tag:{}", key))
+                    .set_values(Some(values))
+                    .build(),
+            }),
+            AwsQueryType::Filter(filter) => filters.push(filter),
+        }
+    }
+    if !names.is_empty() {
+        filters.push(
+            Filter::builder()
+                .name(name_filter)
+                .set_values(Some(names))
+                .build(),
+        );
+    }
+
+    (
+        (!ids.is_empty()).then_some(ids),
+        (!filters.is_empty()).then_some(filters),
+    )
+}
+
 pub async fn create_security_group(
     client: &aws_sdk_ec2::Client,
     name: Option<String>,
     description: Option<String>,
     vpc_id: Option<String>,
-) -> Result<CreateSecurityGroupOutput> {
+    tag_policy: &TagPolicy,
+    mode: ExecutionMode,
+) -> Result<Option<CreateSecurityGroupOutput>> {
     let mut query_builder = client.create_security_group();
 
     query_builder = query_builder
-        .set_description(name)
-        .set_group_name(description)
-        .set_vpc_id(vpc_id);
-
-    let result: CreateSecurityGroupOutput = query_builder.send().await?;
+        .set_group_name(name)
+        .set_description(description)
+        .set_vpc_id(vpc_id)
+        .set_tag_specifications(tag_policy.tag_specifications(&[ResourceType::SecurityGroup], &[]))
+        .dry_run(mode == ExecutionMode::DryRun);
+
+    let result: Option<CreateSecurityGroupOutput> = send_mutation(
+        mode,
+        "CreateSecurityGroup",
+        query_builder.as_input(),
+        query_builder.clone().send(),
+    )
+    .await?;
 
     Ok(result)
 }
@@ -115,7 +487,8 @@ pub async fn create_security_group(
 pub async fn update_security_group(
     client: &aws_sdk_ec2::Client,
     security_group_id: Option<String>,
-) -> Result<ModifySecurityGroupRulesOutput> {
+    mode: ExecutionMode,
+) -> Result<Option<ModifySecurityGroupRulesOutput>> {
     let security_group_rules = SecurityGroupRuleUpdate::builder()
         .set_security_group_rule(Some(SecurityGroupRuleRequest::builder().build()))
         .build();
@@ -123,13 +496,21 @@ pub async fn update_security_group(
     let query_builder = client
         .modify_security_group_rules()
         .set_group_id(security_group_id)
-        .security_group_rules(security_group_rules);
+        .security_group_rules(security_group_rules)
+        .dry_run(mode == ExecutionMode::DryRun);
 
-    let result: ModifySecurityGroupRulesOutput = query_builder.send().await?;
+    let result: Option<ModifySecurityGroupRulesOutput> = send_mutation(
+        mode,
+        "ModifySecurityGroupRules",
+        query_builder.as_input(),
+        query_builder.clone().send(),
+    )
+    .await?;
 
     Ok(result)
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn add_security_group_ingress(
     client: &aws_sdk_ec2::Client,
     security_group_id: Option<String>,
@@ -137,16 +518,26 @@ pub async fn add_security_group_ingress(
     ip_protocol: Option<String>,
     port_range_start: Option<i32>,
     port_range_end: Option<i32>,
-) -> Result<AuthorizeSecurityGroupIngressOutput> {
+    tag_specifications: Option<Vec<TagSpecification>>,
+    mode: ExecutionMode,
+) -> Result<Option<AuthorizeSecurityGroupIngressOutput>> {
     let query_builder = client
         .authorize_security_group_ingress()
         .set_group_id(security_group_id)
         .set_cidr_ip(cidr_ip)
         .set_from_port(port_range_start)
         .set_to_port(port_range_end)
-        .set_ip_protocol(ip_protocol);
+        .set_ip_protocol(ip_protocol)
+        .set_tag_specifications(tag_specifications)
+        .dry_run(mode == ExecutionMode::DryRun);
 
-    let result: AuthorizeSecurityGroupIngressOutput = query_builder.send().await?;
+    let result: Option<AuthorizeSecurityGroupIngressOutput> = send_mutation(
+        mode,
+        "AuthorizeSecurityGroupIngress",
+        query_builder.as_input(),
+        query_builder.clone().send(),
+    )
+    .await?;
 
     Ok(result)
 }
@@ -158,16 +549,48 @@ pub async fn remove_security_group_ingress(
     ip_protocol: Option<String>,
     port_range_start: Option<i32>,
     port_range_end: Option<i32>,
-) -> Result<RevokeSecurityGroupIngressOutput> {
+    mode: ExecutionMode,
+) -> Result<Option<RevokeSecurityGroupIngressOutput>> {
     let query_builder = client
         .revoke_security_group_ingress()
         .set_group_id(security_group_id)
         .set_cidr_ip(cidr_ip)
         .set_from_port(port_range_start)
         .set_to_port(port_range_end)
-        .set_ip_protocol(ip_protocol);
+        .set_ip_protocol(ip_protocol)
+        .dry_run(mode == ExecutionMode::DryRun);
+
+    let result: Option<RevokeSecurityGroupIngressOutput> = send_mutation(
+        mode,
+        "RevokeSecurityGroupIngress",
+        query_builder.as_input(),
+        query_builder.clone().send(),
+    )
+    .await?;
+
+    Ok(result)
+}
 
-    let result: RevokeSecurityGroupIngressOutput = query_builder.send().await?;
+/// Revokes inbound rules of `security_group_id` by rule ID.
+pub async fn revoke_security_group_ingress_rules(
+    client: &aws_sdk_ec2::Client,
+    security_group_id: &str,
+    security_group_rule_ids: Vec<String>,
+    mode: ExecutionMode,
+) -> Result<Option<RevokeSecurityGroupIngressOutput>> {
+    let query_builder = client
+        .revoke_security_group_ingress()
+        .group_id(security_group_id)
+        .set_security_group_rule_ids(Some(security_group_rule_ids))
+        .dry_run(mode == ExecutionMode::DryRun);
+
+    let result: Option<RevokeSecurityGroupIngressOutput> = send_mutation(
+        mode,
+        "RevokeSecurityGroupIngress",
+        query_builder.as_input(),
+        query_builder.clone().send(),
+    )
+    .await?;
 
     Ok(result)
 }
@@ -206,3 +629,117 @@ pub async fn create_security_group_rule(
 
     Ok(security_group_rules)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    fn filter_values(filters: &[Filter]) -> Vec<(&str, Vec<&str>)> {
+        filters
+            .iter()
+            .map(|filter| {
+                (
+                    filter.name().unwrap_or_default(),
+                    filter.values().iter().map(String::as_str).collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn ids_go_to_the_ids_parameter() {
+        let (ids, filters) = split_query(
+            vec![
+                AwsQueryType::Id(strings(&["sg-1"])),
+                AwsQueryType::Id(strings(&["sg-2", "sg-3"])),
+            ],
+            SECURITY_GROUP_NAME_FILTER,
+        );
+
+        assert_eq!(ids, Some(strings(&["sg-1", "sg-2", "sg-3"])));
+        assert!(filters.is_none());
+    }
+
+    #[test]
+    fn names_go_to_the_name_filter_of_the_resource() {
+        let (ids, filters) = split_query(
+            vec![
+                AwsQueryType::Name(strings(&["web"])),
+                AwsQueryType::Name(strings(&["database"])),
+            ],
+            SECURITY_GROUP_NAME_FILTER,
+        );
+        assert!(ids.is_none());
+        assert_eq!(
+            filter_values(&filters.unwrap()),
+            vec![("group-name", vec!["web", "database"])]
+        );
+
+        let (_, filters) =
+            split_query(vec![AwsQueryType::Name(strings(&["web"]))], NAME_TAG_FILTER);
+        assert_eq!(
+            filter_values(&filters.unwrap()),
+            vec![("tag:Name", vec!["web"])]
+        );
+    }
+
+    #[test]
+    fn tags_without_values_match_the_key_only() {
+        let (_, filters) = split_query(
+            vec![
+                AwsQueryType::Tag {
+                    key: String::from("Team"),
+                    values: vec![],
+                },
+                AwsQueryType::Tag {
+                    key: String::from("Environment"),
+                    values: strings(&["staging", "production"]),
+                },
+            ],
+            NAME_TAG_FILTER,
+        );
+
+        assert_eq!(
+            filter_values(&filters.unwrap()),
+            vec![
+                ("tag-key", vec!["Team"]),
+                ("tag:Environment", vec!["staging", "production"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn filters_are_passed_through_before_the_name_filter() {
+        let (ids, filters) = split_query(
+            vec![
+                AwsQueryType::Name(strings(&["web"])),
+                AwsQueryType::Id(strings(&["lt-1"])),
+                AwsQueryType::Filter(
+                    Filter::builder()
+                        .name("instance-state-name")
+                        .values("running")
+                        .build(),
+                ),
+            ],
+            LAUNCH_TEMPLATE_NAME_FILTER,
+        );
+
+        assert_eq!(ids, Some(strings(&["lt-1"])));
+        assert_eq!(
+            filter_values(&filters.unwrap()),
+            vec![
+                ("instance-state-name", vec!["running"]),
+                ("launch-template-name", vec!["web"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_query_sets_neither_parameter() {
+        assert_eq!(split_query(vec![], NAME_TAG_FILTER), (None, None));
+    }
+}
@@ -0,0 +1,461 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+use aws_sdk_ec2::{
+    client::Waiters,
+    operation::{
+        run_instances::RunInstancesOutput, start_instances::StartInstancesOutput,
+        stop_instances::StopInstancesOutput, terminate_instances::TerminateInstancesOutput,
+    },
+    types::{
+        Filter, Instance, InstanceStateName, LaunchTemplateSpecification, ResourceType,
+        TagSpecification,
+    },
+};
+use log::info;
+
+use crate::{
+    amazon_web_services::{
+        ec2::{get_instances, send_mutation, AwsQueryType, ExecutionMode},
+        tagging::TagPolicy,
+    },
+    configuration::constants::amazon_web_services::{
+        LAUNCH_TEMPLATE_ID_TAG_KEY, LAUNCH_TEMPLATE_VERSION_TAG_KEY, LIVE_INSTANCE_STATES,
+        NAME_TAG_KEY,
+    },
+};
+
+/// The parts of an `Instance` the lifecycle commands report.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstanceSummary {
+    pub instance_id: String,
+    /// Value of the `Name` tag.
+    pub name: Option<String>,
+    pub state: InstanceStateName,
+    pub instance_type: Option<String>,
+    pub launch_template_id: Option<String>,
+    pub launch_template_version: Option<String>,
+    pub subnet_id: Option<String>,
+    pub private_ip_address: Option<String>,
+    pub public_ip_address: Option<String>,
+    pub tags: BTreeMap<String, String>,
+}
+
+impl From<&Instance> for InstanceSummary {
+    fn from(instance: &Instance) -> Self {
+        let tags: BTreeMap<String, String> = instance
+            .tags()
+            .iter()
+            .filter_map(|tag| Some((tag.key()?.to_string(), tag.value()?.to_string())))
+            .collect();
+        let launch_template_id: Option<String> = tags.get(LAUNCH_TEMPLATE_ID_TAG_KEY).cloned();
+        let launch_template_version: Option<String> =
+            tags.get(LAUNCH_TEMPLATE_VERSION_TAG_KEY).cloned();
+
+        InstanceSummary {
+            instance_id: instance.instance_id().unwrap_or_default().to_string(),
+            name: tags.get(NAME_TAG_KEY).cloned(),
+            state: instance
+                .state()
+                .and_then(|state| state.name().cloned())
+                .unwrap_or_else(|| InstanceStateName::from("unknown")),
+            instance_type: instance
+                .instance_type()
+                .map(|kind| kind.as_str().to_string()),
+            launch_template_id,
+            launch_template_version,
+            subnet_id: instance.subnet_id().map(String::from),
+            private_ip_address: instance.private_ip_address().map(String::from),
+            public_ip_address: instance.public_ip_address().map(String::from),
+            tags,
+        }
+    }
+}
+
+impl fmt::Display for InstanceSummary {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            formatter,
+            "{} ({}) {} {}",
+            self.instance_id,
+            self.name.as_deref().unwrap_or("unnamed"),
+            self.state.as_str(),
+            self.instance_type.as_deref().unwrap_or_default()
+        )?;
+        if let Some(address) = &self.public_ip_address {
+            write!(formatter, " public {}", address)?;
+        }
+        if let Some(address) = &self.private_ip_address {
+            write!(formatter, " private {}", address)?;
+        }
+        Ok(())
+    }
+}
+
+/// Everything needed to launch instances from a launch template. Fields left empty keep the
+/// template's values.
+#[derive(Debug, Clone)]
+pub struct LaunchRequest {
+    pub template_name: String,
+    /// A version number, `$Latest` or `$Default`. `None` uses the template's default version.
+    pub template_version: Option<String>,
+    pub subnet_id: Option<String>,
+    /// Replaces the template's security groups when not empty.
+    pub security_group_ids: Vec<String>,
+    /// Applied to the instances, their volumes and network interfaces, on top of the tag policy.
+    pub tags: Vec<(String, String)>,
+    pub count: i32,
+}
+
+/// Which instances a lifecycle command acts on.
+#[derive(Debug, Clone)]
+pub enum InstanceSelector {
+    Ids(Vec<String>),
+    /// Instances carrying every one of these tags. Terminated instances are never selected.
+    Tags(Vec<(String, String)>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstanceAction {
+    Start,
+    Stop,
+    Terminate,
+}
+
+impl fmt::Display for InstanceAction {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InstanceAction::Start => write!(formatter, "start"),
+            InstanceAction::Stop => write!(formatter, "stop"),
+            InstanceAction::Terminate => write!(formatter, "terminate"),
+        }
+    }
+}
+
+/// Launches `request.count` instances, tagged with the policy's tags and the request's, and
+/// returns them as EC2 reported them at launch, or an empty list in dry-run mode.
+pub async fn launch_instances(
+    client: &aws_sdk_ec2::Client,
+    request: &LaunchRequest,
+    tag_policy: &TagPolicy,
+    mode: ExecutionMode,
+) -> Result<Vec<InstanceSummary>> {
+    let launch_template: LaunchTemplateSpecification = LaunchTemplateSpecification::builder()
+        .launch_template_name(&request.template_name)
+        .set_version(request.template_version.clone())
+        .build();
+    let tag_specifications: Option<Vec<TagSpecification>> = tag_policy.tag_specifications(
+        &[
+            ResourceType::Instance,
+            ResourceType::Volume,
+            ResourceType::NetworkInterface,
+        ],
+        &request.tags,
+    );
+    let security_group_ids: Option<Vec<String>> =
+        (!request.security_group_ids.is_empty()).then(|| request.security_group_ids.clone());
+
+    let query_builder = client
+        .run_instances()
+        .launch_template(launch_template)
+        .min_count(request.count)
+        .max_count(request.count)
+        .set_subnet_id(request.subnet_id.clone())
+        .set_security_group_ids(security_group_ids)
+        .set_tag_specifications(tag_specifications)
+        .dry_run(mode == ExecutionMode::DryRun);
+
+    let result: Option<RunInstancesOutput> = send_mutation(
+        mode,
+        "RunInstances",
+        query_builder.as_input(),
+        query_builder.clone().send(),
+    )
+    .await?;
+
+    let instances: Vec<InstanceSummary> = result
+        .map(|output| {
+            output
+                .instances()
+                .iter()
+                .map(InstanceSummary::from)
+                .collect()
+        })
+        .unwrap_or_default();
+    for instance in &instances {
+        info!("Launched {} from {}", instance, request.template_name);
+    }
+
+    Ok(instances)
+}
+
+/// Describes the selected instances, following every page of results.
+pub async fn describe_instances(
+    client: &aws_sdk_ec2::Client,
+    selector: &InstanceSelector,
+) -> Result<Vec<InstanceSummary>> {
+    let instances: Vec<InstanceSummary> = get_instances(client, instance_query(selector))
+        .await?
+        .iter()
+        .map(InstanceSummary::from)
+        .collect();
+
+    Ok(instances)
+}
+
+/// The query selecting the instances `selector` names.
+fn instance_query(selector: &InstanceSelector) -> Vec<AwsQueryType> {
+    match selector {
+        InstanceSelector::Ids(ids) => vec![AwsQueryType::Id(ids.clone())],
+        InstanceSelector::Tags(tags) => {
+            let live_states: Filter = Filter::builder()
+                .name("instance-state-name")
+                .set_values(Some(
+                    LIVE_INSTANCE_STATES
+                        .iter()
+                        .map(|state| state.to_string())
+                        .collect(),
+                ))
+                .build();
+            std::iter::once(AwsQueryType::Filter(live_states))
+                .chain(tags.iter().map(|(key, value)| AwsQueryType::Tag {
+                    key: key.clone(),
+                    values: vec![value.clone()],
+                }))
+                .collect()
+        }
+    }
+}
+
+/// Starts, stops or terminates the selected instances and returns them as they were before the
+/// action. Selecting nothing is an error so a mistyped tag doesn't look like success.
+pub async fn change_instance_state(
+    client: &aws_sdk_ec2::Client,
+    selector: &InstanceSelector,
+    action: InstanceAction,
+    mode: ExecutionMode,
+) -> Result<Vec<InstanceSummary>> {
+    let instances: Vec<InstanceSummary> = describe_instances(client, selector).await?;
+    if instances.is_empty() {
+        return Err(anyhow!("No instances match {:?}", selector));
+    }
+    let instance_ids: Vec<String> = instances
+        .iter()
+        .map(|instance| instance.instance_id.clone())
+        .collect();
+    let dry_run: bool = mode == ExecutionMode::DryRun;
+
+    let changed: bool = match action {
+        InstanceAction::Start => {
+            let query_builder = client
+                .start_instances()
+                .set_instance_ids(Some(instance_ids))
+                .dry_run(dry_run);
+            let result: Option<StartInstancesOutput> = send_mutation(
+                mode,
+                "StartInstances",
+                query_builder.as_input(),
+                query_builder.clone().send(),
+            )
+            .await?;
+            result.is_some()
+        }
+        InstanceAction::Stop => {
+            let query_builder = client
+                .stop_instances()
+                .set_instance_ids(Some(instance_ids))
+                .dry_run(dry_run);
+            let result: Option<StopInstancesOutput> = send_mutation(
+                mode,
+                "StopInstances",
+                query_builder.as_input(),
+                query_builder.clone().send(),
+            )
+            .await?;
+            result.is_some()
+        }
+        InstanceAction::Terminate => {
+            let query_builder = client
+                .terminate_instances()
+                .set_instance_ids(Some(instance_ids))
+                .dry_run(dry_run);
+            let result: Option<TerminateInstancesOutput> = send_mutation(
+                mode,
+                "TerminateInstances",
+                query_builder.as_input(),
+                query_builder.clone().send(),
+            )
+            .await?;
+            result.is_some()
+        }
+    };
+
+    if changed {
+        for instance in &instances {
+            info!("Requested {} of {}", action, instance);
+        }
+    }
+
+    Ok(instances)
+}
+
+/// Waits until the instances are `running` and both their system and instance status checks
+/// pass, then describes them again so public addresses assigned at boot are included. `timeout`
+/// covers both waits.
+pub async fn wait_until_instances_ready(
+    client: &aws_sdk_ec2::Client,
+    instance_ids: Vec<String>,
+    timeout: Duration,
+) -> Result<Vec<InstanceSummary>> {
+    let deadline: Instant = Instant::now() + timeout;
+
+    info!("Waiting for {} to run", instance_ids.join(", "));
+    client
+        .wait_until_instance_running()
+        .set_instance_ids(Some(instance_ids.clone()))
+        .wait(timeout)
+        .await?;
+
+    info!(
+        "Waiting for status checks of {} to pass",
+        instance_ids.join(", ")
+    );
+    client
+        .wait_until_instance_status_ok()
+        .set_instance_ids(Some(instance_ids.clone()))
+        .wait(deadline.saturating_duration_since(Instant::now()))
+        .await?;
+
+    describe_instances(client, &InstanceSelector::Ids(instance_ids)).await
+}
+
+/// Waits until the instances reach the state `action` leads to.
+pub async fn wait_for_instance_action(
+    client: &aws_sdk_ec2::Client,
+    instance_ids: Vec<String>,
+    action: InstanceAction,
+    timeout: Duration,
+) -> Result<Vec<InstanceSummary>> {
+    match action {
+        InstanceAction::Start => {
+            return wait_until_instances_ready(client, instance_ids, timeout).await
+        }
+        InstanceAction::Stop => {
+            client
+                .wait_until_instance_stopped()
+                .set_instance_ids(Some(instance_ids.clone()))
+                .wait(timeout)
+                .await?;
+        }
+        InstanceAction::Terminate => {
+            client
+                .wait_until_instance_terminated()
+                .set_instance_ids(Some(instance_ids.clone()))
+                .wait(timeout)
+                .await?;
+        }
+    }
+
+    describe_instances(client, &InstanceSelector::Ids(instance_ids)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use aws_sdk_ec2::types::{InstanceState, InstanceType, Tag};
+
+    use super::*;
+
+    fn tag(key: &str, value: &str) -> Tag {
+        Tag::builder().key(key).value(value).build()
+    }
+
+    #[test]
+    fn summary_reads_the_name_and_launch_template_tags() {
+        let instance: Instance = Instance::builder()
+            .instance_id("i-0123456789abcdef0")
+            .instance_type(InstanceType::T4gNano)
+            .state(
+                InstanceState::builder()
+                    .name(InstanceStateName::Running)
+                    .build(),
+            )
+            .private_ip_address("10.0.1.12")
+            .tags(tag(NAME_TAG_KEY, "web"))
+            .tags(tag(LAUNCH_TEMPLATE_ID_TAG_KEY, "lt-0123456789abcdef0"))
+            .tags(tag(LAUNCH_TEMPLATE_VERSION_TAG_KEY, "3"))
+            .build();
+
+        let summary: InstanceSummary = InstanceSummary::from(&instance);
+
+        assert_eq!(summary.instance_id, "i-0123456789abcdef0");
+        assert_eq!(summary.name.as_deref(), Some("web"));
+        assert_eq!(summary.state, InstanceStateName::Running);
+        assert_eq!(summary.instance_type.as_deref(), Some("t4g.nano"));
+        assert_eq!(
+            summary.launch_template_id.as_deref(),
+            Some("lt-0123456789abcdef0")
+        );
+        assert_eq!(summary.launch_template_version.as_deref(), Some("3"));
+        assert_eq!(summary.tags.len(), 3);
+        assert_eq!(
+            summary.to_string(),
+            "i-0123456789abcdef0 (web) running t4g.nano private 10.0.1.12"
+        );
+    }
+
+    #[test]
+    fn summary_of_an_instance_without_state_or_tags() {
+        let instance: Instance = Instance::builder()
+            .instance_id("i-0123456789abcdef0")
+            .build();
+
+        let summary: InstanceSummary = InstanceSummary::from(&instance);
+
+        assert_eq!(summary.state.as_str(), "unknown");
+        assert_eq!(summary.name, None);
+        assert_eq!(summary.launch_template_id, None);
+        assert_eq!(summary.launch_template_version, None);
+        assert!(summary.tags.is_empty());
+    }
+
+    #[test]
+    fn ids_are_queried_as_ids() {
+        let ids: Vec<String> = vec![String::from("i-0123456789abcdef0")];
+
+        assert_eq!(
+            instance_query(&InstanceSelector::Ids(ids.clone())),
+            vec![AwsQueryType::Id(ids)]
+        );
+    }
+
+    #[test]
+    fn tags_are_queried_among_live_instances() {
+        let query: Vec<AwsQueryType> = instance_query(&InstanceSelector::Tags(vec![
+            (String::from("Role"), String::from("web")),
+            (String::from("Environment"), String::from("staging")),
+        ]));
+
+        let AwsQueryType::Filter(live_states) = &query[0] else {
+            panic!("Expected the live-state filter first, got {:?}", query[0]);
+        };
+        assert_eq!(live_states.name(), Some("instance-state-name"));
+        assert_eq!(live_states.values(), LIVE_INSTANCE_STATES);
+        assert_eq!(
+            query[1..],
+            [
+                AwsQueryType::Tag {
+                    key: String::from("Role"),
+                    values: vec![String::from("web")],
+                },
+                AwsQueryType::Tag {
+                    key: String::from("Environment"),
+                    values: vec![String::from("staging")],
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,923 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+use aws_sdk_ec2::{
+    operation::{
+        create_launch_template::CreateLaunchTemplateOutput,
+        create_launch_template_version::CreateLaunchTemplateVersionOutput,
+    },
+    types::{
+        InstanceType, LaunchTemplate, LaunchTemplateBlockDeviceMapping,
+        LaunchTemplateBlockDeviceMappingRequest, LaunchTemplateEbsBlockDeviceRequest,
+        LaunchTemplateIamInstanceProfileSpecificationRequest, LaunchTemplateVersion,
+        RequestLaunchTemplateData, ResponseLaunchTemplateData, SecurityGroup, VolumeType,
+    },
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    amazon_web_services::{
+        ec2::{
+            self, create_ec2_launch_template, create_ec2_launch_template_version, create_tags,
+            delete_tags, get_ec2_launch_template_versions, log_unsent_mutation,
+            set_ec2_launch_template_default_version, AwsQueryType, ExecutionMode,
+        },
+        security_groups::is_security_group_id,
+        tagging::TagPolicy,
+    },
+    configuration::constants::amazon_web_services::{
+        DEFAULT_LAUNCH_TEMPLATE_VERSION, LATEST_LAUNCH_TEMPLATE_VERSION,
+        PREVIOUS_DEFAULT_VERSION_TAG_KEY,
+    },
+    file_system::get_file_content_as_bytes,
+};
+
+/// Launch templates lorerunner keeps in the declared state, read from a RON file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LaunchTemplatesDocument {
+    pub launch_templates: Vec<LaunchTemplateSpecification>,
+}
+
+/// The parts of a launch template's data lorerunner manages. Every other field is carried over
+/// from the version the new one is based on.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LaunchTemplateSpecification {
+    pub name: String,
+    pub image_id: String,
+    pub instance_type: String,
+    /// Script passed to instances as user data. `None` leaves user data unmanaged.
+    #[serde(default)]
+    pub user_data_path: Option<PathBuf>,
+    /// Names or IDs of security groups. `None` leaves security groups unmanaged.
+    #[serde(default)]
+    pub security_groups: Option<Vec<String>>,
+    /// Name or ARN of the IAM instance profile. `None` leaves the profile unmanaged.
+    #[serde(default)]
+    pub iam_instance_profile: Option<String>,
+    /// The complete set of EBS volumes. `None` leaves block devices unmanaged.
+    #[serde(default)]
+    pub block_devices: Option<Vec<BlockDeviceSpecification>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct BlockDeviceSpecification {
+    /// Such as `/dev/xvda`.
+    pub device_name: String,
+    /// Size in GiB. Defaults to the size of the AMI's snapshot.
+    #[serde(default)]
+    pub volume_size: Option<i32>,
+    /// `gp3`, `gp2`, `io2`...
+    #[serde(default)]
+    pub volume_type: Option<String>,
+    #[serde(default)]
+    pub iops: Option<i32>,
+    #[serde(default)]
+    pub throughput: Option<i32>,
+    #[serde(default)]
+    pub encrypted: Option<bool>,
+    #[serde(default)]
+    pub delete_on_termination: Option<bool>,
+}
+
+impl BlockDeviceSpecification {
+    fn to_request(&self) -> LaunchTemplateBlockDeviceMappingRequest {
+        let ebs: LaunchTemplateEbsBlockDeviceRequest =
+            LaunchTemplateEbsBlockDeviceRequest::builder()
+                .set_volume_size(self.volume_size)
+                .set_volume_type(self.volume_type.as_deref().map(VolumeType::from))
+                .set_iops(self.iops)
+                .set_throughput(self.throughput)
+                .set_encrypted(self.encrypted)
+                .set_delete_on_termination(self.delete_on_termination)
+                .build();
+
+        LaunchTemplateBlockDeviceMappingRequest::builder()
+            .device_name(&self.device_name)
+            .ebs(ebs)
+            .build()
+    }
+}
+
+impl From<&LaunchTemplateBlockDeviceMapping> for BlockDeviceSpecification {
+    fn from(mapping: &LaunchTemplateBlockDeviceMapping) -> Self {
+        let ebs = mapping.ebs();
+        BlockDeviceSpecification {
+            device_name: mapping.device_name().unwrap_or_default().to_string(),
+            volume_size: ebs.and_then(|ebs| ebs.volume_size()),
+            volume_type: ebs
+                .and_then(|ebs| ebs.volume_type())
+                .map(|volume_type| volume_type.as_str().to_string()),
+            iops: ebs.and_then(|ebs| ebs.iops()),
+            throughput: ebs.and_then(|ebs| ebs.throughput()),
+            encrypted: ebs.and_then(|ebs| ebs.encrypted()),
+            delete_on_termination: ebs.and_then(|ebs| ebs.delete_on_termination()),
+        }
+    }
+}
+
+/// One managed field whose value differs from the version it was compared with.
+#[derive(Debug, Clone)]
+pub struct FieldChange {
+    pub field: &'static str,
+    pub current: String,
+    pub desired: String,
+}
+
+impl fmt::Display for FieldChange {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            formatter,
+            "~ {}: {} -> {}",
+            self.field, self.current, self.desired
+        )
+    }
+}
+
+/// What has to happen for one declared launch template to match its specification.
+#[derive(Debug, Clone)]
+pub struct LaunchTemplatePlan {
+    pub name: String,
+    /// `None` when the template doesn't exist yet and will be created.
+    pub template_id: Option<String>,
+    pub latest_version: Option<i64>,
+    pub default_version: Option<i64>,
+    /// Differences from the latest version. A new version is only created when there are any.
+    pub changes: Vec<FieldChange>,
+    /// The managed fields as they should be written.
+    data: RequestLaunchTemplateData,
+}
+
+impl LaunchTemplatePlan {
+    pub fn needs_new_version(&self) -> bool {
+        self.template_id.is_none() || !self.changes.is_empty()
+    }
+
+    pub fn is_default_latest(&self) -> bool {
+        self.latest_version == self.default_version
+    }
+}
+
+pub fn load_launch_templates_document(path: &Path) -> Result<LaunchTemplatesDocument> {
+    let content: Vec<u8> = get_file_content_as_bytes(path)?;
+    let document: LaunchTemplatesDocument = ron::de::from_bytes(&content).map_err(|error| {
+        anyhow!(
+            "Failed to parse launch templates from {}: {}",
+            path.display(),
+            error
+        )
+    })?;
+
+    Ok(document)
+}
+
+/// Compares every declared template with its latest version and returns the changes needed,
+/// one plan per declared template.
+pub async fn plan_launch_templates(
+    client: &aws_sdk_ec2::Client,
+    document: &LaunchTemplatesDocument,
+) -> Result<Vec<LaunchTemplatePlan>> {
+    let mut plans: Vec<LaunchTemplatePlan> = vec![];
+
+    for specification in &document.launch_templates {
+        let data: RequestLaunchTemplateData =
+            build_launch_template_data(client, specification).await?;
+        let versions: Option<Vec<LaunchTemplateVersion>> = get_ec2_launch_template_versions(
+            client,
+            &specification.name,
+            vec![
+                LATEST_LAUNCH_TEMPLATE_VERSION.to_string(),
+                DEFAULT_LAUNCH_TEMPLATE_VERSION.to_string(),
+            ],
+        )
+        .await?;
+
+        let plan: LaunchTemplatePlan = match versions {
+            None => LaunchTemplatePlan {
+                name: specification.name.clone(),
+                template_id: None,
+                latest_version: None,
+                default_version: None,
+                changes: vec![],
+                data,
+            },
+            Some(versions) => {
+                let latest: &LaunchTemplateVersion = versions
+                    .iter()
+                    .max_by_key(|version| version.version_number())
+                    .ok_or_else(|| {
+                        anyhow!("Launch template {} has no versions", specification.name)
+                    })?;
+                let default_version: Option<i64> = versions
+                    .iter()
+                    .find(|version| version.default_version() == Some(true))
+                    .and_then(|version| version.version_number());
+                let current: ResponseLaunchTemplateData = latest
+                    .launch_template_data()
+                    .cloned()
+                    .unwrap_or_else(|| ResponseLaunchTemplateData::builder().build());
+                let changes: Vec<FieldChange> =
+                    diff_launch_template_data(specification, &data, &current)?;
+
+                LaunchTemplatePlan {
+                    name: specification.name.clone(),
+                    template_id: latest.launch_template_id().map(String::from),
+                    latest_version: latest.version_number(),
+                    default_version,
+                    changes,
+                    data,
+                }
+            }
+        };
+        plans.push(plan);
+    }
+
+    Ok(plans)
+}
+
+pub fn log_launch_template_plans(plans: &[LaunchTemplatePlan]) {
+    for plan in plans {
+        match (&plan.template_id, plan.changes.is_empty()) {
+            (None, _) => info!("{} will be created", plan.name),
+            (Some(template_id), true) => info!(
+                "{} ({}) version {} is up to date",
+                plan.name,
+                template_id,
+                plan.latest_version.unwrap_or_default()
+            ),
+            (Some(template_id), false) => info!(
+                "{} ({}) needs a new version based on version {}",
+                plan.name,
+                template_id,
+                plan.latest_version.unwrap_or_default()
+            ),
+        }
+        if !plan.is_default_latest() {
+            info!(
+                "    default version is {}, latest is {}",
+                plan.default_version.unwrap_or_default(),
+                plan.latest_version.unwrap_or_default()
+            );
+        }
+        for change in &plan.changes {
+            info!("    {}", change);
+        }
+    }
+}
+
+/// Creates missing templates and a new version of every template that changed. With `promote`,
+/// the latest version of each template, new or not, becomes its default.
+pub async fn apply_launch_template_plans(
+    client: &aws_sdk_ec2::Client,
+    plans: Vec<LaunchTemplatePlan>,
+    description: Option<String>,
+    promote: bool,
+    tag_policy: &TagPolicy,
+    mode: ExecutionMode,
+) -> Result<()> {
+    for plan in plans {
+        let Some(template_id) = &plan.template_id else {
+            let result: Option<CreateLaunchTemplateOutput> = create_ec2_launch_template(
+                client,
+                &plan.name,
+                plan.data,
+                description.clone(),
+                tag_policy,
+                mode,
+            )
+            .await?;
+            if let Some(template) = result.as_ref().and_then(|result| result.launch_template()) {
+                info!(
+                    "Created launch template {} ({})",
+                    plan.name,
+                    template.launch_template_id().unwrap_or_default()
+                );
+            }
+            continue;
+        };
+
+        let latest_version: Option<i64> = match plan.changes.is_empty() {
+            true => plan.latest_version,
+            false => {
+                let result: Option<CreateLaunchTemplateVersionOutput> =
+                    create_ec2_launch_template_version(
+                        client,
+                        template_id,
+                        plan.latest_version,
+                        plan.data.clone(),
+                        description.clone(),
+                        mode,
+                    )
+                    .await?;
+                let version_number: Option<i64> = result
+                    .as_ref()
+                    .and_then(|result| result.launch_template_version())
+                    .and_then(|version| version.version_number());
+                if let Some(version_number) = version_number {
+                    info!(
+                        "Created version {} of {} ({})",
+                        version_number, plan.name, template_id
+                    );
+                }
+                version_number
+            }
+        };
+
+        match (promote, latest_version) {
+            (false, _) => (),
+            (true, Some(version)) if Some(version) == plan.default_version => {
+                info!(
+                    "Version {} of {} is already the default",
+                    version, plan.name
+                )
+            }
+            (true, Some(version)) => {
+                promote_launch_template_version(
+                    client,
+                    template_id,
+                    version,
+                    plan.default_version,
+                    mode,
+                )
+                .await?
+            }
+            // Only a dry run doesn't create the version that would be promoted.
+            (true, None) => log_unsent_mutation(
+                "ModifyLaunchTemplate (new version)",
+                &format!("{} DefaultVersion=<new version>", template_id),
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+/// Makes `version` the default of the template `template_id` and remembers `previous_default` in
+/// the `PREVIOUS_DEFAULT_VERSION_TAG_KEY` tag, which `roll_back_launch_template` returns to.
+pub async fn promote_launch_template_version(
+    client: &aws_sdk_ec2::Client,
+    template_id: &str,
+    version: i64,
+    previous_default: Option<i64>,
+    mode: ExecutionMode,
+) -> Result<()> {
+    if let Some(previous_default) = previous_default {
+        create_tags(
+            client,
+            vec![template_id.to_string()],
+            vec![(
+                PREVIOUS_DEFAULT_VERSION_TAG_KEY.to_string(),
+                previous_default.to_string(),
+            )],
+            mode,
+        )
+        .await?;
+    }
+    if set_ec2_launch_template_default_version(client, template_id, version, mode)
+        .await?
+        .is_some()
+    {
+        info!(
+            "Promoted version {} of {} to default (was {})",
+            version,
+            template_id,
+            previous_default.map_or("none".to_string(), |version| version.to_string())
+        );
+    }
+
+    Ok(())
+}
+
+/// Promotes version `version` of the template `name`, recording the current default for
+/// rollback.
+pub async fn promote_launch_template(
+    client: &aws_sdk_ec2::Client,
+    name: &str,
+    version: i64,
+    mode: ExecutionMode,
+) -> Result<()> {
+    let template: LaunchTemplate = get_launch_template(client, name).await?;
+    let template_id: &str = template.launch_template_id().unwrap_or_default();
+    match template.default_version_number() == Some(version) {
+        true => info!("Version {} of {} is already the default", version, name),
+        false => {
+            promote_launch_template_version(
+                client,
+                template_id,
+                version,
+                template.default_version_number(),
+                mode,
+            )
+            .await?
+        }
+    }
+
+    Ok(())
+}
+
+/// Makes the default version recorded by the last promotion the default again. `version`
+/// overrides the recorded one.
+pub async fn roll_back_launch_template(
+    client: &aws_sdk_ec2::Client,
+    name: &str,
+    version: Option<i64>,
+    mode: ExecutionMode,
+) -> Result<()> {
+    let template: LaunchTemplate = get_launch_template(client, name).await?;
+    let template_id: &str = template.launch_template_id().unwrap_or_default();
+    let recorded: Option<i64> = template
+        .tags()
+        .iter()
+        .find(|tag| tag.key() == Some(PREVIOUS_DEFAULT_VERSION_TAG_KEY))
+        .and_then(|tag| tag.value())
+        .and_then(|value| value.parse::<i64>().ok());
+    let version: i64 = version.or(recorded).ok_or_else(|| {
+        anyhow!(
+            "Launch template {} has no recorded previous default version. Pass --version.",
+            name
+        )
+    })?;
+
+    if set_ec2_launch_template_default_version(client, template_id, version, mode)
+        .await?
+        .is_some()
+    {
+        info!(
+            "Rolled back {} ({}) from version {} to version {}",
+            name,
+            template_id,
+            template.default_version_number().unwrap_or_default(),
+            version
+        );
+    }
+    // The recorded version has been used. Leaving it would make a second rollback a no-op
+    // that looks like it did something.
+    if recorded.is_some() {
+        delete_tags(
+            client,
+            vec![template_id.to_string()],
+            vec![PREVIOUS_DEFAULT_VERSION_TAG_KEY.to_string()],
+            mode,
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+async fn get_launch_template(client: &aws_sdk_ec2::Client, name: &str) -> Result<LaunchTemplate> {
+    let templates: Vec<LaunchTemplate> =
+        ec2::get_ec2_launch_templates(client, vec![AwsQueryType::Name(vec![name.to_string()])])
+            .await?;
+    templates
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("Launch template not found: {}", name))
+}
+
+/// Builds the request data for the managed fields of `specification`, resolving security group
+/// names and reading user data.
+async fn build_launch_template_data(
+    client: &aws_sdk_ec2::Client,
+    specification: &LaunchTemplateSpecification,
+) -> Result<RequestLaunchTemplateData> {
+    let mut builder = RequestLaunchTemplateData::builder()
+        .image_id(&specification.image_id)
+        .instance_type(InstanceType::from(specification.instance_type.as_str()));
+
+    if let Some(path) = &specification.user_data_path {
+        let user_data: Vec<u8> = get_file_content_as_bytes(path)?;
+        builder = builder.user_data(STANDARD.encode(user_data));
+    }
+    if let Some(security_groups) = &specification.security_groups {
+        builder = builder.set_security_group_ids(Some(
+            resolve_security_group_ids(client, security_groups).await?,
+        ));
+    }
+    if let Some(profile) = &specification.iam_instance_profile {
+        let profile_builder = LaunchTemplateIamInstanceProfileSpecificationRequest::builder();
+        builder = builder.iam_instance_profile(match profile.starts_with("arn:") {
+            true => profile_builder.arn(profile).build(),
+            false => profile_builder.name(profile).build(),
+        });
+    }
+    if let Some(block_devices) = &specification.block_devices {
+        builder = builder.set_block_device_mappings(Some(
+            block_devices
+                .iter()
+                .map(BlockDeviceSpecification::to_request)
+                .collect(),
+        ));
+    }
+
+    Ok(builder.build())
+}
+
+/// Returns the IDs of `security_groups`, which may mix names and IDs, sorted.
+async fn resolve_security_group_ids(
+    client: &aws_sdk_ec2::Client,
+    security_groups: &[String],
+) -> Result<Vec<String>> {
+    let (mut ids, names): (Vec<String>, Vec<String>) = security_groups
+        .iter()
+        .cloned()
+        .partition(|group| is_security_group_id(group));
+
+    if !names.is_empty() {
+        let groups: Vec<SecurityGroup> =
+            ec2::get_security_groups(client, vec![AwsQueryType::Name(names.clone())]).await?;
+        for name in &names {
+            let matches: Vec<&SecurityGroup> = groups
+                .iter()
+                .filter(|group| group.group_name() == Some(name))
+                .collect();
+            if matches.len() > 1 {
+                return Err(anyhow!(
+                    "Security group name {} is used in several VPCs. Use its ID instead.",
+                    name
+                ));
+            }
+            let group_id: &str = matches
+                .first()
+                .and_then(|group| group.group_id())
+                .ok_or_else(|| anyhow!("Security group not found: {}", name))?;
+            ids.push(group_id.to_string());
+        }
+    }
+    ids.sort();
+    ids.dedup();
+
+    Ok(ids)
+}
+
+/// Compares the managed fields of `desired` with `current`.
+fn diff_launch_template_data(
+    specification: &LaunchTemplateSpecification,
+    desired: &RequestLaunchTemplateData,
+    current: &ResponseLaunchTemplateData,
+) -> Result<Vec<FieldChange>> {
+    let mut changes: Vec<FieldChange> = vec![];
+
+    compare(
+        &mut changes,
+        "image_id",
+        current.image_id().map(String::from),
+        desired.image_id().map(String::from),
+    );
+    compare(
+        &mut changes,
+        "instance_type",
+        current
+            .instance_type()
+            .map(|instance_type| instance_type.as_str().to_string()),
+        desired
+            .instance_type()
+            .map(|instance_type| instance_type.as_str().to_string()),
+    );
+
+    if specification.user_data_path.is_some() {
+        // User data is compared as written but shown by size only; it's usually a long script.
+        if current.user_data() != desired.user_data() {
+            changes.push(FieldChange {
+                field: "user_data",
+                current: current
+                    .user_data()
+                    .map_or("none".to_string(), describe_user_data),
+                desired: desired
+                    .user_data()
+                    .map_or("none".to_string(), describe_user_data),
+            });
+        }
+    }
+
+    if specification.security_groups.is_some() {
+        if !current.network_interfaces().is_empty() {
+            return Err(anyhow!(
+                "Launch template {} assigns security groups through network interfaces, which \
+                 can't be managed. Remove security_groups from its specification.",
+                specification.name
+            ));
+        }
+        let mut current_groups: Vec<String> = current.security_group_ids().to_vec();
+        current_groups.sort();
+        compare(
+            &mut changes,
+            "security_groups",
+            Some(current_groups.join(", ")),
+            Some(desired.security_group_ids().join(", ")),
+        );
+    }
+
+    if let Some(profile) = &specification.iam_instance_profile {
+        let current_profile = current.iam_instance_profile();
+        let current_value: Option<String> = match profile.starts_with("arn:") {
+            true => current_profile.and_then(|profile| profile.arn().map(String::from)),
+            false => current_profile.and_then(|profile| {
+                profile.name().map(String::from).or_else(|| {
+                    // Profiles set by ARN only report the ARN. Its last segment is the name.
+                    profile
+                        .arn()
+                        .and_then(|arn| arn.rsplit('/').next())
+                        .map(String::from)
+                })
+            }),
+        };
+        compare(
+            &mut changes,
+            "iam_instance_profile",
+            current_value,
+            Some(profile.clone()),
+        );
+    }
+
+    if let Some(block_devices) = &specification.block_devices {
+        let mut current_devices: Vec<BlockDeviceSpecification> = current
+            .block_device_mappings()
+            .iter()
+            .map(BlockDeviceSpecification::from)
+            .collect();
+        current_devices.sort();
+        let mut desired_devices: Vec<BlockDeviceSpecification> = block_devices.clone();
+        desired_devices.sort();
+        compare(
+            &mut changes,
+            "block_devices",
+            Some(format!("{:?}", current_devices)),
+            Some(format!("{:?}", desired_devices)),
+        );
+    }
+
+    Ok(changes)
+}
+
+fn compare(
+    changes: &mut Vec<FieldChange>,
+    field: &'static str,
+    current: Option<String>,
+    desired: Option<String>,
+) {
+    if current != desired {
+        changes.push(FieldChange {
+            field,
+            current: current.unwrap_or_else(|| "none".to_string()),
+            desired: desired.unwrap_or_else(|| "none".to_string()),
+        });
+    }
+}
+
+fn describe_user_data(user_data: &str) -> String {
+    match STANDARD.decode(user_data) {
+        Ok(decoded) => format!("{} bytes", decoded.len()),
+        Err(_) => "undecodable".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use aws_sdk_ec2::types::{
+        LaunchTemplateEbsBlockDevice, LaunchTemplateIamInstanceProfileSpecification,
+        LaunchTemplateInstanceNetworkInterfaceSpecification,
+    };
+
+    use super::*;
+
+    const PROFILE_ARN: &str = "arn:aws:iam::123456789012:instance-profile/web";
+
+    fn root_volume() -> BlockDeviceSpecification {
+        BlockDeviceSpecification {
+            device_name: String::from("/dev/xvda"),
+            volume_size: Some(20),
+            volume_type: Some(String::from("gp3")),
+            iops: None,
+            throughput: None,
+            encrypted: Some(true),
+            delete_on_termination: Some(true),
+        }
+    }
+
+    fn data_volume() -> BlockDeviceSpecification {
+        BlockDeviceSpecification {
+            device_name: String::from("/dev/xvdb"),
+            volume_size: Some(100),
+            ..root_volume()
+        }
+    }
+
+    fn specification() -> LaunchTemplateSpecification {
+        LaunchTemplateSpecification {
+            name: String::from("web"),
+            image_id: String::from("ami-0123456789abcdef0"),
+            instance_type: String::from("t3.micro"),
+            user_data_path: None,
+            security_groups: Some(vec![String::from("sg-0123456789abcdef0")]),
+            iam_instance_profile: Some(String::from("web")),
+            block_devices: Some(vec![root_volume(), data_volume()]),
+        }
+    }
+
+    /// What `build_launch_template_data` produces for `specification`, whose security groups
+    /// are already IDs.
+    fn desired(specification: &LaunchTemplateSpecification) -> RequestLaunchTemplateData {
+        let profile = LaunchTemplateIamInstanceProfileSpecificationRequest::builder();
+        RequestLaunchTemplateData::builder()
+            .image_id(&specification.image_id)
+            .instance_type(InstanceType::from(specification.instance_type.as_str()))
+            .set_security_group_ids(specification.security_groups.clone())
+            .set_iam_instance_profile(specification.iam_instance_profile.as_ref().map(|name| {
+                match name.starts_with("arn:") {
+                    true => profile.clone().arn(name).build(),
+                    false => profile.clone().name(name).build(),
+                }
+            }))
+            .set_block_device_mappings(specification.block_devices.as_ref().map(|devices| {
+                devices
+                    .iter()
+                    .map(BlockDeviceSpecification::to_request)
+                    .collect()
+            }))
+            .build()
+    }
+
+    fn block_device_mapping(device: &BlockDeviceSpecification) -> LaunchTemplateBlockDeviceMapping {
+        let ebs: LaunchTemplateEbsBlockDevice = LaunchTemplateEbsBlockDevice::builder()
+            .set_volume_size(device.volume_size)
+            .set_volume_type(device.volume_type.as_deref().map(VolumeType::from))
+            .set_encrypted(device.encrypted)
+            .set_delete_on_termination(device.delete_on_termination)
+            .build();
+
+        LaunchTemplateBlockDeviceMapping::builder()
+            .device_name(&device.device_name)
+            .ebs(ebs)
+            .build()
+    }
+
+    /// The latest version as `describe_launch_template_versions` reports it when it matches
+    /// `specification()`, except that block devices come back in `devices` order.
+    fn current(devices: &[BlockDeviceSpecification]) -> ResponseLaunchTemplateData {
+        let profile: LaunchTemplateIamInstanceProfileSpecification =
+            LaunchTemplateIamInstanceProfileSpecification::builder()
+                .name("web")
+                .build();
+
+        ResponseLaunchTemplateData::builder()
+            .image_id("ami-0123456789abcdef0")
+            .instance_type(InstanceType::from("t3.micro"))
+            .security_group_ids("sg-0123456789abcdef0")
+            .iam_instance_profile(profile)
+            .set_block_device_mappings(Some(devices.iter().map(block_device_mapping).collect()))
+            .build()
+    }
+
+    fn changed_fields(changes: &[FieldChange]) -> Vec<&'static str> {
+        changes.iter().map(|change| change.field).collect()
+    }
+
+    #[test]
+    fn matching_data_needs_no_changes() {
+        let specification: LaunchTemplateSpecification = specification();
+        let changes: Vec<FieldChange> = diff_launch_template_data(
+            &specification,
+            &desired(&specification),
+            &current(&[root_volume(), data_volume()]),
+        )
+        .unwrap();
+
+        assert!(changes.is_empty(), "{:?}", changes);
+    }
+
+    #[test]
+    fn changed_fields_are_reported() {
+        let specification: LaunchTemplateSpecification = LaunchTemplateSpecification {
+            instance_type: String::from("t3.small"),
+            security_groups: Some(vec![String::from("sg-0fedcba9876543210")]),
+            ..specification()
+        };
+        let changes: Vec<FieldChange> = diff_launch_template_data(
+            &specification,
+            &desired(&specification),
+            &current(&[root_volume(), data_volume()]),
+        )
+        .unwrap();
+
+        assert_eq!(
+            changed_fields(&changes),
+            vec!["instance_type", "security_groups"]
+        );
+        assert_eq!(changes[0].current, "t3.micro");
+        assert_eq!(changes[0].desired, "t3.small");
+    }
+
+    #[test]
+    fn profile_set_by_arn_matches_its_name() {
+        let specification: LaunchTemplateSpecification = specification();
+        let current: ResponseLaunchTemplateData = ResponseLaunchTemplateData::builder()
+            .iam_instance_profile(
+                LaunchTemplateIamInstanceProfileSpecification::builder()
+                    .arn(PROFILE_ARN)
+                    .build(),
+            )
+            .build();
+
+        let changes: Vec<FieldChange> =
+            diff_launch_template_data(&specification, &desired(&specification), &current).unwrap();
+
+        assert!(!changed_fields(&changes).contains(&"iam_instance_profile"));
+    }
+
+    #[test]
+    fn profile_declared_by_arn_is_compared_with_the_arn() {
+        let specification: LaunchTemplateSpecification = LaunchTemplateSpecification {
+            iam_instance_profile: Some(String::from(PROFILE_ARN)),
+            ..specification()
+        };
+
+        // The current version only reports the name, so the ARN can't be confirmed.
+        let changes: Vec<FieldChange> = diff_launch_template_data(
+            &specification,
+            &desired(&specification),
+            &current(&[root_volume(), data_volume()]),
+        )
+        .unwrap();
+
+        assert_eq!(changed_fields(&changes), vec!["iam_instance_profile"]);
+        assert_eq!(changes[0].current, "none");
+    }
+
+    #[test]
+    fn block_device_order_is_ignored() {
+        let specification: LaunchTemplateSpecification = specification();
+        let changes: Vec<FieldChange> = diff_launch_template_data(
+            &specification,
+            &desired(&specification),
+            &current(&[data_volume(), root_volume()]),
+        )
+        .unwrap();
+
+        assert!(changes.is_empty(), "{:?}", changes);
+    }
+
+    #[test]
+    fn changed_block_device_is_reported() {
+        let specification: LaunchTemplateSpecification = specification();
+        let resized: BlockDeviceSpecification = BlockDeviceSpecification {
+            volume_size: Some(50),
+            ..data_volume()
+        };
+        let changes: Vec<FieldChange> = diff_launch_template_data(
+            &specification,
+            &desired(&specification),
+            &current(&[root_volume(), resized]),
+        )
+        .unwrap();
+
+        assert_eq!(changed_fields(&changes), vec!["block_devices"]);
+    }
+
+    #[test]
+    fn security_groups_with_network_interfaces_are_rejected() {
+        let specification: LaunchTemplateSpecification = specification();
+        let network_interface: LaunchTemplateInstanceNetworkInterfaceSpecification =
+            LaunchTemplateInstanceNetworkInterfaceSpecification::builder()
+                .device_index(0)
+                .groups("sg-0123456789abcdef0")
+                .build();
+        let current: ResponseLaunchTemplateData = ResponseLaunchTemplateData::builder()
+            .network_interfaces(network_interface)
+            .build();
+
+        assert!(
+            diff_launch_template_data(&specification, &desired(&specification), &current).is_err()
+        );
+
+        let unmanaged: LaunchTemplateSpecification = LaunchTemplateSpecification {
+            security_groups: None,
+            ..specification
+        };
+        assert!(diff_launch_template_data(&unmanaged, &desired(&unmanaged), &current).is_ok());
+    }
+
+    #[test]
+    fn unmanaged_fields_are_not_compared() {
+        let specification: LaunchTemplateSpecification = LaunchTemplateSpecification {
+            security_groups: None,
+            iam_instance_profile: None,
+            block_devices: None,
+            ..specification()
+        };
+        let current: ResponseLaunchTemplateData = ResponseLaunchTemplateData::builder()
+            .image_id("ami-0123456789abcdef0")
+            .instance_type(InstanceType::from("t3.micro"))
+            .security_group_ids("sg-0fedcba9876543210")
+            .build();
+
+        let changes: Vec<FieldChange> =
+            diff_launch_template_data(&specification, &desired(&specification), &current).unwrap();
+
+        assert!(changes.is_empty(), "{:?}", changes);
+    }
+}
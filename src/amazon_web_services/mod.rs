@@ -2,3 +2,8 @@ pub mod code_deploy;
 pub mod configuration;
 pub mod ec2;
 pub mod iam;
+pub mod instances;
+pub mod launch_templates;
+pub mod security_groups;
+pub mod tagging;
+pub mod temporary_access;
@@ -0,0 +1,1071 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    path::Path,
+};
+
+use anyhow::{anyhow, Result};
+use aws_sdk_ec2::{
+    operation::create_security_group::CreateSecurityGroupOutput,
+    types::{
+        IpPermission, IpRange, Ipv6Range, PrefixListId, SecurityGroup, SecurityGroupRule,
+        UserIdGroupPair,
+    },
+};
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    amazon_web_services::ec2::{
+        create_security_group, get_security_group_rules, get_security_groups, log_unsent_mutation,
+        send_mutation, AwsQueryType, ExecutionMode,
+    },
+    amazon_web_services::tagging::TagPolicy,
+    configuration::constants::{
+        amazon_web_services::EXPIRES_AT_TAG_KEY,
+        networking::{ALL_PROTOCOLS, TCP_PROTOCOL},
+    },
+    file_system::get_file_content_as_bytes,
+};
+
+/// Security groups lorerunner keeps in the declared state, read from a RON file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecurityGroupsDocument {
+    pub security_groups: Vec<SecurityGroupSpecification>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecurityGroupSpecification {
+    pub name: String,
+    /// Only used when the group is created. AWS doesn't allow changing it afterwards.
+    pub description: String,
+    /// VPC the group belongs to. Required to tell apart groups with the same name in several
+    /// VPCs. Groups are created in the default VPC when omitted.
+    #[serde(default)]
+    pub vpc_id: Option<String>,
+    /// The complete set of inbound rules. `None` leaves inbound rules unmanaged.
+    #[serde(default)]
+    pub ingress: Option<Vec<RuleSpecification>>,
+    /// The complete set of outbound rules. `None` leaves outbound rules unmanaged, including the
+    /// allow-all rule AWS adds to new groups.
+    #[serde(default)]
+    pub egress: Option<Vec<RuleSpecification>>,
+}
+
+/// One protocol and port range allowed from, or to, any number of sources.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleSpecification {
+    /// `tcp`, `udp`, `icmp`, `icmpv6`, a protocol number, or `-1` for all traffic.
+    #[serde(default = "default_rule_protocol")]
+    pub protocol: String,
+    #[serde(default)]
+    pub from_port: Option<i32>,
+    /// Defaults to `from_port`.
+    #[serde(default)]
+    pub to_port: Option<i32>,
+    #[serde(default)]
+    pub cidr_ipv4: Vec<String>,
+    #[serde(default)]
+    pub cidr_ipv6: Vec<String>,
+    /// Names or IDs of security groups.
+    #[serde(default)]
+    pub security_groups: Vec<String>,
+    #[serde(default)]
+    pub prefix_lists: Vec<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+fn default_rule_protocol() -> String {
+    TCP_PROTOCOL.to_string()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleDirection {
+    Ingress,
+    Egress,
+}
+
+/// Where traffic allowed by a rule comes from (ingress) or goes to (egress).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RuleSource {
+    Ipv4(String),
+    Ipv6(String),
+    /// A security group ID, or the name of a declared group that doesn't exist yet.
+    SecurityGroup(String),
+    PrefixList(String),
+}
+
+/// A single security group rule: one protocol, port range and source. Rules are compared
+/// without their description, which can be changed in place.
+#[derive(Debug, Clone)]
+pub struct PermissionRule {
+    pub protocol: String,
+    pub from_port: Option<i32>,
+    pub to_port: Option<i32>,
+    pub source: RuleSource,
+    pub description: Option<String>,
+}
+
+impl PermissionRule {
+    fn key(&self) -> (&str, Option<i32>, Option<i32>, &RuleSource) {
+        (&self.protocol, self.from_port, self.to_port, &self.source)
+    }
+
+    /// The rule as the `IpPermission` AWS authorize and revoke calls take.
+    pub fn to_ip_permission(&self) -> IpPermission {
+        let builder = IpPermission::builder()
+            .ip_protocol(&self.protocol)
+            .set_from_port(self.from_port)
+            .set_to_port(self.to_port);
+        let description: Option<String> = self.description.clone();
+
+        match &self.source {
+            RuleSource::Ipv4(cidr) => builder.ip_ranges(
+                IpRange::builder()
+                    .cidr_ip(cidr)
+                    .set_description(description)
+                    .build(),
+            ),
+            RuleSource::Ipv6(cidr) => builder.ipv6_ranges(
+                Ipv6Range::builder()
+                    .cidr_ipv6(cidr)
+                    .set_description(description)
+                    .build(),
+            ),
+            RuleSource::SecurityGroup(group_id) => builder.user_id_group_pairs(
+                UserIdGroupPair::builder()
+                    .group_id(group_id)
+                    .set_description(description)
+                    .build(),
+            ),
+            RuleSource::PrefixList(prefix_list_id) => builder.prefix_list_ids(
+                PrefixListId::builder()
+                    .prefix_list_id(prefix_list_id)
+                    .set_description(description)
+                    .build(),
+            ),
+        }
+        .build()
+    }
+}
+
+impl fmt::Display for PermissionRule {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let protocol: &str = match self.protocol.as_str() {
+            ALL_PROTOCOLS => "all traffic",
+            protocol => protocol,
+        };
+        write!(formatter, "{}", protocol)?;
+        match (self.from_port, self.to_port) {
+            (Some(from_port), Some(to_port)) if from_port == to_port => {
+                write!(formatter, " {}", from_port)?
+            }
+            (Some(from_port), Some(to_port)) => write!(formatter, " {}-{}", from_port, to_port)?,
+            _ => (),
+        }
+        match &self.source {
+            RuleSource::Ipv4(cidr) | RuleSource::Ipv6(cidr) => write!(formatter, " {}", cidr)?,
+            RuleSource::SecurityGroup(group) => write!(formatter, " group {}", group)?,
+            RuleSource::PrefixList(prefix_list) => {
+                write!(formatter, " prefix list {}", prefix_list)?
+            }
+        }
+        match &self.description {
+            Some(description) => write!(formatter, " ({})", description),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum RuleChange {
+    Authorize(RuleDirection, PermissionRule),
+    Revoke(RuleDirection, PermissionRule),
+    UpdateDescription(RuleDirection, PermissionRule),
+}
+
+impl RuleChange {
+    pub fn rule(&self) -> &PermissionRule {
+        match self {
+            RuleChange::Authorize(_, rule)
+            | RuleChange::Revoke(_, rule)
+            | RuleChange::UpdateDescription(_, rule) => rule,
+        }
+    }
+
+    /// Name of the EC2 API action that applies the change.
+    pub fn operation(&self) -> &'static str {
+        match self {
+            RuleChange::Authorize(RuleDirection::Ingress, _) => "AuthorizeSecurityGroupIngress",
+            RuleChange::Authorize(RuleDirection::Egress, _) => "AuthorizeSecurityGroupEgress",
+            RuleChange::Revoke(RuleDirection::Ingress, _) => "RevokeSecurityGroupIngress",
+            RuleChange::Revoke(RuleDirection::Egress, _) => "RevokeSecurityGroupEgress",
+            RuleChange::UpdateDescription(RuleDirection::Ingress, _) => {
+                "UpdateSecurityGroupRuleDescriptionsIngress"
+            }
+            RuleChange::UpdateDescription(RuleDirection::Egress, _) => {
+                "UpdateSecurityGroupRuleDescriptionsEgress"
+            }
+        }
+    }
+}
+
+impl fmt::Display for RuleChange {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleChange::Authorize(direction, rule) => {
+                write!(formatter, "+ {:?} {}", direction, rule)
+            }
+            RuleChange::Revoke(direction, rule) => write!(formatter, "- {:?} {}", direction, rule),
+            RuleChange::UpdateDescription(direction, rule) => {
+                write!(formatter, "~ {:?} {}", direction, rule)
+            }
+        }
+    }
+}
+
+/// What has to change for one declared security group to match its specification.
+#[derive(Debug, Clone)]
+pub struct SecurityGroupPlan {
+    pub name: String,
+    /// `None` when the group doesn't exist yet and will be created.
+    pub group_id: Option<String>,
+    pub changes: Vec<RuleChange>,
+}
+
+impl SecurityGroupPlan {
+    pub fn is_empty(&self) -> bool {
+        self.group_id.is_some() && self.changes.is_empty()
+    }
+}
+
+pub fn load_security_groups_document(path: &Path) -> Result<SecurityGroupsDocument> {
+    let content: Vec<u8> = get_file_content_as_bytes(path)?;
+    let document: SecurityGroupsDocument = ron::de::from_bytes(&content).map_err(|error| {
+        anyhow!(
+            "Failed to parse security groups from {}: {}",
+            path.display(),
+            error
+        )
+    })?;
+
+    Ok(document)
+}
+
+/// Compares every declared group with what `describe_security_groups` reports and returns the
+/// changes needed, one plan per declared group.
+///
+/// Ingress rules opened by `aws sg allow-my-ip` are never revoked, even when `ingress` is managed.
+/// They carry an `EXPIRES_AT_TAG_KEY` tag and are left for `aws sg sweep`.
+pub async fn plan_security_groups(
+    client: &aws_sdk_ec2::Client,
+    document: &SecurityGroupsDocument,
+) -> Result<Vec<SecurityGroupPlan>> {
+    let existing_groups: Vec<SecurityGroup> = describe_referenced_groups(client, document).await?;
+    // `DescribeSecurityGroupRules` has no `tag-key` filter, so any value is matched instead.
+    let temporary_rules: Vec<SecurityGroupRule> = get_security_group_rules(
+        client,
+        vec![AwsQueryType::Tag {
+            key: EXPIRES_AT_TAG_KEY.to_string(),
+            values: vec!["*".to_string()],
+        }],
+    )
+    .await?;
+    let mut plans: Vec<SecurityGroupPlan> = vec![];
+
+    for specification in &document.security_groups {
+        let existing: Option<&SecurityGroup> = find_group(
+            &existing_groups,
+            &specification.name,
+            specification.vpc_id.as_deref(),
+        )?;
+        let vpc_id: Option<&str> = specification
+            .vpc_id
+            .as_deref()
+            .or_else(|| existing.and_then(|group| group.vpc_id()));
+        let group_id: Option<&str> = existing.and_then(|group| group.group_id());
+        let mut changes: Vec<RuleChange> = vec![];
+
+        for (direction, rules) in [
+            (RuleDirection::Ingress, &specification.ingress),
+            (RuleDirection::Egress, &specification.egress),
+        ] {
+            let Some(rules) = rules else {
+                continue;
+            };
+            let desired: Vec<PermissionRule> =
+                expand_rules(rules, &existing_groups, document, vpc_id)?;
+            let actual: Vec<PermissionRule> = match (existing, direction) {
+                (Some(group), RuleDirection::Ingress) => {
+                    flatten_permissions(group.ip_permissions())
+                }
+                (Some(group), RuleDirection::Egress) => {
+                    flatten_permissions(group.ip_permissions_egress())
+                }
+                (None, _) => vec![],
+            };
+            for change in diff_rules(direction, &desired, &actual) {
+                match group_id.is_some_and(|group_id| {
+                    revokes_temporary_rule(&change, group_id, &temporary_rules)
+                }) {
+                    true => info!(
+                        "Leaving {} on {} to expire",
+                        change.rule(),
+                        specification.name
+                    ),
+                    false => changes.push(change),
+                }
+            }
+        }
+
+        plans.push(SecurityGroupPlan {
+            name: specification.name.clone(),
+            group_id: group_id.map(String::from),
+            changes,
+        });
+    }
+
+    Ok(plans)
+}
+
+pub fn log_security_group_plans(plans: &[SecurityGroupPlan]) {
+    for plan in plans {
+        match (&plan.group_id, plan.is_empty()) {
+            (Some(group_id), true) => info!("{} ({}) is up to date", plan.name, group_id),
+            (Some(group_id), false) => info!("{} ({}) will be updated", plan.name, group_id),
+            (None, _) => info!("{} will be created", plan.name),
+        }
+        for change in &plan.changes {
+            info!("    {}", change);
+        }
+    }
+}
+
+/// Creates missing groups, then authorizes missing rules before revoking stale ones, so that a
+/// replaced rule never leaves a window without access. Running it again right after finds
+/// nothing left to do.
+///
+/// In dry-run mode nothing is created, so rule changes for groups that don't exist yet are only
+/// logged.
+pub async fn apply_security_group_plans(
+    client: &aws_sdk_ec2::Client,
+    document: &SecurityGroupsDocument,
+    plans: Vec<SecurityGroupPlan>,
+    tag_policy: &TagPolicy,
+    mode: ExecutionMode,
+) -> Result<()> {
+    let mut created_groups: bool = false;
+    for plan in plans.iter().filter(|plan| plan.group_id.is_none()) {
+        let specification: &SecurityGroupSpecification = document
+            .security_groups
+            .iter()
+            .find(|specification| specification.name == plan.name)
+            .ok_or_else(|| anyhow!("Security group {} is not declared", plan.name))?;
+        let result: Option<CreateSecurityGroupOutput> = create_security_group(
+            client,
+            Some(specification.name.clone()),
+            Some(specification.description.clone()),
+            specification.vpc_id.clone(),
+            tag_policy,
+            mode,
+        )
+        .await?;
+        if let Some(result) = result {
+            info!(
+                "Created security group {} ({})",
+                specification.name,
+                result.group_id().unwrap_or_default()
+            );
+            created_groups = true;
+        }
+    }
+
+    // Rules referencing a group that was just created can only be expressed with its new ID.
+    let plans: Vec<SecurityGroupPlan> = match created_groups {
+        true => plan_security_groups(client, document).await?,
+        false => plans,
+    };
+
+    for plan in &plans {
+        let ordered_changes = plan
+            .changes
+            .iter()
+            .filter(|change| matches!(change, RuleChange::Authorize(..)))
+            .chain(
+                plan.changes
+                    .iter()
+                    .filter(|change| !matches!(change, RuleChange::Authorize(..))),
+            );
+        let group_id: &str = match (plan.group_id.as_deref(), mode) {
+            (Some(group_id), _) => group_id,
+            (None, ExecutionMode::DryRun) => {
+                for change in ordered_changes {
+                    log_unsent_mutation(
+                        &format!("{} (new group {})", change.operation(), plan.name),
+                        &change.rule().to_ip_permission(),
+                    );
+                }
+                continue;
+            }
+            (None, ExecutionMode::Apply) => {
+                return Err(anyhow!("Security group {} was not created", plan.name))
+            }
+        };
+        for change in ordered_changes {
+            if let Some(()) = apply_rule_change(client, group_id, change, mode).await? {
+                info!("{} ({}): {}", plan.name, group_id, change);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Sends the request for one rule change. Returns `None` when `mode` is a dry run.
+async fn apply_rule_change(
+    client: &aws_sdk_ec2::Client,
+    group_id: &str,
+    change: &RuleChange,
+    mode: ExecutionMode,
+) -> Result<Option<()>> {
+    let dry_run: bool = mode == ExecutionMode::DryRun;
+    let ip_permission: IpPermission = change.rule().to_ip_permission();
+    let operation: &str = change.operation();
+
+    let result: Option<()> = match change {
+        RuleChange::Authorize(RuleDirection::Ingress, _) => {
+            let query_builder = client
+                .authorize_security_group_ingress()
+                .group_id(group_id)
+                .ip_permissions(ip_permission)
+                .dry_run(dry_run);
+            send_mutation(
+                mode,
+                operation,
+                query_builder.as_input(),
+                query_builder.clone().send(),
+            )
+            .await?
+            .map(|_| ())
+        }
+        RuleChange::Authorize(RuleDirection::Egress, _) => {
+            let query_builder = client
+                .authorize_security_group_egress()
+                .group_id(group_id)
+                .ip_permissions(ip_permission)
+                .dry_run(dry_run);
+            send_mutation(
+                mode,
+                operation,
+                query_builder.as_input(),
+                query_builder.clone().send(),
+            )
+            .await?
+            .map(|_| ())
+        }
+        RuleChange::Revoke(RuleDirection::Ingress, _) => {
+            let query_builder = client
+                .revoke_security_group_ingress()
+                .group_id(group_id)
+                .ip_permissions(ip_permission)
+                .dry_run(dry_run);
+            send_mutation(
+                mode,
+                operation,
+                query_builder.as_input(),
+                query_builder.clone().send(),
+            )
+            .await?
+            .map(|_| ())
+        }
+        RuleChange::Revoke(RuleDirection::Egress, _) => {
+            let query_builder = client
+                .revoke_security_group_egress()
+                .group_id(group_id)
+                .ip_permissions(ip_permission)
+                .dry_run(dry_run);
+            send_mutation(
+                mode,
+                operation,
+                query_builder.as_input(),
+                query_builder.clone().send(),
+            )
+            .await?
+            .map(|_| ())
+        }
+        RuleChange::UpdateDescription(RuleDirection::Ingress, _) => {
+            let query_builder = client
+                .update_security_group_rule_descriptions_ingress()
+                .group_id(group_id)
+                .ip_permissions(ip_permission)
+                .dry_run(dry_run);
+            send_mutation(
+                mode,
+                operation,
+                query_builder.as_input(),
+                query_builder.clone().send(),
+            )
+            .await?
+            .map(|_| ())
+        }
+        RuleChange::UpdateDescription(RuleDirection::Egress, _) => {
+            let query_builder = client
+                .update_security_group_rule_descriptions_egress()
+                .group_id(group_id)
+                .ip_permissions(ip_permission)
+                .dry_run(dry_run);
+            send_mutation(
+                mode,
+                operation,
+                query_builder.as_input(),
+                query_builder.clone().send(),
+            )
+            .await?
+            .map(|_| ())
+        }
+    };
+
+    Ok(result)
+}
+
+/// Describes the declared groups and every group their rules reference by name.
+async fn describe_referenced_groups(
+    client: &aws_sdk_ec2::Client,
+    document: &SecurityGroupsDocument,
+) -> Result<Vec<SecurityGroup>> {
+    let mut names: Vec<String> = document
+        .security_groups
+        .iter()
+        .map(|specification| specification.name.clone())
+        .collect();
+    for specification in &document.security_groups {
+        let rules = specification
+            .ingress
+            .iter()
+            .chain(specification.egress.iter())
+            .flatten();
+        for rule in rules {
+            names.extend(
+                rule.security_groups
+                    .iter()
+                    .filter(|group| !is_security_group_id(group))
+                    .cloned(),
+            );
+        }
+    }
+    names.sort();
+    names.dedup();
+
+    // Names are matched with the `group-name` filter, which doesn't fail for names that don't
+    // exist yet.
+    get_security_groups(client, vec![AwsQueryType::Name(names)]).await
+}
+
+fn find_group<'a>(
+    groups: &'a [SecurityGroup],
+    name: &str,
+    vpc_id: Option<&str>,
+) -> Result<Option<&'a SecurityGroup>> {
+    let candidates: Vec<&SecurityGroup> = groups
+        .iter()
+        .filter(|group| group.group_name() == Some(name))
+        .filter(|group| vpc_id.is_none() || group.vpc_id() == vpc_id)
+        .collect();
+
+    match candidates.as_slice() {
+        [] => Ok(None),
+        [group] => Ok(Some(group)),
+        _ => Err(anyhow!(
+            "Security group name {} exists in several VPCs. Set vpc_id to pick one.",
+            name
+        )),
+    }
+}
+
+/// Expands specifications into one rule per source, resolving group names to IDs.
+fn expand_rules(
+    specifications: &[RuleSpecification],
+    existing_groups: &[SecurityGroup],
+    document: &SecurityGroupsDocument,
+    vpc_id: Option<&str>,
+) -> Result<Vec<PermissionRule>> {
+    let mut rules: Vec<PermissionRule> = vec![];
+
+    for specification in specifications {
+        let protocol: String = normalize_protocol(&specification.protocol);
+        let (from_port, to_port): (Option<i32>, Option<i32>) = match protocol.as_str() {
+            ALL_PROTOCOLS => (None, None),
+            "icmp" | "icmpv6" => (
+                Some(specification.from_port.unwrap_or(-1)),
+                Some(
+                    specification
+                        .to_port
+                        .or(specification.from_port)
+                        .unwrap_or(-1),
+                ),
+            ),
+            _ => {
+                let from_port: i32 = specification
+                    .from_port
+                    .ok_or_else(|| anyhow!("A {} rule needs from_port", specification.protocol))?;
+                (
+                    Some(from_port),
+                    Some(specification.to_port.unwrap_or(from_port)),
+                )
+            }
+        };
+
+        let mut sources: Vec<RuleSource> = vec![];
+        sources.extend(
+            specification
+                .cidr_ipv4
+                .iter()
+                .cloned()
+                .map(RuleSource::Ipv4),
+        );
+        sources.extend(
+            specification
+                .cidr_ipv6
+                .iter()
+                .cloned()
+                .map(RuleSource::Ipv6),
+        );
+        sources.extend(
+            specification
+                .prefix_lists
+                .iter()
+                .cloned()
+                .map(RuleSource::PrefixList),
+        );
+        for group in &specification.security_groups {
+            sources.push(RuleSource::SecurityGroup(resolve_group_reference(
+                group,
+                existing_groups,
+                document,
+                vpc_id,
+            )?));
+        }
+        if sources.is_empty() {
+            return Err(anyhow!(
+                "A {} rule has no CIDR, security group or prefix list",
+                specification.protocol
+            ));
+        }
+
+        rules.extend(sources.into_iter().map(|source| PermissionRule {
+            protocol: protocol.clone(),
+            from_port,
+            to_port,
+            source,
+            description: specification.description.clone(),
+        }));
+    }
+
+    Ok(rules)
+}
+
+/// The ID of the group `reference` names. A declared group that doesn't exist yet resolves to
+/// its name, which is only shown in plans: applying creates it and plans again.
+fn resolve_group_reference(
+    reference: &str,
+    existing_groups: &[SecurityGroup],
+    document: &SecurityGroupsDocument,
+    vpc_id: Option<&str>,
+) -> Result<String> {
+    if is_security_group_id(reference) {
+        return Ok(reference.to_string());
+    }
+
+    match find_group(existing_groups, reference, vpc_id)?.and_then(|group| group.group_id()) {
+        Some(group_id) => Ok(group_id.to_string()),
+        None if document
+            .security_groups
+            .iter()
+            .any(|specification| specification.name == reference) =>
+        {
+            Ok(reference.to_string())
+        }
+        None => Err(anyhow!(
+            "Referenced security group not found: {}",
+            reference
+        )),
+    }
+}
+
+/// Splits AWS permissions, which group several sources per protocol and port range, into one
+/// rule per source.
+fn flatten_permissions(permissions: &[IpPermission]) -> Vec<PermissionRule> {
+    let mut rules: Vec<PermissionRule> = vec![];
+
+    for permission in permissions {
+        let protocol: String =
+            normalize_protocol(permission.ip_protocol().unwrap_or(ALL_PROTOCOLS));
+        let (from_port, to_port): (Option<i32>, Option<i32>) = match protocol.as_str() {
+            ALL_PROTOCOLS => (None, None),
+            _ => (permission.from_port(), permission.to_port()),
+        };
+        let mut push = |source: RuleSource, description: Option<&str>| {
+            rules.push(PermissionRule {
+                protocol: protocol.clone(),
+                from_port,
+                to_port,
+                source,
+                description: description.map(String::from),
+            })
+        };
+
+        for range in permission.ip_ranges() {
+            if let Some(cidr) = range.cidr_ip() {
+                push(RuleSource::Ipv4(cidr.to_string()), range.description());
+            }
+        }
+        for range in permission.ipv6_ranges() {
+            if let Some(cidr) = range.cidr_ipv6() {
+                push(RuleSource::Ipv6(cidr.to_string()), range.description());
+            }
+        }
+        for pair in permission.user_id_group_pairs() {
+            if let Some(group_id) = pair.group_id() {
+                push(
+                    RuleSource::SecurityGroup(group_id.to_string()),
+                    pair.description(),
+                );
+            }
+        }
+        for prefix_list in permission.prefix_list_ids() {
+            if let Some(prefix_list_id) = prefix_list.prefix_list_id() {
+                push(
+                    RuleSource::PrefixList(prefix_list_id.to_string()),
+                    prefix_list.description(),
+                );
+            }
+        }
+    }
+
+    rules
+}
+
+/// Whether `change` revokes an ingress rule of `group_id` that is one of `temporary_rules`.
+fn revokes_temporary_rule(
+    change: &RuleChange,
+    group_id: &str,
+    temporary_rules: &[SecurityGroupRule],
+) -> bool {
+    let RuleChange::Revoke(RuleDirection::Ingress, rule) = change else {
+        return false;
+    };
+    let RuleSource::Ipv4(cidr) = &rule.source else {
+        return false;
+    };
+
+    temporary_rules.iter().any(|temporary| {
+        temporary.group_id() == Some(group_id)
+            && temporary.is_egress() == Some(false)
+            && temporary.cidr_ipv4() == Some(cidr.as_str())
+            && normalize_protocol(temporary.ip_protocol().unwrap_or(ALL_PROTOCOLS)) == rule.protocol
+            && (rule.protocol == ALL_PROTOCOLS
+                || (temporary.from_port() == rule.from_port && temporary.to_port() == rule.to_port))
+    })
+}
+
+fn diff_rules(
+    direction: RuleDirection,
+    desired: &[PermissionRule],
+    actual: &[PermissionRule],
+) -> Vec<RuleChange> {
+    let actual_by_key: HashMap<_, &PermissionRule> =
+        actual.iter().map(|rule| (rule.key(), rule)).collect();
+    let desired_by_key: HashMap<_, &PermissionRule> =
+        desired.iter().map(|rule| (rule.key(), rule)).collect();
+    let mut changes: Vec<RuleChange> = vec![];
+    let mut planned = HashSet::new();
+
+    for rule in desired {
+        // The same rule may be declared twice, and AWS rejects authorizing a duplicate.
+        if !planned.insert(rule.key()) {
+            continue;
+        }
+        match actual_by_key.get(&rule.key()) {
+            None => changes.push(RuleChange::Authorize(direction, rule.clone())),
+            Some(existing) if existing.description != rule.description => {
+                changes.push(RuleChange::UpdateDescription(direction, rule.clone()))
+            }
+            Some(_) => (),
+        }
+    }
+    for rule in actual {
+        if !desired_by_key.contains_key(&rule.key()) {
+            changes.push(RuleChange::Revoke(direction, rule.clone()));
+        }
+    }
+
+    changes
+}
+
+/// Maps the protocol names and numbers AWS accepts to the form `describe_security_groups`
+/// reports.
+fn normalize_protocol(protocol: &str) -> String {
+    match protocol.to_ascii_lowercase().as_str() {
+        "6" => String::from("tcp"),
+        "17" => String::from("udp"),
+        "1" => String::from("icmp"),
+        "58" => String::from("icmpv6"),
+        "all" => ALL_PROTOCOLS.to_string(),
+        protocol => protocol.to_string(),
+    }
+}
+
+pub fn is_security_group_id(reference: &str) -> bool {
+    reference.starts_with("sg-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document() -> SecurityGroupsDocument {
+        SecurityGroupsDocument {
+            security_groups: vec![],
+        }
+    }
+
+    fn https_from(cidr_ipv4: &[&str], description: Option<&str>) -> RuleSpecification {
+        RuleSpecification {
+            protocol: String::from("tcp"),
+            from_port: Some(443),
+            to_port: None,
+            cidr_ipv4: cidr_ipv4.iter().map(|cidr| cidr.to_string()).collect(),
+            cidr_ipv6: vec![],
+            security_groups: vec![],
+            prefix_lists: vec![],
+            description: description.map(String::from),
+        }
+    }
+
+    fn expand(specifications: &[RuleSpecification]) -> Vec<PermissionRule> {
+        expand_rules(specifications, &[], &document(), None).unwrap()
+    }
+
+    /// An HTTPS permission as `describe_security_groups` reports it, with every CIDR in one
+    /// permission.
+    fn https_permission(protocol: &str, cidrs: &[(&str, Option<&str>)]) -> IpPermission {
+        cidrs
+            .iter()
+            .fold(
+                IpPermission::builder()
+                    .ip_protocol(protocol)
+                    .from_port(443)
+                    .to_port(443),
+                |builder, (cidr, description)| {
+                    builder.ip_ranges(
+                        IpRange::builder()
+                            .cidr_ip(*cidr)
+                            .set_description(description.map(String::from))
+                            .build(),
+                    )
+                },
+            )
+            .build()
+    }
+
+    #[test]
+    fn matching_rules_need_no_changes() {
+        let desired: Vec<PermissionRule> = expand(&[https_from(
+            &["10.0.0.0/16", "192.168.1.0/24"],
+            Some("Office"),
+        )]);
+        let actual: Vec<PermissionRule> = flatten_permissions(&[https_permission(
+            "tcp",
+            &[
+                ("192.168.1.0/24", Some("Office")),
+                ("10.0.0.0/16", Some("Office")),
+            ],
+        )]);
+
+        assert_eq!(actual.len(), 2);
+        assert!(diff_rules(RuleDirection::Ingress, &desired, &actual).is_empty());
+    }
+
+    #[test]
+    fn changed_description_is_updated_in_place() {
+        let desired: Vec<PermissionRule> = expand(&[https_from(&["10.0.0.0/16"], Some("VPN"))]);
+        let actual: Vec<PermissionRule> =
+            flatten_permissions(&[https_permission("tcp", &[("10.0.0.0/16", Some("Office"))])]);
+
+        let changes: Vec<RuleChange> = diff_rules(RuleDirection::Ingress, &desired, &actual);
+
+        assert_eq!(changes.len(), 1);
+        assert!(matches!(
+            &changes[0],
+            RuleChange::UpdateDescription(RuleDirection::Ingress, rule)
+                if rule.description.as_deref() == Some("VPN")
+        ));
+        assert_eq!(
+            changes[0].operation(),
+            "UpdateSecurityGroupRuleDescriptionsIngress"
+        );
+    }
+
+    #[test]
+    fn missing_rules_are_authorized_and_stale_rules_revoked() {
+        let desired: Vec<PermissionRule> = expand(&[https_from(&["10.0.0.0/16"], None)]);
+        let actual: Vec<PermissionRule> =
+            flatten_permissions(&[https_permission("tcp", &[("0.0.0.0/0", None)])]);
+
+        let changes: Vec<RuleChange> = diff_rules(RuleDirection::Egress, &desired, &actual);
+
+        assert_eq!(changes.len(), 2);
+        assert!(matches!(
+            &changes[0],
+            RuleChange::Authorize(RuleDirection::Egress, rule)
+                if rule.source == RuleSource::Ipv4(String::from("10.0.0.0/16"))
+        ));
+        assert!(matches!(
+            &changes[1],
+            RuleChange::Revoke(RuleDirection::Egress, rule)
+                if rule.source == RuleSource::Ipv4(String::from("0.0.0.0/0"))
+        ));
+    }
+
+    #[test]
+    fn normalize_protocol_uses_the_names_aws_reports() {
+        assert_eq!(normalize_protocol("6"), "tcp");
+        assert_eq!(normalize_protocol("TCP"), "tcp");
+        assert_eq!(normalize_protocol("17"), "udp");
+        assert_eq!(normalize_protocol("1"), "icmp");
+        assert_eq!(normalize_protocol("58"), "icmpv6");
+        assert_eq!(normalize_protocol("all"), "-1");
+        assert_eq!(normalize_protocol("-1"), "-1");
+        assert_eq!(normalize_protocol("50"), "50");
+    }
+
+    #[test]
+    fn protocol_numbers_match_protocol_names() {
+        let mut specification: RuleSpecification = https_from(&["10.0.0.0/16"], None);
+        specification.protocol = String::from("6");
+        let desired: Vec<PermissionRule> = expand(&[specification]);
+        let actual: Vec<PermissionRule> =
+            flatten_permissions(&[https_permission("tcp", &[("10.0.0.0/16", None)])]);
+
+        assert!(diff_rules(RuleDirection::Ingress, &desired, &actual).is_empty());
+    }
+
+    #[test]
+    fn rule_declared_twice_is_authorized_once() {
+        let desired: Vec<PermissionRule> = expand(&[
+            https_from(&["10.0.0.0/16"], None),
+            https_from(&["10.0.0.0/16"], None),
+        ]);
+
+        let changes: Vec<RuleChange> = diff_rules(RuleDirection::Ingress, &desired, &[]);
+
+        assert_eq!(desired.len(), 2);
+        assert_eq!(changes.len(), 1);
+        assert!(matches!(changes[0], RuleChange::Authorize(..)));
+    }
+
+    #[test]
+    fn all_traffic_rules_ignore_ports() {
+        let specification: RuleSpecification = RuleSpecification {
+            protocol: String::from("all"),
+            from_port: Some(0),
+            to_port: Some(65535),
+            ..https_from(&["10.0.0.0/16"], None)
+        };
+        let desired: Vec<PermissionRule> = expand(&[specification]);
+        let actual: Vec<PermissionRule> = flatten_permissions(&[IpPermission::builder()
+            .ip_protocol("-1")
+            .from_port(-1)
+            .to_port(-1)
+            .ip_ranges(IpRange::builder().cidr_ip("10.0.0.0/16").build())
+            .build()]);
+
+        assert_eq!(desired[0].from_port, None);
+        assert_eq!(actual[0].from_port, None);
+        assert!(diff_rules(RuleDirection::Egress, &desired, &actual).is_empty());
+    }
+
+    #[test]
+    fn only_revocations_of_temporary_ingress_rules_are_recognized() {
+        let temporary_rules: Vec<SecurityGroupRule> = vec![SecurityGroupRule::builder()
+            .group_id("sg-0123456789abcdef0")
+            .is_egress(false)
+            .ip_protocol("tcp")
+            .from_port(22)
+            .to_port(22)
+            .cidr_ipv4("203.0.113.7/32")
+            .build()];
+        let rule = |protocol: &str, port: i32, cidr: &str| PermissionRule {
+            protocol: protocol.to_string(),
+            from_port: Some(port),
+            to_port: Some(port),
+            source: RuleSource::Ipv4(cidr.to_string()),
+            description: None,
+        };
+        let revokes_temporary_rule = |change: RuleChange, group_id: &str| {
+            revokes_temporary_rule(&change, group_id, &temporary_rules)
+        };
+
+        assert!(revokes_temporary_rule(
+            RuleChange::Revoke(RuleDirection::Ingress, rule("tcp", 22, "203.0.113.7/32")),
+            "sg-0123456789abcdef0"
+        ));
+        assert!(!revokes_temporary_rule(
+            RuleChange::Revoke(RuleDirection::Ingress, rule("tcp", 22, "203.0.113.7/32")),
+            "sg-0fedcba9876543210"
+        ));
+        assert!(!revokes_temporary_rule(
+            RuleChange::Revoke(RuleDirection::Ingress, rule("tcp", 443, "203.0.113.7/32")),
+            "sg-0123456789abcdef0"
+        ));
+        assert!(!revokes_temporary_rule(
+            RuleChange::Revoke(RuleDirection::Ingress, rule("tcp", 22, "198.51.100.0/24")),
+            "sg-0123456789abcdef0"
+        ));
+        assert!(!revokes_temporary_rule(
+            RuleChange::Revoke(RuleDirection::Egress, rule("tcp", 22, "203.0.113.7/32")),
+            "sg-0123456789abcdef0"
+        ));
+        assert!(!revokes_temporary_rule(
+            RuleChange::Authorize(RuleDirection::Ingress, rule("tcp", 22, "203.0.113.7/32")),
+            "sg-0123456789abcdef0"
+        ));
+    }
+
+    #[test]
+    fn expand_rules_creates_one_rule_per_source() {
+        let specification: RuleSpecification = RuleSpecification {
+            cidr_ipv6: vec![String::from("::/0")],
+            security_groups: vec![String::from("sg-0123456789abcdef0")],
+            prefix_lists: vec![String::from("pl-12345678")],
+            ..https_from(&["0.0.0.0/0"], None)
+        };
+
+        let rules: Vec<PermissionRule> = expand(&[specification]);
+
+        assert_eq!(
+            rules.iter().map(|rule| &rule.source).collect::<Vec<_>>(),
+            vec![
+                &RuleSource::Ipv4(String::from("0.0.0.0/0")),
+                &RuleSource::Ipv6(String::from("::/0")),
+                &RuleSource::PrefixList(String::from("pl-12345678")),
+                &RuleSource::SecurityGroup(String::from("sg-0123456789abcdef0")),
+            ]
+        );
+        assert!(rules
+            .iter()
+            .all(|rule| rule.from_port == Some(443) && rule.to_port == Some(443)));
+    }
+
+    #[test]
+    fn expand_rules_rejects_unknown_group_names_and_missing_sources() {
+        let unknown_group: RuleSpecification = RuleSpecification {
+            security_groups: vec![String::from("Missing")],
+            ..https_from(&[], None)
+        };
+
+        assert!(expand_rules(&[unknown_group], &[], &document(), None).is_err());
+        assert!(expand_rules(&[https_from(&[], None)], &[], &document(), None).is_err());
+    }
+}
@@ -0,0 +1,498 @@
+use std::{collections::BTreeMap, fmt};
+
+use anyhow::{anyhow, Result};
+use aws_sdk_ec2::types::{ResourceType, Tag, TagSpecification};
+use log::{info, warn};
+
+use crate::{
+    amazon_web_services::ec2::{
+        self, create_tags, get_ec2_launch_templates, get_instances, get_security_groups,
+        AwsQueryType, ExecutionMode,
+    },
+    configuration::{constants::amazon_web_services::NAME_TAG_KEY, Configuration},
+};
+
+/// Tags every EC2 resource lorerunner creates must carry, from `aws_required_tags`.
+///
+/// A tag with an empty value is required but can't be applied automatically. The audit reports
+/// resources without it, which have to be tagged by hand or launched with the tag given.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TagPolicy {
+    tags: BTreeMap<String, String>,
+}
+
+impl TagPolicy {
+    pub fn new(tags: BTreeMap<String, String>) -> Self {
+        TagPolicy { tags }
+    }
+
+    pub fn from_configuration(configuration: &Configuration) -> Self {
+        TagPolicy::new(configuration.aws_required_tags.clone())
+    }
+
+    /// The policy's tags with values, overridden and extended by `tags`.
+    pub fn merge(&self, tags: &[(String, String)]) -> Vec<(String, String)> {
+        let mut merged: BTreeMap<String, String> = self
+            .tags
+            .iter()
+            .filter(|(_, value)| !value.is_empty())
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        merged.extend(tags.iter().cloned());
+        merged.into_iter().collect()
+    }
+
+    /// `TagSpecifications` applying the merged tags to each of `resource_types` at creation.
+    pub fn tag_specifications(
+        &self,
+        resource_types: &[ResourceType],
+        tags: &[(String, String)],
+    ) -> Option<Vec<TagSpecification>> {
+        let tags: Vec<Tag> = self
+            .merge(tags)
+            .into_iter()
+            .map(|(key, value)| Tag::builder().key(key).value(value).build())
+            .collect();
+        match tags.is_empty() {
+            true => None,
+            false => Some(
+                resource_types
+                    .iter()
+                    .map(|resource_type| {
+                        TagSpecification::builder()
+                            .resource_type(resource_type.clone())
+                            .set_tags(Some(tags.clone()))
+                            .build()
+                    })
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Keys of required tags `tags` doesn't have. A tag with a different value isn't missing; it
+    /// may mean something else owns the resource.
+    pub fn missing_keys(&self, tags: &[Tag]) -> Vec<String> {
+        self.tags
+            .keys()
+            .filter(|key| !tags.iter().any(|tag| tag.key() == Some(key.as_str())))
+            .cloned()
+            .collect()
+    }
+
+    fn value(&self, key: &str) -> Option<&str> {
+        self.tags
+            .get(key)
+            .map(String::as_str)
+            .filter(|value| !value.is_empty())
+    }
+}
+
+/// EC2 resource types the tag audit covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
+pub enum TaggedResourceType {
+    Instance,
+    Volume,
+    SecurityGroup,
+    LaunchTemplate,
+}
+
+impl TaggedResourceType {
+    pub const ALL: [TaggedResourceType; 4] = [
+        TaggedResourceType::Instance,
+        TaggedResourceType::Volume,
+        TaggedResourceType::SecurityGroup,
+        TaggedResourceType::LaunchTemplate,
+    ];
+
+    /// What the IDs of resources of this type start with.
+    pub fn id_prefix(&self) -> &'static str {
+        match self {
+            TaggedResourceType::Instance => "i-",
+            TaggedResourceType::Volume => "vol-",
+            TaggedResourceType::SecurityGroup => "sg-",
+            TaggedResourceType::LaunchTemplate => "lt-",
+        }
+    }
+}
+
+impl fmt::Display for TaggedResourceType {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TaggedResourceType::Instance => write!(formatter, "instance"),
+            TaggedResourceType::Volume => write!(formatter, "volume"),
+            TaggedResourceType::SecurityGroup => write!(formatter, "security group"),
+            TaggedResourceType::LaunchTemplate => write!(formatter, "launch template"),
+        }
+    }
+}
+
+/// A resource missing at least one required tag.
+#[derive(Debug, Clone)]
+pub struct TagAuditFinding {
+    pub resource_type: TaggedResourceType,
+    pub resource_id: String,
+    /// Value of the `Name` tag, or the group or template name.
+    pub name: Option<String>,
+    pub missing_keys: Vec<String>,
+}
+
+impl fmt::Display for TagAuditFinding {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            formatter,
+            "{} {} ({}) is missing {}",
+            self.resource_type,
+            self.resource_id,
+            self.name.as_deref().unwrap_or("unnamed"),
+            self.missing_keys.join(", ")
+        )
+    }
+}
+
+/// Lists resources of `resource_types` matching `query` that lack any tag `policy` requires.
+/// Terminated instances and default security groups are skipped.
+///
+/// IDs are only looked up as the type their prefix names. Raw filters only apply to one type,
+/// so a query with any needs exactly one resource type.
+pub async fn audit_tags(
+    client: &aws_sdk_ec2::Client,
+    policy: &TagPolicy,
+    resource_types: &[TaggedResourceType],
+    query: Vec<AwsQueryType>,
+) -> Result<Vec<TagAuditFinding>> {
+    if resource_types.len() != 1
+        && query
+            .iter()
+            .any(|criterion| matches!(criterion, AwsQueryType::Filter(_)))
+    {
+        return Err(anyhow!(
+            "Filters only apply to one resource type. Pass exactly one --type with --filter."
+        ));
+    }
+    let mut findings: Vec<TagAuditFinding> = vec![];
+
+    for resource_type in resource_types {
+        let Some(query) = resource_query(*resource_type, &query) else {
+            continue;
+        };
+        let resources: Vec<(String, Option<String>, Vec<Tag>)> = match resource_type {
+            TaggedResourceType::Instance => get_instances(client, query.clone())
+                .await?
+                .into_iter()
+                .filter(|instance| {
+                    instance.state().and_then(|state| state.name())
+                        != Some(&aws_sdk_ec2::types::InstanceStateName::Terminated)
+                })
+                .map(|instance| {
+                    (
+                        instance.instance_id().unwrap_or_default().to_string(),
+                        name_tag(instance.tags()),
+                        instance.tags().to_vec(),
+                    )
+                })
+                .collect(),
+            TaggedResourceType::Volume => ec2::get_volumes(client, query.clone())
+                .await?
+                .into_iter()
+                .map(|volume| {
+                    (
+                        volume.volume_id().unwrap_or_default().to_string(),
+                        name_tag(volume.tags()),
+                        volume.tags().to_vec(),
+                    )
+                })
+                .collect(),
+            TaggedResourceType::SecurityGroup => get_security_groups(client, query.clone())
+                .await?
+                .into_iter()
+                .filter(|group| group.group_name() != Some("default"))
+                .map(|group| {
+                    (
+                        group.group_id().unwrap_or_default().to_string(),
+                        group.group_name().map(String::from),
+                        group.tags().to_vec(),
+                    )
+                })
+                .collect(),
+            TaggedResourceType::LaunchTemplate => get_ec2_launch_templates(client, query.clone())
+                .await?
+                .into_iter()
+                .map(|template| {
+                    (
+                        template
+                            .launch_template_id()
+                            .unwrap_or_default()
+                            .to_string(),
+                        template.launch_template_name().map(String::from),
+                        template.tags().to_vec(),
+                    )
+                })
+                .collect(),
+        };
+
+        for (resource_id, name, tags) in resources {
+            let missing_keys: Vec<String> = policy.missing_keys(&tags);
+            if !missing_keys.is_empty() {
+                findings.push(TagAuditFinding {
+                    resource_type: *resource_type,
+                    resource_id,
+                    name,
+                    missing_keys,
+                });
+            }
+        }
+    }
+
+    Ok(findings)
+}
+
+/// Adds the missing tags to every finding's resource, using the policy's values. Required tags
+/// without a value are skipped with a warning. Returns how many resources were tagged.
+pub async fn backfill_tags(
+    client: &aws_sdk_ec2::Client,
+    policy: &TagPolicy,
+    findings: &[TagAuditFinding],
+    mode: ExecutionMode,
+) -> Result<usize> {
+    let mut tagged: usize = 0;
+
+    for finding in findings {
+        let (fillable, unfillable): (Vec<&String>, Vec<&String>) = finding
+            .missing_keys
+            .iter()
+            .partition(|key| policy.value(key).is_some());
+        if !unfillable.is_empty() {
+            warn!(
+                "{} {} needs a value for {} to be tagged by hand",
+                finding.resource_type,
+                finding.resource_id,
+                unfillable
+                    .iter()
+                    .map(|key| key.as_str())
+                    .collect::<Vec<&str>>()
+                    .join(", ")
+            );
+        }
+        if fillable.is_empty() {
+            continue;
+        }
+
+        let tags: Vec<(String, String)> = fillable
+            .into_iter()
+            .map(|key| {
+                (
+                    key.clone(),
+                    policy.value(key).unwrap_or_default().to_string(),
+                )
+            })
+            .collect();
+        let result = create_tags(
+            client,
+            vec![finding.resource_id.clone()],
+            tags.clone(),
+            mode,
+        )
+        .await?;
+        if result.is_some() {
+            info!(
+                "Tagged {} {} with {}",
+                finding.resource_type,
+                finding.resource_id,
+                tags.iter()
+                    .map(|(key, value)| format!("{}={}", key, value))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            );
+            tagged += 1;
+        }
+    }
+
+    Ok(tagged)
+}
+
+/// `query` narrowed to the IDs of `resource_type`, or `None` when it names IDs of other types
+/// only and no resource of this type can match.
+fn resource_query(
+    resource_type: TaggedResourceType,
+    query: &[AwsQueryType],
+) -> Option<Vec<AwsQueryType>> {
+    let mut resource_query: Vec<AwsQueryType> = vec![];
+
+    for criterion in query {
+        match criterion {
+            AwsQueryType::Id(ids) => {
+                let ids: Vec<String> = ids
+                    .iter()
+                    .filter(|id| id.starts_with(resource_type.id_prefix()))
+                    .cloned()
+                    .collect();
+                // An empty ID list would describe every resource of the type instead of none.
+                if ids.is_empty() {
+                    return None;
+                }
+                resource_query.push(AwsQueryType::Id(ids));
+            }
+            criterion => resource_query.push(criterion.clone()),
+        }
+    }
+
+    Some(resource_query)
+}
+
+fn name_tag(tags: &[Tag]) -> Option<String> {
+    tags.iter()
+        .find(|tag| tag.key() == Some(NAME_TAG_KEY))
+        .and_then(|tag| tag.value())
+        .map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> TagPolicy {
+        TagPolicy::new(BTreeMap::from([
+            (String::from("Environment"), String::from("production")),
+            (String::from("Owner"), String::new()),
+            (String::from("Team"), String::from("platform")),
+        ]))
+    }
+
+    fn tag(key: &str, value: &str) -> Tag {
+        Tag::builder().key(key).value(value).build()
+    }
+
+    fn pair(key: &str, value: &str) -> (String, String) {
+        (key.to_string(), value.to_string())
+    }
+
+    #[test]
+    fn merge_skips_tags_without_values_and_lets_given_tags_win() {
+        let merged: Vec<(String, String)> = policy().merge(&[
+            pair("Team", "data"),
+            pair("Owner", "alex"),
+            pair("Name", "web"),
+        ]);
+
+        assert_eq!(
+            merged,
+            vec![
+                pair("Environment", "production"),
+                pair("Name", "web"),
+                pair("Owner", "alex"),
+                pair("Team", "data"),
+            ]
+        );
+        assert_eq!(
+            policy().merge(&[]),
+            vec![pair("Environment", "production"), pair("Team", "platform")]
+        );
+    }
+
+    #[test]
+    fn tag_specifications_cover_every_resource_type() {
+        let specifications: Vec<TagSpecification> = policy()
+            .tag_specifications(
+                &[ResourceType::Instance, ResourceType::Volume],
+                &[pair("Name", "web")],
+            )
+            .unwrap();
+
+        assert_eq!(
+            specifications
+                .iter()
+                .map(|specification| specification.resource_type().cloned())
+                .collect::<Vec<_>>(),
+            vec![Some(ResourceType::Instance), Some(ResourceType::Volume)]
+        );
+        for specification in &specifications {
+            assert_eq!(
+                specification.tags(),
+                &[
+                    tag("Environment", "production"),
+                    tag("Name", "web"),
+                    tag("Team", "platform"),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn tag_specifications_are_omitted_without_tags() {
+        assert!(TagPolicy::default()
+            .tag_specifications(&[ResourceType::Instance], &[])
+            .is_none());
+        let unvalued: TagPolicy =
+            TagPolicy::new(BTreeMap::from([(String::from("Owner"), String::new())]));
+        assert!(unvalued
+            .tag_specifications(&[ResourceType::Instance], &[])
+            .is_none());
+    }
+
+    fn ids(query: Option<Vec<AwsQueryType>>) -> Option<Vec<String>> {
+        query.map(|query| {
+            query
+                .into_iter()
+                .flat_map(|criterion| match criterion {
+                    AwsQueryType::Id(ids) => ids,
+                    _ => vec![],
+                })
+                .collect()
+        })
+    }
+
+    #[test]
+    fn ids_reach_only_the_describe_call_of_their_type() {
+        let query: Vec<AwsQueryType> = vec![
+            AwsQueryType::Id(vec![
+                String::from("i-0123456789abcdef0"),
+                String::from("vol-0123456789abcdef0"),
+            ]),
+            AwsQueryType::Tag {
+                key: String::from("Team"),
+                values: vec![],
+            },
+        ];
+
+        assert_eq!(
+            ids(resource_query(TaggedResourceType::Instance, &query)),
+            Some(vec![String::from("i-0123456789abcdef0")])
+        );
+        assert_eq!(
+            ids(resource_query(TaggedResourceType::Volume, &query)),
+            Some(vec![String::from("vol-0123456789abcdef0")])
+        );
+        assert_eq!(
+            resource_query(TaggedResourceType::SecurityGroup, &query),
+            None
+        );
+        assert_eq!(
+            resource_query(TaggedResourceType::LaunchTemplate, &query),
+            None
+        );
+    }
+
+    #[test]
+    fn queries_without_ids_reach_every_type() {
+        let query: Vec<AwsQueryType> = vec![AwsQueryType::Name(vec![String::from("web")])];
+
+        for resource_type in TaggedResourceType::ALL {
+            assert_eq!(resource_query(resource_type, &query), Some(query.clone()));
+        }
+    }
+
+    #[test]
+    fn missing_keys_ignore_values() {
+        let tags: Vec<Tag> = vec![tag("Environment", "staging"), tag("Name", "web")];
+
+        assert_eq!(policy().missing_keys(&tags), vec!["Owner", "Team"]);
+        assert!(policy()
+            .missing_keys(&[
+                tag("Environment", "production"),
+                tag("Owner", "alex"),
+                tag("Team", "platform"),
+            ])
+            .is_empty());
+        assert!(TagPolicy::default().missing_keys(&[]).is_empty());
+    }
+}
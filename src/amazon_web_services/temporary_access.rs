@@ -0,0 +1,349 @@
+use std::net::{IpAddr, Ipv4Addr};
+
+use anyhow::{anyhow, Result};
+use aws_sdk_ec2::{
+    operation::authorize_security_group_ingress::AuthorizeSecurityGroupIngressOutput,
+    types::{Filter, ResourceType, SecurityGroupRule, TagSpecification},
+};
+use log::{info, warn};
+use time::{format_description::well_known::Rfc3339, Duration, OffsetDateTime};
+
+use crate::{
+    amazon_web_services::{
+        ec2::{
+            add_security_group_ingress, create_tags, get_security_group_rules,
+            revoke_security_group_ingress_rules, AwsQueryType, ExecutionMode,
+        },
+        tagging::TagPolicy,
+    },
+    configuration::constants::amazon_web_services::EXPIRES_AT_TAG_KEY,
+    http::{HttpRequest, HttpResponse, HttpTransport},
+};
+
+/// Finds the public address this machine's traffic reaches AWS from.
+pub trait PublicIpResolver {
+    fn resolve(&self) -> Result<IpAddr>;
+}
+
+/// Asks an echo service that answers with the caller's address as plain text, such as
+/// `https://checkip.amazonaws.com`.
+pub struct HttpPublicIpResolver<T: HttpTransport> {
+    transport: T,
+    url: String,
+}
+
+impl<T: HttpTransport> HttpPublicIpResolver<T> {
+    pub fn new(transport: T, url: &str) -> Self {
+        HttpPublicIpResolver {
+            transport,
+            url: url.to_string(),
+        }
+    }
+}
+
+impl<T: HttpTransport> PublicIpResolver for HttpPublicIpResolver<T> {
+    fn resolve(&self) -> Result<IpAddr> {
+        let response: HttpResponse = self
+            .transport
+            .send(&HttpRequest::new("GET", &self.url))
+            .map_err(|error| anyhow!("Failed to reach {}: {}", self.url, error))?;
+        if !(200..300).contains(&response.status) {
+            return Err(anyhow!(
+                "{} answered {} {}",
+                self.url,
+                response.status,
+                response.status_text
+            ));
+        }
+        let body: String = String::from_utf8_lossy(&response.body).trim().to_string();
+
+        body.parse::<IpAddr>()
+            .map_err(|_| anyhow!("{} answered {:?}, not an IP address", self.url, body))
+    }
+}
+
+/// Always answers the same address. Used when the address is given on the command line.
+pub struct StaticPublicIpResolver(pub IpAddr);
+
+impl PublicIpResolver for StaticPublicIpResolver {
+    fn resolve(&self) -> Result<IpAddr> {
+        Ok(self.0)
+    }
+}
+
+/// An inbound rule opened to one address until `expires_at`.
+#[derive(Debug, Clone)]
+pub struct TemporaryIngress {
+    pub group_id: String,
+    pub protocol: String,
+    pub from_port: i32,
+    pub to_port: i32,
+    pub duration: Duration,
+}
+
+/// Opens `ingress` to the address `resolver` finds, tagging the rule with when it expires.
+/// When the same rule already exists, its expiry is moved instead. Returns the rule's ID, or
+/// `None` in dry-run mode.
+pub async fn grant_temporary_ingress(
+    client: &aws_sdk_ec2::Client,
+    resolver: &dyn PublicIpResolver,
+    ingress: &TemporaryIngress,
+    tag_policy: &TagPolicy,
+    mode: ExecutionMode,
+) -> Result<Option<String>> {
+    let cidr_ip: String = ingress_cidr(resolver.resolve()?)?;
+    let expires_at: String = (OffsetDateTime::now_utc() + ingress.duration)
+        .replace_nanosecond(0)?
+        .format(&Rfc3339)?;
+
+    let existing: Option<SecurityGroupRule> = get_security_group_rules(
+        client,
+        vec![AwsQueryType::Filter(
+            Filter::builder()
+                .name("group-id")
+                .values(&ingress.group_id)
+                .build(),
+        )],
+    )
+    .await?
+    .into_iter()
+    .find(|rule| {
+        rule.is_egress() == Some(false)
+            && rule.cidr_ipv4() == Some(cidr_ip.as_str())
+            && rule.ip_protocol() == Some(ingress.protocol.as_str())
+            && rule.from_port() == Some(ingress.from_port)
+            && rule.to_port() == Some(ingress.to_port)
+    });
+
+    if let Some(rule) = existing {
+        let rule_id: String = rule
+            .security_group_rule_id()
+            .unwrap_or_default()
+            .to_string();
+        // A permanent rule that happens to match must not start expiring.
+        if !rule
+            .tags()
+            .iter()
+            .any(|tag| tag.key() == Some(EXPIRES_AT_TAG_KEY))
+        {
+            info!(
+                "{} already allows {} {}-{} from {} permanently",
+                ingress.group_id, ingress.protocol, ingress.from_port, ingress.to_port, cidr_ip
+            );
+            return Ok(Some(rule_id));
+        }
+        let result = create_tags(
+            client,
+            vec![rule_id.clone()],
+            vec![(EXPIRES_AT_TAG_KEY.to_string(), expires_at.clone())],
+            mode,
+        )
+        .await?;
+        if result.is_none() {
+            return Ok(None);
+        }
+        info!(
+            "Extended {} on {} for {} until {}",
+            rule_id, ingress.group_id, cidr_ip, expires_at
+        );
+        return Ok(Some(rule_id));
+    }
+
+    let tag_specifications: Option<Vec<TagSpecification>> = tag_policy.tag_specifications(
+        &[ResourceType::SecurityGroupRule],
+        &[(EXPIRES_AT_TAG_KEY.to_string(), expires_at.clone())],
+    );
+    let result: Option<AuthorizeSecurityGroupIngressOutput> = add_security_group_ingress(
+        client,
+        Some(ingress.group_id.clone()),
+        Some(cidr_ip.clone()),
+        Some(ingress.protocol.clone()),
+        Some(ingress.from_port),
+        Some(ingress.to_port),
+        tag_specifications,
+        mode,
+    )
+    .await?;
+
+    let rule_id: Option<String> = result.and_then(|result| {
+        result
+            .security_group_rules()
+            .first()
+            .and_then(|rule| rule.security_group_rule_id())
+            .map(String::from)
+    });
+    if let Some(rule_id) = &rule_id {
+        info!(
+            "Allowed {} {}-{} from {} on {} until {} ({})",
+            ingress.protocol,
+            ingress.from_port,
+            ingress.to_port,
+            cidr_ip,
+            ingress.group_id,
+            expires_at,
+            rule_id
+        );
+    }
+
+    Ok(rule_id)
+}
+
+/// The single-address CIDR a temporary rule opens for `address`.
+fn ingress_cidr(address: IpAddr) -> Result<String> {
+    let address: Ipv4Addr = match address {
+        IpAddr::V4(address) => address,
+        IpAddr::V6(address) => {
+            return Err(anyhow!(
+                "Public address {} is IPv6. Only IPv4 rules are supported; pass an IPv4 address \
+                 with --ip.",
+                address
+            ))
+        }
+    };
+
+    Ok(format!("{}/32", address))
+}
+
+/// Revokes every inbound rule whose `EXPIRES_AT_TAG_KEY` tag is earlier than `now`. Rules with
+/// an unreadable expiry are left alone. Returns the rules that expired.
+pub async fn sweep_expired_ingress(
+    client: &aws_sdk_ec2::Client,
+    now: OffsetDateTime,
+    mode: ExecutionMode,
+) -> Result<Vec<SecurityGroupRule>> {
+    // `DescribeSecurityGroupRules` has no `tag-key` filter, so any value is matched instead.
+    let rules: Vec<SecurityGroupRule> = get_security_group_rules(
+        client,
+        vec![AwsQueryType::Tag {
+            key: EXPIRES_AT_TAG_KEY.to_string(),
+            values: vec!["*".to_string()],
+        }],
+    )
+    .await?;
+
+    let mut expired: Vec<SecurityGroupRule> = vec![];
+    for rule in rules
+        .into_iter()
+        .filter(|rule| rule.is_egress() == Some(false))
+    {
+        let expires_at: &str = rule
+            .tags()
+            .iter()
+            .find(|tag| tag.key() == Some(EXPIRES_AT_TAG_KEY))
+            .and_then(|tag| tag.value())
+            .unwrap_or_default();
+        match has_expired(expires_at, now) {
+            Ok(true) => expired.push(rule),
+            Ok(false) => (),
+            Err(_) => warn!(
+                "Ignoring {} on {}: {} {:?} is not an RFC 3339 time",
+                rule.security_group_rule_id().unwrap_or_default(),
+                rule.group_id().unwrap_or_default(),
+                EXPIRES_AT_TAG_KEY,
+                expires_at
+            ),
+        }
+    }
+
+    let mut group_ids: Vec<&str> = expired.iter().filter_map(|rule| rule.group_id()).collect();
+    group_ids.sort();
+    group_ids.dedup();
+    for group_id in group_ids {
+        let rules: Vec<&SecurityGroupRule> = expired
+            .iter()
+            .filter(|rule| rule.group_id() == Some(group_id))
+            .collect();
+        let rule_ids: Vec<String> = rules
+            .iter()
+            .filter_map(|rule| rule.security_group_rule_id().map(String::from))
+            .collect();
+        let result = revoke_security_group_ingress_rules(client, group_id, rule_ids, mode).await?;
+        if result.is_some() {
+            for rule in rules {
+                info!(
+                    "Revoked expired {} {}-{} from {} on {} ({})",
+                    rule.ip_protocol().unwrap_or_default(),
+                    rule.from_port().unwrap_or_default(),
+                    rule.to_port().unwrap_or_default(),
+                    rule.cidr_ipv4().unwrap_or_default(),
+                    group_id,
+                    rule.security_group_rule_id().unwrap_or_default()
+                );
+            }
+        }
+    }
+
+    Ok(expired)
+}
+
+/// Whether the RFC 3339 time `expires_at` is at or before `now`.
+fn has_expired(expires_at: &str, now: OffsetDateTime) -> Result<bool> {
+    let expires_at: OffsetDateTime = OffsetDateTime::parse(expires_at, &Rfc3339)?;
+
+    Ok(expires_at <= now)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv6Addr;
+
+    use super::*;
+    use crate::http::fake::FakeTransport;
+
+    const RESOLVER_URL: &str = "https://checkip.example.com";
+
+    fn resolve(status: u16, body: &str) -> Result<IpAddr> {
+        let transport: FakeTransport =
+            FakeTransport::new().respond_to("GET", RESOLVER_URL, HttpResponse::new(status, body));
+        HttpPublicIpResolver::new(transport, RESOLVER_URL).resolve()
+    }
+
+    #[test]
+    fn resolver_reads_the_address_from_the_body() {
+        assert_eq!(
+            resolve(200, "203.0.113.7\n").unwrap(),
+            IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7))
+        );
+    }
+
+    #[test]
+    fn resolver_rejects_bodies_that_are_not_addresses() {
+        assert!(resolve(200, "<html>Blocked</html>").is_err());
+        assert!(resolve(200, "").is_err());
+        assert!(resolve(503, "203.0.113.7").is_err());
+    }
+
+    #[test]
+    fn ipv6_addresses_are_rejected() {
+        let address: IpAddr = resolve(200, "2001:db8::1\n").unwrap();
+
+        assert_eq!(
+            address,
+            IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1))
+        );
+        assert!(ingress_cidr(address).is_err());
+        assert_eq!(
+            ingress_cidr(IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7))).unwrap(),
+            "203.0.113.7/32"
+        );
+    }
+
+    #[test]
+    fn rules_expire_once_their_time_has_come() {
+        let now: OffsetDateTime = OffsetDateTime::parse("2024-06-01T12:00:00Z", &Rfc3339).unwrap();
+
+        assert!(has_expired("2024-06-01T11:59:59Z", now).unwrap());
+        assert!(has_expired("2024-06-01T12:00:00Z", now).unwrap());
+        assert!(has_expired("2024-06-01T13:00:00+02:00", now).unwrap());
+        assert!(!has_expired("2024-06-01T12:00:01Z", now).unwrap());
+    }
+
+    #[test]
+    fn unparseable_expiry_is_an_error() {
+        let now: OffsetDateTime = OffsetDateTime::parse("2024-06-01T12:00:00Z", &Rfc3339).unwrap();
+
+        assert!(has_expired("tomorrow", now).is_err());
+        assert!(has_expired("", now).is_err());
+        assert!(has_expired("2024-06-01", now).is_err());
+    }
+}
@@ -0,0 +1,2144 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use aws_config::SdkConfig;
+use aws_sdk_ec2::types::{Filter, LaunchTemplate, ResourceType, SecurityGroup, Vpc};
+use clap::{ArgGroup, Args, Parser, Subcommand};
+use log::{info, warn};
+use time::OffsetDateTime;
+
+use crate::amazon_web_services::ec2::{
+    self, add_security_group_ingress, remove_security_group_ingress, AwsQueryType, ExecutionMode,
+};
+use crate::amazon_web_services::instances::{
+    change_instance_state, describe_instances, launch_instances, wait_for_instance_action,
+    wait_until_instances_ready, InstanceAction, InstanceSelector, InstanceSummary, LaunchRequest,
+};
+use crate::amazon_web_services::launch_templates::{
+    apply_launch_template_plans, load_launch_templates_document, log_launch_template_plans,
+    plan_launch_templates, promote_launch_template, roll_back_launch_template, LaunchTemplatePlan,
+    LaunchTemplatesDocument,
+};
+use crate::amazon_web_services::security_groups::{
+    apply_security_group_plans, load_security_groups_document, log_security_group_plans,
+    plan_security_groups, SecurityGroupPlan, SecurityGroupsDocument,
+};
+use crate::amazon_web_services::tagging::{
+    audit_tags, backfill_tags, TagAuditFinding, TagPolicy, TaggedResourceType,
+};
+use crate::amazon_web_services::temporary_access::{
+    grant_temporary_ingress, sweep_expired_ingress, HttpPublicIpResolver, PublicIpResolver,
+    StaticPublicIpResolver, TemporaryIngress,
+};
+use crate::configuration::constants::amazon_web_services::{
+    DEFAULT_INSTANCE_WAIT_TIMEOUT_SECONDS, DEFAULT_TEMPORARY_INGRESS_MINUTES,
+    RUST_ARM_NANO_TEMPLATE_NAME,
+};
+use crate::configuration::constants::github::{
+    DEFAULT_GITHUB_ACTIONS_ORGANIZATION_VISIBILITY, DEFAULT_GITHUB_WORKFLOW_RUN_TIMEOUT_SECONDS,
+    GITHUB_ACTIONS_AWS_REGION_VARIABLE, GITHUB_ACTIONS_AWS_ROLE_ARN_SECRET,
+    GITHUB_ACTIONS_SECURITY_GROUP_VARIABLE_PREFIX, GITHUB_DEFAULT_PER_PAGE,
+};
+use crate::configuration::constants::networking::{ANYWHERE_IPV4, SSH_PORT, TCP_PROTOCOL};
+use crate::configuration::constants::project_file_paths::{
+    DEFAULT_LAUNCH_TEMPLATES_FILE_PATH, DEFAULT_SECURITY_GROUPS_FILE_PATH,
+};
+use crate::configuration::{Configuration, SitePublishMethod};
+use crate::file_system::{get_directory, get_file_content_as_string, Directory};
+use crate::github::actions::ActionsScope;
+use crate::github::check_runs::{CheckRunAnnotation, CheckRunOutput, CheckRunUpdate, NewCheckRun};
+use crate::github::deployments::{track_deployment, track_deployment_async, DeploymentTarget};
+use crate::github::github_app::GitHubApp;
+use crate::github::models::{
+    CheckRunConclusion, CheckRunStatus, PagesSource, Release, WorkflowRun,
+};
+use crate::github::pages::PagesUpdate;
+use crate::github::pagination::PaginationOptions;
+use crate::github::releases::NewRelease;
+use crate::github::webhooks::queue::{DeliveryQueue, DeliveryState};
+use crate::github::webhooks::server;
+use crate::github::workflows::ensure_workflow_run_succeeded;
+use crate::http::{cassette::CassetteTransport, ureq_transport::UreqTransport, HttpTransport};
+use crate::workshop::{deploy_loremaster_static_site, deploy_site};
+
+/// Lorerunner - GitHub App and Amazon Web Services automation for the loremaster project.
+#[derive(Debug, Parser)]
+#[command(name = "lorerunner", version, about, long_about = None)]
+pub struct CommandLineInterface {
+    #[command(subcommand)]
+    pub command: Command,
+    /// Record GitHub HTTP interactions to a cassette file while running the command.
+    #[arg(long, global = true, value_name = "PATH", conflicts_with = "replay")]
+    pub record: Option<PathBuf>,
+    /// Replay GitHub HTTP interactions from a cassette file instead of calling GitHub.
+    #[arg(long, global = true, value_name = "PATH")]
+    pub replay: Option<PathBuf>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Interact with the GitHub API as the configured GitHub App.
+    #[command(name = "github")]
+    GitHub {
+        #[command(flatten)]
+        installation: InstallationSelector,
+        #[command(subcommand)]
+        command: GitHubCommand,
+    },
+    /// Inspect and modify Amazon Web Services resources.
+    Aws {
+        /// Log every mutating AWS request and send it with EC2's `DryRun` flag, which checks
+        /// permissions and parameters without changing anything. Only AWS commands support it.
+        #[arg(long, global = true)]
+        dry_run: bool,
+        #[command(subcommand)]
+        command: AwsCommand,
+    },
+    /// Build and publish the loremaster static site.
+    Site {
+        #[command(subcommand)]
+        command: SiteCommand,
+    },
+    /// Receive GitHub App webhook deliveries.
+    Webhook {
+        #[command(subcommand)]
+        command: WebhookCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum GitHubCommand {
+    /// Call the GitHub API root to verify connectivity and authentication.
+    Ping,
+    /// Fetch the details of the authenticated GitHub App.
+    App,
+    /// Fetch the details of the selected GitHub App installation.
+    Installation,
+    /// List every installation of the GitHub App.
+    Installations(PaginationArguments),
+    /// List the repositories the selected installation can access.
+    Repositories(PaginationArguments),
+    /// List every installation of the GitHub App with the repositories each can access.
+    Discover(PaginationArguments),
+    /// Show the installation's current API rate limits.
+    RateLimit,
+    /// Create or update releases and their assets.
+    Release {
+        #[command(subcommand)]
+        command: ReleaseCommand,
+    },
+    /// Inspect and configure the GitHub Pages site.
+    Pages {
+        #[command(subcommand)]
+        command: PagesCommand,
+    },
+    /// Publish pipeline results as check runs on a commit.
+    CheckRun {
+        #[command(subcommand)]
+        command: CheckRunCommand,
+    },
+    /// Manage GitHub Actions secrets of a repository, environment or organization.
+    Secret {
+        #[command(subcommand)]
+        command: SecretCommand,
+    },
+    /// Manage GitHub Actions variables of a repository, environment or organization.
+    Variable {
+        #[command(subcommand)]
+        command: VariableCommand,
+    },
+    /// Trigger and follow GitHub Actions workflow runs.
+    Workflow {
+        #[command(subcommand)]
+        command: WorkflowCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum WorkflowCommand {
+    /// Dispatch a `workflow_dispatch` workflow and follow the run until it completes, failing
+    /// unless it succeeds.
+    Run {
+        /// `owner/name` of the repository.
+        #[arg(long)]
+        repository: String,
+        /// Workflow ID or file name, such as `deploy.yml`.
+        #[arg(long)]
+        workflow: String,
+        /// Branch or tag to run the workflow on.
+        #[arg(long = "ref")]
+        git_ref: String,
+        /// Workflow input as `name=value`. May be repeated.
+        #[arg(long = "input", value_name = "NAME=VALUE", value_parser = parse_workflow_input)]
+        inputs: Vec<(String, String)>,
+        /// Return once the run has started instead of following it.
+        #[arg(long, conflicts_with_all = ["timeout", "logs", "artifacts"])]
+        no_wait: bool,
+        /// Seconds to wait for the run to complete.
+        #[arg(long, default_value_t = DEFAULT_GITHUB_WORKFLOW_RUN_TIMEOUT_SECONDS)]
+        timeout: u64,
+        #[command(flatten)]
+        output: WorkflowOutputArguments,
+    },
+    /// Follow an existing run until it completes, failing unless it succeeds.
+    Watch {
+        /// `owner/name` of the repository.
+        #[arg(long)]
+        repository: String,
+        /// ID of the run.
+        #[arg(long)]
+        run_id: u64,
+        /// Seconds to wait for the run to complete.
+        #[arg(long, default_value_t = DEFAULT_GITHUB_WORKFLOW_RUN_TIMEOUT_SECONDS)]
+        timeout: u64,
+        #[command(flatten)]
+        output: WorkflowOutputArguments,
+    },
+    /// Download the logs and artifacts of a run.
+    Download {
+        /// `owner/name` of the repository.
+        #[arg(long)]
+        repository: String,
+        /// ID of the run.
+        #[arg(long)]
+        run_id: u64,
+        #[command(flatten)]
+        output: WorkflowOutputArguments,
+    },
+}
+
+/// Where a finished run's logs and artifacts are extracted to.
+#[derive(Debug, Args)]
+pub struct WorkflowOutputArguments {
+    /// Directory to extract the run's logs into.
+    #[arg(long, value_name = "DIRECTORY")]
+    pub logs: Option<PathBuf>,
+    /// Directory to extract each artifact into, one sub-directory per artifact.
+    #[arg(long, value_name = "DIRECTORY")]
+    pub artifacts: Option<PathBuf>,
+}
+
+impl WorkflowOutputArguments {
+    fn save<T: HttpTransport>(
+        &self,
+        github_app: &GitHubApp<T>,
+        repository: &str,
+        run_id: u64,
+    ) -> Result<()> {
+        if let Some(directory) = &self.logs {
+            github_app.save_workflow_run_logs(repository, run_id, directory)?;
+        }
+        if let Some(directory) = &self.artifacts {
+            let saved: usize =
+                github_app.save_workflow_run_artifacts(repository, run_id, directory)?;
+            info!("Saved {} artifact(s) to {}", saved, directory.display());
+        }
+        Ok(())
+    }
+}
+
+fn parse_workflow_input(input: &str) -> Result<(String, String)> {
+    input
+        .split_once('=')
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .ok_or_else(|| anyhow!("Expected NAME=VALUE, got {:?}", input))
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SecretCommand {
+    /// List the names of the secrets. GitHub never returns their values.
+    List {
+        #[command(flatten)]
+        scope: ActionsScopeArguments,
+        #[command(flatten)]
+        pagination: PaginationArguments,
+    },
+    /// Create or replace a secret.
+    Set {
+        #[command(flatten)]
+        scope: ActionsScopeArguments,
+        /// Name of the secret.
+        name: String,
+        #[command(flatten)]
+        value: ActionsValueArguments,
+    },
+    /// Delete a secret.
+    Delete {
+        #[command(flatten)]
+        scope: ActionsScopeArguments,
+        /// Name of the secret.
+        name: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum VariableCommand {
+    /// List the variables and their values.
+    List {
+        #[command(flatten)]
+        scope: ActionsScopeArguments,
+        #[command(flatten)]
+        pagination: PaginationArguments,
+    },
+    /// Create a variable, or update it if it exists.
+    Set {
+        #[command(flatten)]
+        scope: ActionsScopeArguments,
+        /// Name of the variable.
+        name: String,
+        #[command(flatten)]
+        value: ActionsValueArguments,
+    },
+    /// Delete a variable.
+    Delete {
+        #[command(flatten)]
+        scope: ActionsScopeArguments,
+        /// Name of the variable.
+        name: String,
+    },
+}
+
+/// Where an Actions secret or variable lives. One of `--repository` or `--organization` is
+/// required.
+#[derive(Debug, Args)]
+pub struct ActionsScopeArguments {
+    /// `owner/name` of the repository.
+    #[arg(long, conflicts_with = "organization")]
+    pub repository: Option<String>,
+    /// Deployment environment of `--repository`.
+    #[arg(long, requires = "repository")]
+    pub environment: Option<String>,
+    /// Login of the organization.
+    #[arg(long)]
+    pub organization: Option<String>,
+    /// Which organization repositories can read the value: `all` or `private`.
+    #[arg(long, requires = "organization", default_value = DEFAULT_GITHUB_ACTIONS_ORGANIZATION_VISIBILITY)]
+    pub visibility: String,
+}
+
+impl ActionsScopeArguments {
+    fn into_scope(self) -> Result<ActionsScope> {
+        let scope: ActionsScope = match (self.repository, self.environment, self.organization) {
+            (Some(repository), Some(environment), None) => ActionsScope::Environment {
+                repository,
+                environment,
+            },
+            (Some(repository), None, None) => ActionsScope::Repository(repository),
+            (None, None, Some(organization)) => ActionsScope::Organization {
+                organization,
+                visibility: self.visibility,
+            },
+            _ => {
+                return Err(anyhow!(
+                    "Either --repository or --organization is required."
+                ))
+            }
+        };
+        scope.validate()?;
+
+        Ok(scope)
+    }
+}
+
+/// Where the value of a secret or variable comes from.
+#[derive(Debug, Args)]
+#[group(required = true, multiple = false)]
+pub struct ActionsValueArguments {
+    /// The value itself. Prefer `--from-env` for secrets, since arguments show up in process
+    /// listings.
+    #[arg(long)]
+    pub value: Option<String>,
+    /// Name of an environment variable holding the value.
+    #[arg(long, value_name = "VARIABLE")]
+    pub from_env: Option<String>,
+}
+
+impl ActionsValueArguments {
+    fn into_value(self) -> Result<String> {
+        match (self.value, self.from_env) {
+            (Some(value), _) => Ok(value),
+            (None, Some(variable)) => std::env::var(&variable)
+                .map_err(|error| anyhow!("Failed to read ${}: {}", variable, error)),
+            (None, None) => Err(anyhow!("Either --value or --from-env is required.")),
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ReleaseCommand {
+    /// Create the release for a tag, or update it if it exists, and upload assets to it.
+    Publish(ReleaseArguments),
+}
+
+#[derive(Debug, Args)]
+pub struct ReleaseArguments {
+    /// `owner/name` of the repository.
+    #[arg(long)]
+    pub repository: String,
+    /// Tag of the release. Created from `--target` if it doesn't exist.
+    #[arg(long)]
+    pub tag: String,
+    /// Branch or commit SHA to create the tag from.
+    #[arg(long)]
+    pub target: Option<String>,
+    /// Title of the release.
+    #[arg(long)]
+    pub name: Option<String>,
+    /// Markdown file with the release notes.
+    #[arg(long, value_name = "PATH")]
+    pub notes_file: Option<PathBuf>,
+    /// Append notes generated from the pull requests merged since the previous release.
+    #[arg(long)]
+    pub generate_notes: bool,
+    /// Tag the generated notes start from.
+    #[arg(long, requires = "generate_notes")]
+    pub previous_tag: Option<String>,
+    #[arg(long)]
+    pub draft: bool,
+    #[arg(long)]
+    pub prerelease: bool,
+    /// File to upload, replacing an existing asset of the same name. A directory uploads each
+    /// file directly inside it, such as the Perseus `pkg` output.
+    #[arg(long = "asset", value_name = "PATH")]
+    pub assets: Vec<PathBuf>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum PagesCommand {
+    /// Show the Pages configuration and latest build.
+    Show {
+        /// `owner/name` of the repository. Defaults to the configured pages repository.
+        #[arg(long)]
+        repository: Option<String>,
+    },
+    /// Update the Pages source, custom domain or HTTPS enforcement.
+    Configure {
+        /// `owner/name` of the repository. Defaults to the configured pages repository.
+        #[arg(long)]
+        repository: Option<String>,
+        /// Branch Pages builds from.
+        #[arg(long, requires = "path")]
+        branch: Option<String>,
+        /// Folder Pages builds from, `/` or `/docs`.
+        #[arg(long, requires = "branch")]
+        path: Option<String>,
+        /// Custom domain the site is served from.
+        #[arg(long)]
+        cname: Option<String>,
+        /// Redirect HTTP requests to HTTPS.
+        #[arg(long)]
+        https_enforced: Option<bool>,
+    },
+    /// Request a Pages build of the source branch.
+    Build {
+        /// `owner/name` of the repository. Defaults to the configured pages repository.
+        #[arg(long)]
+        repository: Option<String>,
+        /// Wait until the build finishes, failing if it errors.
+        #[arg(long)]
+        wait: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CheckRunCommand {
+    /// Start a check run on a commit, or publish a finished one with `--conclusion`.
+    Create {
+        /// `owner/name` of the repository.
+        #[arg(long)]
+        repository: String,
+        /// Full SHA of the commit the check run is attached to.
+        #[arg(long)]
+        sha: String,
+        /// Name shown on the pull request.
+        #[arg(long)]
+        name: String,
+        /// Complete the check run immediately with this conclusion.
+        #[arg(long, value_enum)]
+        conclusion: Option<CheckRunConclusion>,
+        /// Link to the full pipeline results.
+        #[arg(long)]
+        details_url: Option<String>,
+        #[command(flatten)]
+        output: CheckRunOutputArguments,
+    },
+    /// Update an existing check run.
+    Update {
+        /// `owner/name` of the repository.
+        #[arg(long)]
+        repository: String,
+        /// ID of the check run.
+        #[arg(long)]
+        id: u64,
+        #[arg(long, value_enum, conflicts_with = "conclusion")]
+        status: Option<CheckRunStatus>,
+        /// Complete the check run with this conclusion.
+        #[arg(long, value_enum)]
+        conclusion: Option<CheckRunConclusion>,
+        /// Link to the full pipeline results.
+        #[arg(long)]
+        details_url: Option<String>,
+        #[command(flatten)]
+        output: CheckRunOutputArguments,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct CheckRunOutputArguments {
+    /// Title of the check run output.
+    #[arg(long)]
+    pub title: Option<String>,
+    /// Markdown file with the output summary.
+    #[arg(long, value_name = "PATH", requires = "title")]
+    pub summary_file: Option<PathBuf>,
+    /// JSON file with an array of annotations (`path`, `start_line`, `end_line`,
+    /// `annotation_level`, `message`).
+    #[arg(long, value_name = "PATH", requires = "title")]
+    pub annotations_file: Option<PathBuf>,
+}
+
+impl CheckRunOutputArguments {
+    fn into_output(self) -> Result<Option<CheckRunOutput>> {
+        let Some(title) = self.title else {
+            return Ok(None);
+        };
+        let summary: String = match self.summary_file {
+            Some(path) => get_file_content_as_string(path.to_string_lossy().to_string())?,
+            None => String::new(),
+        };
+        let annotations: Vec<CheckRunAnnotation> = match self.annotations_file {
+            Some(path) => serde_json::from_str(&get_file_content_as_string(
+                path.to_string_lossy().to_string(),
+            )?)?,
+            None => vec![],
+        };
+
+        Ok(Some(CheckRunOutput {
+            title,
+            summary,
+            text: None,
+            annotations,
+        }))
+    }
+}
+
+/// Picks the installation GitHub commands act as. Defaults to `github_app_installation_id`.
+#[derive(Debug, Args)]
+#[group(multiple = false)]
+pub struct InstallationSelector {
+    /// ID of the installation.
+    #[arg(long)]
+    pub installation: Option<u64>,
+    /// Login of the organization or user the app is installed on.
+    #[arg(long)]
+    pub account: Option<String>,
+    /// `owner/name` of a repository the installation can access.
+    #[arg(long, value_name = "REPOSITORY")]
+    pub installation_for: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct PaginationArguments {
+    /// Items to request per page (at most 100).
+    #[arg(long, default_value_t = GITHUB_DEFAULT_PER_PAGE)]
+    pub per_page: u32,
+    /// Stop after this many pages.
+    #[arg(long)]
+    pub max_pages: Option<u32>,
+}
+
+impl From<PaginationArguments> for PaginationOptions {
+    fn from(arguments: PaginationArguments) -> Self {
+        PaginationOptions {
+            per_page: arguments.per_page,
+            max_pages: arguments.max_pages,
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AwsCommand {
+    /// EC2 launch templates and VPCs.
+    Ec2 {
+        #[command(subcommand)]
+        command: Ec2Command,
+    },
+    /// Author launch template versions from a RON file, promote them and roll back.
+    Template {
+        #[command(subcommand)]
+        command: LaunchTemplateCommand,
+    },
+    /// Audit and back-fill the tags `aws_required_tags` requires on EC2 resources.
+    Tags {
+        #[command(subcommand)]
+        command: TagCommand,
+    },
+    /// Launch EC2 instances from launch templates and start, stop or terminate them.
+    Instance {
+        #[command(subcommand)]
+        command: InstanceCommand,
+    },
+    /// EC2 security groups.
+    Sg {
+        #[command(subcommand)]
+        command: SecurityGroupCommand,
+    },
+    /// Push the AWS region, a role ARN and security group IDs into GitHub Actions, as the
+    /// `AWS_REGION` variable, the `AWS_ROLE_ARN` secret and `AWS_SECURITY_GROUP_ID_<NAME>`
+    /// variables.
+    Publish {
+        #[command(flatten)]
+        scope: ActionsScopeArguments,
+        /// ARN of the role workflows assume.
+        #[arg(long)]
+        role_arn: Option<String>,
+        /// Name of a security group whose ID is published.
+        #[arg(long = "security-group")]
+        security_groups: Vec<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Ec2Command {
+    /// Describe launch templates, optionally filtered.
+    Templates {
+        #[command(flatten)]
+        query: QueryArguments,
+    },
+    /// Describe VPCs, optionally filtered. A VPC's name is its `Name` tag.
+    Vpcs {
+        #[command(flatten)]
+        query: QueryArguments,
+    },
+}
+
+/// Narrows an EC2 describe command to resources matching every option given. Describes
+/// everything when none is.
+#[derive(Debug, Args)]
+pub struct QueryArguments {
+    /// Resource ID. May be repeated.
+    #[arg(long = "id")]
+    pub ids: Vec<String>,
+    /// Resource name. May be repeated.
+    #[arg(long = "name")]
+    pub names: Vec<String>,
+    /// Tag as `KEY` or `KEY=VALUE[,VALUE...]`. May be repeated.
+    #[arg(long = "tag", value_name = "KEY[=VALUE]", value_parser = parse_tag_query)]
+    pub tags: Vec<(String, Vec<String>)>,
+    /// Raw EC2 filter as `NAME=VALUE[,VALUE...]`, such as `vpc-id=vpc-0123`. May be repeated.
+    #[arg(long = "filter", value_name = "NAME=VALUE", value_parser = parse_filter)]
+    pub filters: Vec<Filter>,
+}
+
+impl QueryArguments {
+    fn into_query(self) -> Vec<AwsQueryType> {
+        let mut query: Vec<AwsQueryType> = vec![];
+        if !self.ids.is_empty() {
+            query.push(AwsQueryType::Id(self.ids));
+        }
+        if !self.names.is_empty() {
+            query.push(AwsQueryType::Name(self.names));
+        }
+        query.extend(
+            self.tags
+                .into_iter()
+                .map(|(key, values)| AwsQueryType::Tag { key, values }),
+        );
+        query.extend(self.filters.into_iter().map(AwsQueryType::Filter));
+        query
+    }
+}
+
+fn parse_tag_query(tag: &str) -> Result<(String, Vec<String>)> {
+    let (key, values): (&str, Vec<String>) = match tag.split_once('=') {
+        Some((key, values)) => (key, values.split(',').map(String::from).collect()),
+        None => (tag, vec![]),
+    };
+    match key.is_empty() {
+        true => Err(anyhow!("Expected KEY or KEY=VALUE, got {:?}", tag)),
+        false => Ok((key.to_string(), values)),
+    }
+}
+
+fn parse_filter(filter: &str) -> Result<Filter> {
+    match filter.split_once('=') {
+        Some((name, values)) if !name.is_empty() => Ok(Filter::builder()
+            .name(name)
+            .set_values(Some(values.split(',').map(String::from).collect()))
+            .build()),
+        _ => Err(anyhow!("Expected NAME=VALUE, got {:?}", filter)),
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum TagCommand {
+    /// List EC2 resources missing required tags, and optionally add them.
+    #[command(group(
+        ArgGroup::new("backfill_scope")
+            .multiple(true)
+            .args(["ids", "names", "tags", "filters", "all"])
+    ))]
+    Audit {
+        /// Resource type to audit. May be repeated. Audits every type when omitted. `--filter`
+        /// needs exactly one.
+        #[arg(long = "type", value_enum)]
+        resource_types: Vec<TaggedResourceType>,
+        #[command(flatten)]
+        query: QueryArguments,
+        /// Add the missing tags that have a value in `aws_required_tags`. Needs a query or
+        /// `--all`, so that every resource in the region is never tagged by accident.
+        #[arg(long, requires = "backfill_scope")]
+        backfill: bool,
+        /// Back-fill every resource in the region.
+        #[arg(
+            long,
+            requires = "backfill",
+            conflicts_with_all = ["ids", "names", "tags", "filters"]
+        )]
+        all: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum LaunchTemplateCommand {
+    /// Show how the declared launch templates differ from their latest versions.
+    Plan {
+        /// RON file declaring the launch templates.
+        #[arg(long, value_name = "PATH", default_value = DEFAULT_LAUNCH_TEMPLATES_FILE_PATH)]
+        file: PathBuf,
+    },
+    /// Create a new version of every declared launch template that changed.
+    Apply {
+        /// RON file declaring the launch templates.
+        #[arg(long, value_name = "PATH", default_value = DEFAULT_LAUNCH_TEMPLATES_FILE_PATH)]
+        file: PathBuf,
+        /// Description of the new versions.
+        #[arg(long)]
+        description: Option<String>,
+        /// Make the latest version of each template its default.
+        #[arg(long)]
+        promote: bool,
+        /// With `--promote`, report the promotion to GitHub as an EC2 deployment.
+        #[command(flatten)]
+        deployment: DeploymentArguments,
+    },
+    /// Make a version the default, remembering the current default for rollback.
+    Promote {
+        /// Name of the launch template.
+        #[arg(long)]
+        name: String,
+        /// Version number to promote.
+        #[arg(long)]
+        version: i64,
+        #[command(flatten)]
+        deployment: DeploymentArguments,
+    },
+    /// Restore the default version the last promotion replaced.
+    Rollback {
+        /// Name of the launch template.
+        #[arg(long)]
+        name: String,
+        /// Version number to restore instead of the recorded one.
+        #[arg(long)]
+        version: Option<i64>,
+        #[command(flatten)]
+        deployment: DeploymentArguments,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum InstanceCommand {
+    /// Launch instances from a launch template and wait until their status checks pass.
+    Launch {
+        /// Name of the launch template.
+        #[arg(long, default_value = RUST_ARM_NANO_TEMPLATE_NAME)]
+        template: String,
+        /// Template version: a number, `$Latest` or `$Default`. Defaults to the default version.
+        #[arg(long)]
+        version: Option<String>,
+        /// Subnet to launch into instead of the template's.
+        #[arg(long)]
+        subnet_id: Option<String>,
+        /// Security group ID replacing the template's groups. May be repeated.
+        #[arg(long = "security-group-id")]
+        security_group_ids: Vec<String>,
+        /// Tag for the instances and their volumes as `KEY=VALUE`. May be repeated.
+        #[arg(long = "tag", value_name = "KEY=VALUE", value_parser = parse_tag)]
+        tags: Vec<(String, String)>,
+        /// Number of instances to launch.
+        #[arg(long, default_value_t = 1)]
+        count: i32,
+        #[command(flatten)]
+        wait: InstanceWaitArguments,
+        #[command(flatten)]
+        deployment: DeploymentArguments,
+    },
+    /// Describe instances by ID or tag.
+    List {
+        #[command(flatten)]
+        target: InstanceTargetArguments,
+    },
+    /// Start stopped instances and wait until their status checks pass.
+    Start {
+        #[command(flatten)]
+        target: InstanceTargetArguments,
+        #[command(flatten)]
+        wait: InstanceWaitArguments,
+    },
+    /// Stop running instances.
+    Stop {
+        #[command(flatten)]
+        target: InstanceTargetArguments,
+        #[command(flatten)]
+        wait: InstanceWaitArguments,
+    },
+    /// Terminate instances.
+    Terminate {
+        #[command(flatten)]
+        target: InstanceTargetArguments,
+        #[command(flatten)]
+        wait: InstanceWaitArguments,
+    },
+}
+
+/// Reports an EC2 rollout to GitHub as a deployment in `ec2_repository`.
+#[derive(Debug, Args)]
+pub struct DeploymentArguments {
+    /// Report the rollout to GitHub as a deployment of this branch, tag or commit SHA.
+    #[arg(long = "ref")]
+    pub git_ref: Option<String>,
+    /// Link to the rollout's logs, shown on the GitHub deployment instead of
+    /// `deployment_log_url`.
+    #[arg(long, requires = "git_ref")]
+    pub log_url: Option<String>,
+}
+
+#[derive(Debug, Args)]
+#[group(required = true, multiple = false)]
+pub struct InstanceTargetArguments {
+    /// Instance ID. May be repeated.
+    #[arg(long = "id")]
+    pub ids: Vec<String>,
+    /// Select instances carrying this tag, as `KEY=VALUE`. May be repeated; all must match.
+    #[arg(long = "tag", value_name = "KEY=VALUE", value_parser = parse_tag)]
+    pub tags: Vec<(String, String)>,
+}
+
+impl InstanceTargetArguments {
+    fn into_selector(self) -> InstanceSelector {
+        match self.ids.is_empty() {
+            true => InstanceSelector::Tags(self.tags),
+            false => InstanceSelector::Ids(self.ids),
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct InstanceWaitArguments {
+    /// Return once EC2 accepted the request instead of waiting for the new state.
+    #[arg(long, conflicts_with = "timeout")]
+    pub no_wait: bool,
+    /// Seconds to wait for the instances to reach the new state.
+    #[arg(long, default_value_t = DEFAULT_INSTANCE_WAIT_TIMEOUT_SECONDS)]
+    pub timeout: u64,
+}
+
+fn parse_tag(tag: &str) -> Result<(String, String)> {
+    match tag.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(anyhow!("Expected KEY=VALUE, got {:?}", tag)),
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SecurityGroupCommand {
+    /// Describe security groups, optionally filtered.
+    List {
+        #[command(flatten)]
+        query: QueryArguments,
+    },
+    /// Show what applying the declared security groups would change.
+    Plan {
+        /// RON file declaring the security groups and their rules.
+        #[arg(long, value_name = "PATH", default_value = DEFAULT_SECURITY_GROUPS_FILE_PATH)]
+        file: PathBuf,
+    },
+    /// Create the declared security groups and authorize or revoke rules until they match.
+    /// Rules opened by `allow-my-ip` are left for `sweep`.
+    Apply {
+        /// RON file declaring the security groups and their rules.
+        #[arg(long, value_name = "PATH", default_value = DEFAULT_SECURITY_GROUPS_FILE_PATH)]
+        file: PathBuf,
+    },
+    /// Authorize an ingress rule on a security group.
+    AddIngress(IngressArguments),
+    /// Allow this machine's public IP address in for a while. The rule is tagged with when it
+    /// expires; running the command again extends it.
+    AllowMyIp {
+        #[command(flatten)]
+        group: SecurityGroupSelector,
+        /// First port of the range, such as 5432 for Postgres.
+        #[arg(long, default_value_t = SSH_PORT)]
+        port: i32,
+        /// Last port of the range. Defaults to `--port`.
+        #[arg(long)]
+        to_port: Option<i32>,
+        /// IP protocol of the rule.
+        #[arg(long, default_value = TCP_PROTOCOL)]
+        protocol: String,
+        /// How long the rule stays before `aws sg sweep` revokes it.
+        #[arg(long, default_value_t = DEFAULT_TEMPORARY_INGRESS_MINUTES)]
+        minutes: i64,
+        /// IPv4 address to allow instead of the one `public_ip_resolver_url` reports.
+        #[arg(long)]
+        ip: Option<Ipv4Addr>,
+    },
+    /// Revoke the temporary ingress rules whose expiry has passed. Meant to run on a schedule.
+    Sweep,
+    /// Revoke an ingress rule from a security group.
+    RemoveIngress(IngressArguments),
+}
+
+#[derive(Debug, Args)]
+pub struct IngressArguments {
+    #[command(flatten)]
+    pub group: SecurityGroupSelector,
+    /// CIDR block the rule applies to.
+    #[arg(long, default_value = ANYWHERE_IPV4)]
+    pub cidr: String,
+    /// IP protocol of the rule.
+    #[arg(long, default_value = TCP_PROTOCOL)]
+    pub protocol: String,
+    /// First port of the range.
+    #[arg(long)]
+    pub port: i32,
+    /// Last port of the range. Defaults to `--port`.
+    #[arg(long)]
+    pub to_port: Option<i32>,
+}
+
+#[derive(Debug, Args)]
+#[group(required = true, multiple = false)]
+pub struct SecurityGroupSelector {
+    /// ID of the security group.
+    #[arg(long)]
+    pub group_id: Option<String>,
+    /// Name of the security group.
+    #[arg(long)]
+    pub group_name: Option<String>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum WebhookCommand {
+    /// Run an HTTP server that verifies and dispatches GitHub webhook deliveries.
+    Serve {
+        /// Address to listen on. Defaults to `webhook_listen_address` from the configuration.
+        #[arg(long)]
+        address: Option<String>,
+    },
+    /// List deliveries that exhausted their retries and were moved to the dead-letter area.
+    Failed,
+    /// Run a dead-lettered delivery's handler again.
+    Replay {
+        /// `X-GitHub-Delivery` ID of the delivery to replay.
+        delivery_id: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SiteCommand {
+    /// Export the Perseus frontend and stage it for GitHub Pages.
+    Deploy {
+        /// Report the deploy to GitHub as a deployment of this branch, tag or commit SHA in
+        /// `site_repository`.
+        #[arg(long = "ref")]
+        git_ref: Option<String>,
+        /// Link to the deploy's logs, shown on the GitHub deployment.
+        #[arg(long, requires = "git_ref")]
+        log_url: Option<String>,
+    },
+}
+
+pub async fn run(
+    command_line_interface: CommandLineInterface,
+    configuration: &Configuration,
+) -> Result<()> {
+    let CommandLineInterface {
+        command,
+        record,
+        replay,
+    } = command_line_interface;
+
+    match command {
+        Command::GitHub {
+            installation,
+            command,
+        } => run_github_command(
+            command,
+            installation,
+            configuration,
+            github_transport(record.as_deref(), replay.as_deref())?,
+        ),
+        Command::Aws { dry_run, command } => {
+            let mode: ExecutionMode = match dry_run {
+                true => ExecutionMode::DryRun,
+                false => ExecutionMode::Apply,
+            };
+            run_aws_command(
+                command,
+                configuration,
+                record.as_deref(),
+                replay.as_deref(),
+                mode,
+            )
+            .await
+        }
+        Command::Site { command } => match command {
+            SiteCommand::Deploy { git_ref, log_url } => {
+                // Only reporting to GitHub or publishing through its API needs the GitHub App.
+                if git_ref.is_none()
+                    && configuration.site_publish_method == SitePublishMethod::LocalRepository
+                {
+                    return deploy_loremaster_static_site().map(|_| ());
+                }
+
+                let configured_app: GitHubApp<Box<dyn HttpTransport>> = GitHubApp::with_transport(
+                    configuration,
+                    github_transport(record.as_deref(), replay.as_deref())?,
+                )?;
+                let github_app: GitHubApp<Box<dyn HttpTransport>> =
+                    match configured_app.get_installation_id() {
+                        0 => configured_app.for_repository(&configuration.site_repository)?,
+                        _ => configured_app,
+                    };
+                let deploy =
+                    || deploy_site(&github_app, configuration, git_ref.as_deref()).map(|_| ());
+                match &git_ref {
+                    Some(git_ref) => {
+                        let mut target: DeploymentTarget =
+                            DeploymentTarget::for_site(configuration, git_ref)?;
+                        if log_url.is_some() {
+                            target.log_url = log_url;
+                        }
+                        track_deployment(&github_app, &target, deploy)
+                    }
+                    None => deploy(),
+                }
+            }
+        },
+        Command::Webhook { command } => match command {
+            WebhookCommand::Serve { address } => {
+                let listen_address: String =
+                    address.unwrap_or_else(|| configuration.webhook_listen_address.clone());
+                server::serve(configuration.clone(), &listen_address).await
+            }
+            WebhookCommand::Failed => {
+                let queue: DeliveryQueue = DeliveryQueue::open(configuration)?;
+                for delivery in queue.list(DeliveryState::DeadLetter)? {
+                    info!(
+                        "{} {} - {} attempt(s) - {}",
+                        delivery.delivery_id,
+                        delivery.event_name,
+                        delivery.attempts,
+                        delivery.last_error.unwrap_or_default()
+                    );
+                }
+                Ok(())
+            }
+            WebhookCommand::Replay { delivery_id } => {
+                DeliveryQueue::open(configuration)?.replay(&delivery_id, configuration)
+            }
+        },
+    }
+}
+
+/// The transport GitHub requests go through, honouring `--record` and `--replay`.
+fn github_transport(
+    record: Option<&Path>,
+    replay: Option<&Path>,
+) -> Result<Box<dyn HttpTransport>> {
+    let transport: Box<dyn HttpTransport> = match (record, replay) {
+        (_, Some(cassette_path)) => Box::new(CassetteTransport::replay(cassette_path)?),
+        (Some(cassette_path), None) => Box::new(CassetteTransport::record(
+            cassette_path,
+            UreqTransport::new(),
+        )),
+        (None, None) => Box::new(UreqTransport::new()),
+    };
+
+    Ok(transport)
+}
+
+fn run_github_command(
+    command: GitHubCommand,
+    installation: InstallationSelector,
+    configuration: &Configuration,
+    transport: Box<dyn HttpTransport>,
+) -> Result<()> {
+    let configured_app: GitHubApp<Box<dyn HttpTransport>> =
+        GitHubApp::with_transport(configuration, transport)?;
+    let github_app: GitHubApp<Box<dyn HttpTransport>> = match installation {
+        InstallationSelector {
+            installation: Some(installation_id),
+            ..
+        } => configured_app.with_installation(installation_id),
+        InstallationSelector {
+            account: Some(login),
+            ..
+        } => configured_app.for_account(&login)?,
+        InstallationSelector {
+            installation_for: Some(repository),
+            ..
+        } => configured_app.for_repository(&repository)?,
+        _ => configured_app,
+    };
+
+    match command {
+        GitHubCommand::Ping => {
+            let api_root = github_app.ping_github()?;
+            info!("GitHub responded with {} API routes", api_root.urls.len());
+        }
+        GitHubCommand::App => {
+            let app = github_app.get_app_details()?;
+            info!(
+                "{} ({}) - {} installation(s) - {}",
+                app.name,
+                app.id,
+                app.installations_count.unwrap_or_default(),
+                app.html_url
+            );
+        }
+        GitHubCommand::Installation => {
+            let installation = github_app.get_installation()?;
+            info!(
+                "Installation {} on {} ({}) - {} repositories",
+                installation.id,
+                installation
+                    .account
+                    .map(|account| account.login)
+                    .unwrap_or_default(),
+                installation.target_type,
+                installation.repository_selection
+            );
+        }
+        GitHubCommand::Installations(arguments) => {
+            for installation in github_app.list_installations(arguments.into()) {
+                let installation = installation?;
+                info!(
+                    "Installation {} on {} ({})",
+                    installation.id,
+                    installation
+                        .account
+                        .map(|account| account.login)
+                        .unwrap_or_default(),
+                    installation.target_type
+                );
+            }
+        }
+        GitHubCommand::Repositories(arguments) => {
+            for repository in github_app.list_installation_repositories(arguments.into()) {
+                let repository = repository?;
+                info!("{} - {}", repository.full_name, repository.html_url);
+            }
+        }
+        GitHubCommand::Discover(arguments) => {
+            let options: PaginationOptions = arguments.into();
+            for installation in github_app.list_installations(options) {
+                let installation = installation?;
+                info!(
+                    "Installation {} on {} ({})",
+                    installation.id,
+                    installation
+                        .account
+                        .as_ref()
+                        .map(|account| account.login.as_str())
+                        .unwrap_or_default(),
+                    installation.repository_selection
+                );
+                for repository in github_app
+                    .with_installation(installation.id)
+                    .list_installation_repositories(options)
+                {
+                    let repository = repository?;
+                    info!("\t{} - {}", repository.full_name, repository.html_url);
+                }
+            }
+        }
+        GitHubCommand::RateLimit => {
+            let rate_limit = github_app.get_rate_limit()?.rate;
+            info!(
+                "{}/{} requests remaining, resets at {}",
+                rate_limit.remaining, rate_limit.limit, rate_limit.reset
+            );
+        }
+        GitHubCommand::Release { command } => match command {
+            ReleaseCommand::Publish(arguments) => {
+                let mut new_release: NewRelease = NewRelease::new(&arguments.tag);
+                new_release.target_commitish = arguments.target;
+                new_release.name = arguments.name;
+                new_release.body = match arguments.notes_file {
+                    Some(path) => Some(get_file_content_as_string(
+                        path.to_string_lossy().to_string(),
+                    )?),
+                    None => None,
+                };
+                new_release.generate_release_notes = arguments.generate_notes;
+                new_release.previous_tag_name = arguments.previous_tag;
+                new_release.draft = arguments.draft;
+                new_release.prerelease = arguments.prerelease;
+
+                let asset_paths: Vec<PathBuf> = expand_asset_paths(&arguments.assets)?;
+                let release: Release =
+                    github_app.create_or_update_release(&arguments.repository, &new_release)?;
+                for asset_path in asset_paths {
+                    github_app.replace_release_asset(
+                        &arguments.repository,
+                        &release,
+                        &asset_path,
+                    )?;
+                }
+                info!(
+                    "Published release {} - {}",
+                    release.tag_name, release.html_url
+                );
+            }
+        },
+        GitHubCommand::Pages { command } => match command {
+            PagesCommand::Show { repository } => {
+                let repository: String = pages_repository(repository, configuration);
+                match github_app.get_pages(&repository)? {
+                    Some(pages) => {
+                        let source: String = pages
+                            .source
+                            .map(|source| format!("{} {}", source.branch, source.path))
+                            .unwrap_or_default();
+                        info!(
+                            "{} - {} - source: {} - build type: {} - custom domain: {} - HTTPS enforced: {}",
+                            pages.html_url.unwrap_or_default(),
+                            pages.status.unwrap_or_default(),
+                            source,
+                            pages.build_type.unwrap_or_default(),
+                            pages.cname.unwrap_or_default(),
+                            pages.https_enforced.unwrap_or_default()
+                        );
+                    }
+                    None => info!("GitHub Pages is not enabled for {}", repository),
+                }
+                if let Some(build) = github_app.get_latest_pages_build(&repository)? {
+                    info!(
+                        "Latest build: {:?} - commit {} - {}",
+                        build.status,
+                        build.commit.unwrap_or_default(),
+                        build.error.message.unwrap_or_default()
+                    );
+                }
+            }
+            PagesCommand::Configure {
+                repository,
+                branch,
+                path,
+                cname,
+                https_enforced,
+            } => {
+                let repository: String = pages_repository(repository, configuration);
+                let update: PagesUpdate = PagesUpdate {
+                    cname,
+                    https_enforced,
+                    source: branch
+                        .zip(path)
+                        .map(|(branch, path)| PagesSource { branch, path }),
+                };
+                github_app.update_pages(&repository, &update)?;
+                info!("Updated GitHub Pages configuration for {}", repository);
+            }
+            PagesCommand::Build { repository, wait } => {
+                let repository: String = pages_repository(repository, configuration);
+                let previous_build_url: Option<String> = github_app
+                    .get_latest_pages_build(&repository)?
+                    .map(|build| build.url);
+                let request = github_app.request_pages_build(&repository)?;
+                info!(
+                    "Requested Pages build of {}: {}",
+                    repository, request.status
+                );
+                if wait {
+                    github_app.wait_for_pages_build(
+                        &repository,
+                        Duration::from_secs(configuration.site_pages_build_timeout_seconds),
+                        |build| Some(&build.url) != previous_build_url.as_ref(),
+                    )?;
+                }
+            }
+        },
+        GitHubCommand::CheckRun { command } => match command {
+            CheckRunCommand::Create {
+                repository,
+                sha,
+                name,
+                conclusion,
+                details_url,
+                output,
+            } => {
+                let mut new_check_run: NewCheckRun = NewCheckRun::new(&name, &sha);
+                new_check_run.details_url = details_url;
+                new_check_run.output = output.into_output()?;
+                if conclusion.is_some() {
+                    new_check_run.status = CheckRunStatus::Completed;
+                    new_check_run.conclusion = conclusion;
+                    new_check_run.completed_at = Some(OffsetDateTime::now_utc());
+                }
+                let check_run = github_app.create_check_run(&repository, &new_check_run)?;
+                info!(
+                    "Created check run {} ({}) - {}",
+                    check_run.name,
+                    check_run.id,
+                    check_run.html_url.unwrap_or_default()
+                );
+            }
+            CheckRunCommand::Update {
+                repository,
+                id,
+                status,
+                conclusion,
+                details_url,
+                output,
+            } => {
+                let update: CheckRunUpdate = CheckRunUpdate {
+                    status: match conclusion {
+                        Some(_) => Some(CheckRunStatus::Completed),
+                        None => status,
+                    },
+                    details_url,
+                    conclusion,
+                    completed_at: conclusion.map(|_| OffsetDateTime::now_utc()),
+                    output: output.into_output()?,
+                };
+                let check_run = github_app.update_check_run(&repository, id, &update)?;
+                info!(
+                    "Updated check run {} ({}) - {:?} {:?}",
+                    check_run.name, check_run.id, check_run.status, check_run.conclusion
+                );
+            }
+        },
+        GitHubCommand::Secret { command } => match command {
+            SecretCommand::List { scope, pagination } => {
+                let scope: ActionsScope = scope.into_scope()?;
+                for secret in github_app.list_actions_secrets(&scope, pagination.into()) {
+                    let secret = secret?;
+                    info!("{} - updated {}", secret.name, secret.updated_at);
+                }
+            }
+            SecretCommand::Set { scope, name, value } => {
+                let scope: ActionsScope = scope.into_scope()?;
+                github_app.set_actions_secret(&scope, &name, &value.into_value()?)?;
+            }
+            SecretCommand::Delete { scope, name } => {
+                let scope: ActionsScope = scope.into_scope()?;
+                github_app.delete_actions_secret(&scope, &name)?;
+                info!("Deleted secret {} from {}", name, scope);
+            }
+        },
+        GitHubCommand::Workflow { command } => match command {
+            WorkflowCommand::Run {
+                repository,
+                workflow,
+                git_ref,
+                inputs,
+                no_wait,
+                timeout,
+                output,
+            } => {
+                let inputs: BTreeMap<String, String> = inputs.into_iter().collect();
+                let run: WorkflowRun =
+                    github_app.dispatch_workflow_run(&repository, &workflow, &git_ref, &inputs)?;
+                if !no_wait {
+                    follow_workflow_run(&github_app, &repository, run.id, timeout, &output)?;
+                }
+            }
+            WorkflowCommand::Watch {
+                repository,
+                run_id,
+                timeout,
+                output,
+            } => follow_workflow_run(&github_app, &repository, run_id, timeout, &output)?,
+            WorkflowCommand::Download {
+                repository,
+                run_id,
+                output,
+            } => output.save(&github_app, &repository, run_id)?,
+        },
+        GitHubCommand::Variable { command } => match command {
+            VariableCommand::List { scope, pagination } => {
+                let scope: ActionsScope = scope.into_scope()?;
+                for variable in github_app.list_actions_variables(&scope, pagination.into()) {
+                    let variable = variable?;
+                    info!("{}={}", variable.name, variable.value);
+                }
+            }
+            VariableCommand::Set { scope, name, value } => {
+                let scope: ActionsScope = scope.into_scope()?;
+                github_app.set_actions_variable(&scope, &name, &value.into_value()?)?;
+            }
+            VariableCommand::Delete { scope, name } => {
+                let scope: ActionsScope = scope.into_scope()?;
+                github_app.delete_actions_variable(&scope, &name)?;
+                info!("Deleted variable {} from {}", name, scope);
+            }
+        },
+    }
+
+    for (authentication, resource, rate_limit) in github_app.rate_limits() {
+        info!(
+            "GitHub rate limit ({:?} {}): {}/{} requests remaining",
+            authentication, resource, rate_limit.remaining, rate_limit.limit
+        );
+    }
+
+    Ok(())
+}
+
+/// Waits for a run to complete, saves its outputs whether or not it passed, then fails unless it
+/// succeeded.
+fn follow_workflow_run<T: HttpTransport>(
+    github_app: &GitHubApp<T>,
+    repository: &str,
+    run_id: u64,
+    timeout: u64,
+    output: &WorkflowOutputArguments,
+) -> Result<()> {
+    let run: WorkflowRun =
+        github_app.watch_workflow_run(repository, run_id, Duration::from_secs(timeout))?;
+    output.save(github_app, repository, run.id)?;
+    ensure_workflow_run_succeeded(&run)
+}
+
+fn pages_repository(repository: Option<String>, configuration: &Configuration) -> String {
+    repository.unwrap_or_else(|| configuration.get_site_pages_repository().to_string())
+}
+
+/// Replaces each directory in `paths` with the files directly inside it, in name order.
+fn expand_asset_paths(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut asset_paths: Vec<PathBuf> = vec![];
+    for path in paths {
+        match path.is_dir() {
+            true => {
+                let directory: Directory = get_directory(path)?;
+                for sub_directory_path in &directory.sub_directory_paths {
+                    warn!(
+                        "Skipping {}. Release assets are uploaded flat, so nested directories are not included.",
+                        sub_directory_path
+                    );
+                }
+                let mut file_paths: Vec<PathBuf> =
+                    directory.files_paths.iter().map(PathBuf::from).collect();
+                file_paths.sort();
+                asset_paths.extend(file_paths);
+            }
+            false => asset_paths.push(path.clone()),
+        }
+    }
+
+    Ok(asset_paths)
+}
+
+async fn run_aws_command(
+    command: AwsCommand,
+    configuration: &Configuration,
+    record: Option<&Path>,
+    replay: Option<&Path>,
+    mode: ExecutionMode,
+) -> Result<()> {
+    let aws_configuration: SdkConfig = aws_config::load_from_env().await;
+    let ec2_client: aws_sdk_ec2::Client = aws_sdk_ec2::Client::new(&aws_configuration);
+    let tag_policy: TagPolicy = TagPolicy::from_configuration(configuration);
+
+    match command {
+        AwsCommand::Ec2 { command } => match command {
+            Ec2Command::Templates { query } => {
+                let templates: Vec<LaunchTemplate> =
+                    ec2::get_ec2_launch_templates(&ec2_client, query.into_query()).await?;
+                for template in &templates {
+                    info!(
+                        "{} ({})",
+                        template.launch_template_name().unwrap_or_default(),
+                        template.launch_template_id().unwrap_or_default()
+                    );
+                }
+            }
+            Ec2Command::Vpcs { query } => {
+                let vpcs: Vec<Vpc> = ec2::get_vpcs(&ec2_client, query.into_query()).await?;
+                for vpc in &vpcs {
+                    info!(
+                        "{} {}",
+                        vpc.vpc_id().unwrap_or_default(),
+                        vpc.cidr_block().unwrap_or_default()
+                    );
+                }
+            }
+        },
+        AwsCommand::Template { command } => match command {
+            LaunchTemplateCommand::Plan { file } => {
+                let document: LaunchTemplatesDocument = load_launch_templates_document(&file)?;
+                let plans: Vec<LaunchTemplatePlan> =
+                    plan_launch_templates(&ec2_client, &document).await?;
+                log_launch_template_plans(&plans);
+            }
+            LaunchTemplateCommand::Apply {
+                file,
+                description,
+                promote,
+                deployment,
+            } => {
+                if deployment.git_ref.is_some() && !promote {
+                    return Err(anyhow!(
+                        "--ref reports a promotion to GitHub and needs --promote"
+                    ));
+                }
+                let document: LaunchTemplatesDocument = load_launch_templates_document(&file)?;
+                let plans: Vec<LaunchTemplatePlan> =
+                    plan_launch_templates(&ec2_client, &document).await?;
+                log_launch_template_plans(&plans);
+                let up_to_date: bool = plans.iter().all(|plan| {
+                    !plan.needs_new_version() && (!promote || plan.is_default_latest())
+                });
+                match up_to_date {
+                    true => info!("Launch templates already match {}", file.display()),
+                    false => {
+                        let rollout_description: String =
+                            format!("Promote launch templates from {}", file.display());
+                        track_ec2_rollout(
+                            configuration,
+                            deployment,
+                            &rollout_description,
+                            (record, replay),
+                            mode,
+                            apply_launch_template_plans(
+                                &ec2_client,
+                                plans,
+                                description,
+                                promote,
+                                &tag_policy,
+                                mode,
+                            ),
+                        )
+                        .await?
+                    }
+                }
+            }
+            LaunchTemplateCommand::Promote {
+                name,
+                version,
+                deployment,
+            } => {
+                track_ec2_rollout(
+                    configuration,
+                    deployment,
+                    &format!("Promote version {} of launch template {}", version, name),
+                    (record, replay),
+                    mode,
+                    promote_launch_template(&ec2_client, &name, version, mode),
+                )
+                .await?
+            }
+            LaunchTemplateCommand::Rollback {
+                name,
+                version,
+                deployment,
+            } => {
+                track_ec2_rollout(
+                    configuration,
+                    deployment,
+                    &format!("Roll back launch template {}", name),
+                    (record, replay),
+                    mode,
+                    roll_back_launch_template(&ec2_client, &name, version, mode),
+                )
+                .await?
+            }
+        },
+        AwsCommand::Tags { command } => match command {
+            TagCommand::Audit {
+                resource_types,
+                query,
+                backfill,
+                all: _,
+            } => {
+                let resource_types: Vec<TaggedResourceType> = match resource_types.is_empty() {
+                    true => TaggedResourceType::ALL.to_vec(),
+                    false => resource_types,
+                };
+                let findings: Vec<TagAuditFinding> = audit_tags(
+                    &ec2_client,
+                    &tag_policy,
+                    &resource_types,
+                    query.into_query(),
+                )
+                .await?;
+                for finding in &findings {
+                    info!("{}", finding);
+                }
+                match (findings.is_empty(), backfill) {
+                    (true, _) => info!("Every resource carries the required tags"),
+                    (false, false) => info!(
+                        "{} resource(s) are missing required tags. Run with --backfill to add them.",
+                        findings.len()
+                    ),
+                    (false, true) => {
+                        let tagged: usize =
+                            backfill_tags(&ec2_client, &tag_policy, &findings, mode).await?;
+                        info!("Back-filled tags on {} resource(s)", tagged);
+                    }
+                }
+            }
+        },
+        AwsCommand::Instance { command } => {
+            run_instance_command(
+                &ec2_client,
+                command,
+                configuration,
+                (record, replay),
+                &tag_policy,
+                mode,
+            )
+            .await?
+        }
+        AwsCommand::Sg { command } => match command {
+            SecurityGroupCommand::List { query } => {
+                let security_groups: Vec<SecurityGroup> =
+                    ec2::get_security_groups(&ec2_client, query.into_query()).await?;
+                for security_group in &security_groups {
+                    info!(
+                        "{} ({})",
+                        security_group.group_name().unwrap_or_default(),
+                        security_group.group_id().unwrap_or_default()
+                    );
+                }
+            }
+            SecurityGroupCommand::Plan { file } => {
+                let document: SecurityGroupsDocument = load_security_groups_document(&file)?;
+                let plans: Vec<SecurityGroupPlan> =
+                    plan_security_groups(&ec2_client, &document).await?;
+                log_security_group_plans(&plans);
+            }
+            SecurityGroupCommand::Apply { file } => {
+                let document: SecurityGroupsDocument = load_security_groups_document(&file)?;
+                let plans: Vec<SecurityGroupPlan> =
+                    plan_security_groups(&ec2_client, &document).await?;
+                log_security_group_plans(&plans);
+                match plans.iter().all(SecurityGroupPlan::is_empty) {
+                    true => info!("Security groups already match {}", file.display()),
+                    false => {
+                        apply_security_group_plans(&ec2_client, &document, plans, &tag_policy, mode)
+                            .await?
+                    }
+                }
+            }
+            SecurityGroupCommand::AddIngress(arguments) => {
+                let group_id: String =
+                    resolve_security_group_id(&ec2_client, &arguments.group).await?;
+                let result = add_security_group_ingress(
+                    &ec2_client,
+                    Some(group_id.clone()),
+                    Some(arguments.cidr.clone()),
+                    Some(arguments.protocol.clone()),
+                    Some(arguments.port),
+                    Some(arguments.to_port.unwrap_or(arguments.port)),
+                    tag_policy.tag_specifications(&[ResourceType::SecurityGroupRule], &[]),
+                    mode,
+                )
+                .await?;
+                if result.is_none() {
+                    return Ok(());
+                }
+                info!(
+                    "Authorized ingress {} {}-{} from {} on {}",
+                    arguments.protocol,
+                    arguments.port,
+                    arguments.to_port.unwrap_or(arguments.port),
+                    arguments.cidr,
+                    group_id
+                );
+            }
+            SecurityGroupCommand::AllowMyIp {
+                group,
+                port,
+                to_port,
+                protocol,
+                minutes,
+                ip,
+            } => {
+                let resolver: Box<dyn PublicIpResolver> = match ip {
+                    Some(address) => Box::new(StaticPublicIpResolver(IpAddr::V4(address))),
+                    None => Box::new(HttpPublicIpResolver::new(
+                        UreqTransport::new(),
+                        &configuration.public_ip_resolver_url,
+                    )),
+                };
+                let ingress: TemporaryIngress = TemporaryIngress {
+                    group_id: resolve_security_group_id(&ec2_client, &group).await?,
+                    protocol,
+                    from_port: port,
+                    to_port: to_port.unwrap_or(port),
+                    duration: time::Duration::minutes(minutes),
+                };
+                grant_temporary_ingress(
+                    &ec2_client,
+                    resolver.as_ref(),
+                    &ingress,
+                    &tag_policy,
+                    mode,
+                )
+                .await?;
+            }
+            SecurityGroupCommand::Sweep => {
+                let expired =
+                    sweep_expired_ingress(&ec2_client, OffsetDateTime::now_utc(), mode).await?;
+                if expired.is_empty() {
+                    info!("No temporary ingress rules have expired");
+                }
+            }
+            SecurityGroupCommand::RemoveIngress(arguments) => {
+                let group_id: String =
+                    resolve_security_group_id(&ec2_client, &arguments.group).await?;
+                let result = remove_security_group_ingress(
+                    &ec2_client,
+                    Some(group_id.clone()),
+                    Some(arguments.cidr.clone()),
+                    Some(arguments.protocol.clone()),
+                    Some(arguments.port),
+                    Some(arguments.to_port.unwrap_or(arguments.port)),
+                    mode,
+                )
+                .await?;
+                if result.is_none() {
+                    return Ok(());
+                }
+                info!(
+                    "Revoked ingress {} {}-{} from {} on {}",
+                    arguments.protocol,
+                    arguments.port,
+                    arguments.to_port.unwrap_or(arguments.port),
+                    arguments.cidr,
+                    group_id
+                );
+            }
+        },
+        AwsCommand::Publish {
+            scope,
+            role_arn,
+            security_groups,
+        } => {
+            let scope: ActionsScope = scope.into_scope()?;
+            let mut variables: Vec<(String, String)> = vec![];
+            if let Some(region) = aws_configuration.region() {
+                variables.push((
+                    GITHUB_ACTIONS_AWS_REGION_VARIABLE.to_string(),
+                    region.to_string(),
+                ));
+            }
+            if !security_groups.is_empty() {
+                let groups: Vec<SecurityGroup> = ec2::get_security_groups(
+                    &ec2_client,
+                    vec![AwsQueryType::Name(security_groups.clone())],
+                )
+                .await?;
+                for name in &security_groups {
+                    let group_id: &str = groups
+                        .iter()
+                        .find(|security_group| security_group.group_name() == Some(name))
+                        .and_then(|security_group| security_group.group_id())
+                        .ok_or_else(|| anyhow!("Security group not found: {}", name))?;
+                    variables.push((security_group_variable_name(name), group_id.to_string()));
+                }
+            }
+
+            if mode == ExecutionMode::DryRun {
+                for (name, value) in &variables {
+                    info!(
+                        "[dry run] Would set variable {}={} in {}",
+                        name, value, scope
+                    );
+                }
+                if role_arn.is_some() {
+                    info!(
+                        "[dry run] Would set secret {} in {}",
+                        GITHUB_ACTIONS_AWS_ROLE_ARN_SECRET, scope
+                    );
+                }
+                return Ok(());
+            }
+
+            let configured_app: GitHubApp<Box<dyn HttpTransport>> =
+                GitHubApp::with_transport(configuration, github_transport(record, replay)?)?;
+            let github_app: GitHubApp<Box<dyn HttpTransport>> =
+                match (configured_app.get_installation_id(), &scope) {
+                    (0, ActionsScope::Organization { organization, .. }) => {
+                        configured_app.for_account(organization)?
+                    }
+                    (0, ActionsScope::Repository(repository))
+                    | (0, ActionsScope::Environment { repository, .. }) => {
+                        configured_app.for_repository(repository)?
+                    }
+                    _ => configured_app,
+                };
+            for (name, value) in &variables {
+                github_app.set_actions_variable(&scope, name, value)?;
+            }
+            if let Some(role_arn) = role_arn {
+                github_app.set_actions_secret(
+                    &scope,
+                    GITHUB_ACTIONS_AWS_ROLE_ARN_SECRET,
+                    &role_arn,
+                )?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn run_instance_command(
+    ec2_client: &aws_sdk_ec2::Client,
+    command: InstanceCommand,
+    configuration: &Configuration,
+    cassette: (Option<&Path>, Option<&Path>),
+    tag_policy: &TagPolicy,
+    mode: ExecutionMode,
+) -> Result<()> {
+    let (selector, action, wait): (InstanceSelector, InstanceAction, InstanceWaitArguments) =
+        match command {
+            InstanceCommand::Launch {
+                template,
+                version,
+                subnet_id,
+                security_group_ids,
+                tags,
+                count,
+                wait,
+                deployment,
+            } => {
+                let request: LaunchRequest = LaunchRequest {
+                    template_name: template,
+                    template_version: version,
+                    subnet_id,
+                    security_group_ids,
+                    tags,
+                    count,
+                };
+                let launch = async {
+                    let instances: Vec<InstanceSummary> =
+                        launch_instances(ec2_client, &request, tag_policy, mode).await?;
+                    if !instances.is_empty() && !wait.no_wait {
+                        let instance_ids: Vec<String> = instances
+                            .iter()
+                            .map(|instance| instance.instance_id.clone())
+                            .collect();
+                        for instance in wait_until_instances_ready(
+                            ec2_client,
+                            instance_ids,
+                            Duration::from_secs(wait.timeout),
+                        )
+                        .await?
+                        {
+                            info!("Ready: {}", instance);
+                        }
+                    }
+                    Ok(())
+                };
+                return track_ec2_rollout(
+                    configuration,
+                    deployment,
+                    &format!(
+                        "Launch {} instance(s) from {}",
+                        request.count, request.template_name
+                    ),
+                    cassette,
+                    mode,
+                    launch,
+                )
+                .await;
+            }
+            InstanceCommand::List { target } => {
+                for instance in describe_instances(ec2_client, &target.into_selector()).await? {
+                    info!("{}", instance);
+                }
+                return Ok(());
+            }
+            InstanceCommand::Start { target, wait } => {
+                (target.into_selector(), InstanceAction::Start, wait)
+            }
+            InstanceCommand::Stop { target, wait } => {
+                (target.into_selector(), InstanceAction::Stop, wait)
+            }
+            InstanceCommand::Terminate { target, wait } => {
+                (target.into_selector(), InstanceAction::Terminate, wait)
+            }
+        };
+
+    let instances: Vec<InstanceSummary> =
+        change_instance_state(ec2_client, &selector, action, mode).await?;
+    if mode == ExecutionMode::Apply && !wait.no_wait {
+        let instance_ids: Vec<String> = instances
+            .iter()
+            .map(|instance| instance.instance_id.clone())
+            .collect();
+        for instance in wait_for_instance_action(
+            ec2_client,
+            instance_ids,
+            action,
+            Duration::from_secs(wait.timeout),
+        )
+        .await?
+        {
+            info!("{}", instance);
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs `rollout`, reporting it to GitHub as an EC2 deployment of `--ref` when one was given.
+/// Dry runs change nothing, so they are never reported.
+async fn track_ec2_rollout<F>(
+    configuration: &Configuration,
+    deployment: DeploymentArguments,
+    description: &str,
+    (record, replay): (Option<&Path>, Option<&Path>),
+    mode: ExecutionMode,
+    rollout: F,
+) -> Result<()>
+where
+    F: Future<Output = Result<()>>,
+{
+    let Some(git_ref) = deployment.git_ref else {
+        return rollout.await;
+    };
+    if mode == ExecutionMode::DryRun {
+        info!(
+            "[dry run] Would report {:?} to GitHub as a deployment of {}",
+            description, git_ref
+        );
+        return rollout.await;
+    }
+
+    let mut target: DeploymentTarget =
+        DeploymentTarget::for_ec2(configuration, &git_ref, description)?;
+    if deployment.log_url.is_some() {
+        target.log_url = deployment.log_url;
+    }
+    let configured_app: GitHubApp<Box<dyn HttpTransport>> =
+        GitHubApp::with_transport(configuration, github_transport(record, replay)?)?;
+    let github_app: GitHubApp<Box<dyn HttpTransport>> = match configured_app.get_installation_id() {
+        0 => configured_app.for_repository(&target.repository)?,
+        _ => configured_app,
+    };
+
+    track_deployment_async(&github_app, &target, rollout).await
+}
+
+/// `AWS_SECURITY_GROUP_ID_` followed by the group name upper-cased, with every character Actions
+/// doesn't allow in a variable name replaced by `_`.
+fn security_group_variable_name(group_name: &str) -> String {
+    let suffix: String = group_name
+        .chars()
+        .map(|character| match character.is_ascii_alphanumeric() {
+            true => character.to_ascii_uppercase(),
+            false => '_',
+        })
+        .collect();
+    format!(
+        "{}{}",
+        GITHUB_ACTIONS_SECURITY_GROUP_VARIABLE_PREFIX, suffix
+    )
+}
+
+async fn resolve_security_group_id(
+    client: &aws_sdk_ec2::Client,
+    selector: &SecurityGroupSelector,
+) -> Result<String> {
+    if let Some(group_id) = &selector.group_id {
+        return Ok(group_id.clone());
+    }
+
+    let Some(group_name) = &selector.group_name else {
+        return Err(anyhow!("Either a security group ID or name is required."));
+    };
+
+    let security_groups: Vec<SecurityGroup> =
+        ec2::get_security_groups(client, vec![AwsQueryType::Name(vec![group_name.clone()])])
+            .await?;
+
+    security_groups
+        .first()
+        .and_then(|security_group| security_group.group_id().map(String::from))
+        .ok_or_else(|| anyhow!("Security group not found: {}", group_name))
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::CommandFactory;
+
+    use super::*;
+
+    fn parses(arguments: &[&str]) -> bool {
+        CommandLineInterface::try_parse_from(
+            std::iter::once("lorerunner").chain(arguments.iter().copied()),
+        )
+        .is_ok()
+    }
+
+    #[test]
+    fn command_line_interface_is_well_formed() {
+        CommandLineInterface::command().debug_assert();
+    }
+
+    #[test]
+    fn backfill_needs_a_query_or_all() {
+        assert!(!parses(&["aws", "tags", "audit", "--backfill"]));
+        assert!(parses(&[
+            "aws",
+            "tags",
+            "audit",
+            "--backfill",
+            "--id",
+            "i-1"
+        ]));
+        assert!(parses(&[
+            "aws",
+            "tags",
+            "audit",
+            "--backfill",
+            "--tag",
+            "team"
+        ]));
+        assert!(parses(&["aws", "tags", "audit", "--backfill", "--all"]));
+        assert!(parses(&["aws", "tags", "audit", "--id", "i-1"]));
+    }
+
+    #[test]
+    fn all_is_only_for_backfill_and_excludes_queries() {
+        assert!(!parses(&["aws", "tags", "audit", "--all"]));
+        assert!(!parses(&[
+            "aws",
+            "tags",
+            "audit",
+            "--backfill",
+            "--all",
+            "--id",
+            "i-1"
+        ]));
+        assert!(!parses(&[
+            "aws",
+            "tags",
+            "audit",
+            "--backfill",
+            "--all",
+            "--name",
+            "web"
+        ]));
+    }
+
+    #[test]
+    fn record_and_replay_conflict() {
+        assert!(!parses(&[
+            "--record", "a.json", "--replay", "b.json", "github", "ping"
+        ]));
+        assert!(parses(&["--record", "a.json", "github", "ping"]));
+    }
+
+    #[test]
+    fn dry_run_is_only_accepted_by_aws_commands() {
+        assert!(!parses(&["--dry-run", "github", "ping"]));
+        assert!(!parses(&["github", "--dry-run", "ping"]));
+        assert!(parses(&["aws", "--dry-run", "sg", "sweep"]));
+        assert!(parses(&["aws", "sg", "sweep", "--dry-run"]));
+    }
+
+    #[test]
+    fn installation_selectors_are_exclusive() {
+        assert!(!parses(&[
+            "github",
+            "--installation",
+            "1",
+            "--account",
+            "lore",
+            "ping"
+        ]));
+        assert!(parses(&["github", "--account", "lore", "ping"]));
+    }
+
+    #[test]
+    fn environment_needs_a_repository() {
+        assert!(!parses(&[
+            "github",
+            "secret",
+            "list",
+            "--environment",
+            "staging"
+        ]));
+        assert!(parses(&[
+            "github",
+            "secret",
+            "list",
+            "--repository",
+            "lore/runner",
+            "--environment",
+            "staging",
+        ]));
+        assert!(!parses(&[
+            "github",
+            "secret",
+            "list",
+            "--repository",
+            "lore/runner",
+            "--organization",
+            "lore",
+        ]));
+    }
+
+    #[test]
+    fn allow_my_ip_accepts_only_ipv4_addresses() {
+        assert!(parses(&[
+            "aws",
+            "sg",
+            "allow-my-ip",
+            "--group-name",
+            "db",
+            "--ip",
+            "203.0.113.7"
+        ]));
+        assert!(!parses(&[
+            "aws",
+            "sg",
+            "allow-my-ip",
+            "--group-name",
+            "db",
+            "--ip",
+            "2001:db8::1"
+        ]));
+    }
+
+    #[test]
+    fn workflow_run_without_waiting_excludes_wait_options() {
+        let run: [&str; 9] = [
+            "github",
+            "workflow",
+            "run",
+            "--repository",
+            "lore/runner",
+            "--workflow",
+            "ci.yml",
+            "--ref",
+            "main",
+        ];
+        assert!(parses(&run));
+        assert!(parses(&[&run[..], &["--no-wait"]].concat()));
+        assert!(!parses(
+            &[&run[..], &["--no-wait", "--timeout", "60"]].concat()
+        ));
+    }
+}
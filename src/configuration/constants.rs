@@ -1,5 +1,8 @@
 pub mod project_file_paths {
     pub const CONFIGURATION_FILE_PATH: &str = "./private/configuration.ron";
+    pub const DEFAULT_WEBHOOK_QUEUE_PATH: &str = "./private/webhook_queue";
+    pub const DEFAULT_SECURITY_GROUPS_FILE_PATH: &str = "./private/security_groups.ron";
+    pub const DEFAULT_LAUNCH_TEMPLATES_FILE_PATH: &str = "./private/launch_templates.ron";
     pub const FRONTEND_PATH: &str = "";
     pub const FRONTEND_PKG_PATH: &str = "";
     pub const GITHUB_PAGES_REPOSITORY_PATH: &str = "";
@@ -19,17 +22,130 @@ pub mod command_line {
 pub mod http {
     pub const HTTP_HEADER_ACCEPT: &str = "Accept";
     pub const HTTP_HEADER_AUTHORIZATION: &str = "Authorization";
+    pub const HTTP_HEADER_RETRY_AFTER: &str = "Retry-After";
+    pub const HTTP_HEADER_LINK: &str = "Link";
+    pub const HTTP_HEADER_CONTENT_TYPE: &str = "Content-Type";
+    pub const JSON_CONTENT_TYPE: &str = "application/json";
+    pub const OCTET_STREAM_CONTENT_TYPE: &str = "application/octet-stream";
+    /// Written in place of secrets when a cassette is recorded.
+    pub const CASSETTE_REDACTED_VALUE: &str = "redacted";
     pub mod github {
         pub const HTTP_HEADER_GITHUB_API_VERSION: &str = "X-GitHub-Api-Version";
+        pub const HTTP_HEADER_GITHUB_DELIVERY: &str = "X-GitHub-Delivery";
+        pub const HTTP_HEADER_GITHUB_EVENT: &str = "X-GitHub-Event";
+        pub const HTTP_HEADER_HUB_SIGNATURE_256: &str = "X-Hub-Signature-256";
+        pub const HTTP_HEADER_RATE_LIMIT_LIMIT: &str = "X-RateLimit-Limit";
+        pub const HTTP_HEADER_RATE_LIMIT_REMAINING: &str = "X-RateLimit-Remaining";
+        pub const HTTP_HEADER_RATE_LIMIT_RESET: &str = "X-RateLimit-Reset";
+        pub const HTTP_HEADER_RATE_LIMIT_USED: &str = "X-RateLimit-Used";
+        pub const HTTP_HEADER_RATE_LIMIT_RESOURCE: &str = "X-RateLimit-Resource";
     }
 }
 
 pub mod github {
     pub const MAX_GITHUB_JWT_EXPIRATION_MINUTES: u64 = 10;
-    pub const GITHUB_API_URL: &str = "https://api.github.com";
+    /// How far `iat` is backdated to tolerate the local clock running ahead of GitHub's.
+    pub const GITHUB_JWT_CLOCK_DRIFT_SECONDS: u64 = 60;
+    pub const GITHUB_APP_PRIVATE_KEY_MIN_BITS: usize = 2048;
+    pub const GITHUB_TOKEN_REFRESH_MARGIN_SECONDS: i64 = 60;
+    /// Requests left in the window at which lorerunner pauses until the window resets.
+    pub const GITHUB_RATE_LIMIT_RESERVE: u32 = 5;
+    pub const GITHUB_RATE_LIMIT_MAX_RETRIES: u32 = 3;
+    pub const GITHUB_DEFAULT_PER_PAGE: u32 = 30;
+    pub const GITHUB_MAX_PER_PAGE: u32 = 100;
+    /// GitHub's documented wait for secondary rate limits that don't send `Retry-After`.
+    pub const GITHUB_SECONDARY_RATE_LIMIT_WAIT_SECONDS: u64 = 60;
+    /// Phrase in the message of a 403 that hit a secondary rate limit rather than a permission.
+    pub const GITHUB_SECONDARY_RATE_LIMIT_MESSAGE: &str = "secondary rate limit";
+    /// `X-RateLimit-Resource` buckets, each with its own budget.
+    pub const GITHUB_CORE_RATE_LIMIT_RESOURCE: &str = "core";
+    pub const GITHUB_SEARCH_RATE_LIMIT_RESOURCE: &str = "search";
+    pub const GITHUB_CODE_SEARCH_RATE_LIMIT_RESOURCE: &str = "code_search";
+    pub const GITHUB_GRAPHQL_RATE_LIMIT_RESOURCE: &str = "graphql";
+    pub const GITHUB_SEARCH_API_ROUTE: &str = "/search";
+    pub const GITHUB_CODE_SEARCH_API_ROUTE: &str = "/search/code";
+    pub const GITHUB_GRAPHQL_API_ROUTE: &str = "/graphql";
+    pub const DEFAULT_GITHUB_API_URL: &str = "https://api.github.com";
+    pub const DEFAULT_GITHUB_UPLOAD_URL: &str = "https://uploads.github.com";
     pub const GITHUB_API_VERSION: &str = "2022-11-28";
     pub const GET_GITHUB_APP_API_ROUTE: &str = "/app";
+    pub const GITHUB_APP_INSTALLATIONS_API_ROUTE: &str = "/app/installations";
+    pub const GITHUB_ACCESS_TOKENS_API_ROUTE: &str = "/access_tokens";
+    pub const GITHUB_INSTALLATION_API_ROUTE: &str = "/installation";
+    pub const GITHUB_ORGANIZATIONS_API_ROUTE: &str = "/orgs";
+    pub const GITHUB_USERS_API_ROUTE: &str = "/users";
+    pub const GITHUB_INSTALLATION_REPOSITORIES_API_ROUTE: &str = "/installation/repositories";
+    pub const GITHUB_RATE_LIMIT_API_ROUTE: &str = "/rate_limit";
+    pub const GITHUB_REPOSITORIES_API_ROUTE: &str = "/repos";
+    pub const GITHUB_DEPLOYMENTS_API_ROUTE: &str = "/deployments";
+    pub const GITHUB_DEPLOYMENT_STATUSES_API_ROUTE: &str = "/statuses";
+    pub const GITHUB_RELEASES_API_ROUTE: &str = "/releases";
+    pub const GITHUB_RELEASE_TAGS_API_ROUTE: &str = "/releases/tags";
+    pub const GITHUB_RELEASE_ASSETS_API_ROUTE: &str = "/releases/assets";
+    pub const GITHUB_GENERATE_RELEASE_NOTES_API_ROUTE: &str = "/releases/generate-notes";
+    pub const GITHUB_ASSETS_API_ROUTE: &str = "/assets";
+    pub const GITHUB_GIT_BLOBS_API_ROUTE: &str = "/git/blobs";
+    pub const GITHUB_GIT_COMMITS_API_ROUTE: &str = "/git/commits";
+    pub const GITHUB_GIT_REF_API_ROUTE: &str = "/git/ref";
+    pub const GITHUB_GIT_REFS_API_ROUTE: &str = "/git/refs";
+    pub const GITHUB_GIT_TREES_API_ROUTE: &str = "/git/trees";
+    pub const GIT_BRANCH_REF_PREFIX: &str = "refs/heads/";
+    pub const GIT_REGULAR_FILE_MODE: &str = "100644";
+    pub const GIT_BLOB_OBJECT_TYPE: &str = "blob";
+    pub const GIT_TREE_OBJECT_TYPE: &str = "tree";
+    pub const GITHUB_PAGES_API_ROUTE: &str = "/pages";
+    pub const GITHUB_PAGES_BUILDS_API_ROUTE: &str = "/pages/builds";
+    pub const GITHUB_PAGES_LATEST_BUILD_API_ROUTE: &str = "/pages/builds/latest";
+    pub const GITHUB_PAGES_WORKFLOW_BUILD_TYPE: &str = "workflow";
+    pub const GITHUB_PAGES_BUILD_POLL_INTERVAL_SECONDS: u64 = 10;
+    pub const DEFAULT_SITE_PAGES_BUILD_TIMEOUT_SECONDS: u64 = 600;
+    pub const GITHUB_ACTIONS_API_ROUTE: &str = "/actions";
+    pub const GITHUB_ENVIRONMENTS_API_ROUTE: &str = "/environments";
+    pub const GITHUB_SECRETS_API_ROUTE: &str = "/secrets";
+    pub const GITHUB_SECRETS_PUBLIC_KEY_API_ROUTE: &str = "/secrets/public-key";
+    pub const GITHUB_VARIABLES_API_ROUTE: &str = "/variables";
+    /// Actions variable and secret names `aws publish` writes.
+    pub const GITHUB_ACTIONS_AWS_REGION_VARIABLE: &str = "AWS_REGION";
+    pub const GITHUB_ACTIONS_AWS_ROLE_ARN_SECRET: &str = "AWS_ROLE_ARN";
+    pub const GITHUB_ACTIONS_SECURITY_GROUP_VARIABLE_PREFIX: &str = "AWS_SECURITY_GROUP_ID_";
+    pub const DEFAULT_GITHUB_ACTIONS_ORGANIZATION_VISIBILITY: &str = "private";
+    /// Organization secret and variable visibilities lorerunner can write. `selected` also needs
+    /// the list of repositories, which isn't supported.
+    pub const GITHUB_ACTIONS_ORGANIZATION_VISIBILITIES: [&str; 2] = ["all", "private"];
+    pub const GITHUB_WORKFLOWS_API_ROUTE: &str = "/actions/workflows";
+    pub const GITHUB_WORKFLOW_DISPATCHES_API_ROUTE: &str = "/dispatches";
+    pub const GITHUB_WORKFLOW_RUNS_API_ROUTE: &str = "/actions/runs";
+    pub const GITHUB_WORKFLOW_ARTIFACTS_API_ROUTE: &str = "/actions/artifacts";
+    pub const GITHUB_JOBS_API_ROUTE: &str = "/jobs";
+    pub const GITHUB_LOGS_API_ROUTE: &str = "/logs";
+    pub const GITHUB_ARTIFACTS_API_ROUTE: &str = "/artifacts";
+    pub const GITHUB_ZIP_API_ROUTE: &str = "/zip";
+    pub const GITHUB_WORKFLOW_DISPATCH_EVENT: &str = "workflow_dispatch";
+    pub const GITHUB_WORKFLOW_RUN_POLL_INTERVAL_SECONDS: u64 = 5;
+    /// How long a dispatched run may take to show up before the dispatch is considered lost.
+    pub const GITHUB_WORKFLOW_DISPATCH_DISCOVERY_TIMEOUT_SECONDS: u64 = 60;
+    pub const DEFAULT_GITHUB_WORKFLOW_RUN_TIMEOUT_SECONDS: u64 = 3600;
+    pub const GITHUB_CHECK_RUNS_API_ROUTE: &str = "/check-runs";
+    /// GitHub accepts at most this many annotations per check run create or update request.
+    pub const GITHUB_CHECK_RUN_MAX_ANNOTATIONS_PER_REQUEST: usize = 50;
+    pub const GITHUB_CHECK_RUN_MAX_SUMMARY_CHARACTERS: usize = 65535;
+    pub const GITHUB_CHECK_RUN_MAX_TEXT_CHARACTERS: usize = 65535;
+    pub const SITE_BUILD_CHECK_RUN_NAME: &str = "lorerunner / site build";
+    /// Recent deployments checked for a lingering active status after a new one succeeds.
+    pub const GITHUB_PREVIOUS_DEPLOYMENTS_SCAN_LIMIT: usize = 30;
     pub const GITHUB_API_ACCEPT_HEADER_VALUE: &str = "application/vnd.github+json";
+    pub const GITHUB_WEBHOOK_ROUTE: &str = "/webhooks";
+    pub const GITHUB_WEBHOOK_SIGNATURE_PREFIX: &str = "sha256=";
+    pub const DEFAULT_WEBHOOK_LISTEN_ADDRESS: &str = "0.0.0.0:3000";
+    pub const DEFAULT_SITE_BRANCH: &str = "main";
+    pub const DEFAULT_SITE_ENVIRONMENT: &str = "github-pages";
+    pub const DEFAULT_SITE_PAGES_BRANCH: &str = "gh-pages";
+    /// Files on the pages branch that aren't part of the export but must survive a publish.
+    pub const DEFAULT_SITE_PAGES_PRESERVED_PATHS: [&str; 1] = ["CNAME"];
+    pub const DEFAULT_WEBHOOK_MAX_ATTEMPTS: u32 = 5;
+    pub const WEBHOOK_RETRY_BACKOFF_BASE_SECONDS: i64 = 30;
+    pub const WEBHOOK_RETRY_BACKOFF_MAX_SECONDS: i64 = 3600;
+    pub const WEBHOOK_QUEUE_POLL_INTERVAL_SECONDS: u64 = 5;
 }
 
 pub mod environment_variables {
@@ -42,13 +158,41 @@ pub mod amazon_web_services {
     pub const RUST_ARM_NANO_TEMPLATE_NAME: &str = "rust-arm";
     pub const HTTPS_ALLOWED_SECURITY_GROUP_NAME: &str = "HTTPS Allowed";
     pub const PUBLIC_WEB_SERVER_SECURITY_GROUP_NAME: &str = "Public Web Server";
+    /// Required tag marking the EC2 resources lorerunner created.
+    pub const MANAGED_BY_TAG_KEY: &str = "managed-by";
+    pub const MANAGED_BY_TAG_VALUE: &str = "lorerunner";
+    /// Tag EC2 shows as a resource's name.
+    pub const NAME_TAG_KEY: &str = "Name";
+    /// System tags EC2 adds to instances launched from a launch template.
+    pub const LAUNCH_TEMPLATE_ID_TAG_KEY: &str = "aws:ec2launchtemplate:id";
+    pub const LAUNCH_TEMPLATE_VERSION_TAG_KEY: &str = "aws:ec2launchtemplate:version";
+    /// Instance states that can still be started, stopped or terminated.
+    pub const LIVE_INSTANCE_STATES: [&str; 5] =
+        ["pending", "running", "shutting-down", "stopping", "stopped"];
+    /// Version specifiers EC2 resolves to a launch template's newest and default versions.
+    pub const LATEST_LAUNCH_TEMPLATE_VERSION: &str = "$Latest";
+    pub const DEFAULT_LAUNCH_TEMPLATE_VERSION: &str = "$Default";
+    /// Launch template tag recording the default version a promotion replaced.
+    pub const PREVIOUS_DEFAULT_VERSION_TAG_KEY: &str = "lorerunner:previous-default-version";
+    /// Tag holding the RFC 3339 time after which `aws sg sweep` revokes a temporary rule.
+    pub const EXPIRES_AT_TAG_KEY: &str = "lorerunner:expires-at";
+    pub const DEFAULT_TEMPORARY_INGRESS_MINUTES: i64 = 60;
+    /// GitHub deployment environment EC2 rollouts are reported under by default.
+    pub const DEFAULT_EC2_ENVIRONMENT: &str = "ec2";
+    /// How long instance commands wait for instances to reach the requested state.
+    pub const DEFAULT_INSTANCE_WAIT_TIMEOUT_SECONDS: u64 = 900;
 }
 
 pub mod networking {
     pub const TCP_PROTOCOL: &str = "tcp";
+    /// Protocol AWS uses for rules that allow all traffic.
+    pub const ALL_PROTOCOLS: &str = "-1";
     pub const HTTP_PORT: i32 = 80_i32;
     pub const HTTPS_PORT: i32 = 443_i32;
+    pub const SSH_PORT: i32 = 22_i32;
     pub const POSTGRES_PORT: i32 = 5432_i32;
     pub const ANYWHERE_IPV4: &str = "0.0.0.0/0";
     pub const ANYWHERE_IPV6: &str = "::/0";
+    /// Answers with the caller's public IPv4 address as plain text.
+    pub const DEFAULT_PUBLIC_IP_RESOLVER_URL: &str = "https://checkip.amazonaws.com";
 }
@@ -1,13 +1,24 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use env_logger::{Builder, Target};
 use log::LevelFilter;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::io::Write;
 use time::{format_description::well_known::Rfc3339, OffsetDateTime};
 
 use crate::file_system::{create_file_if_missing, get_file_content_as_string, CreateFileResult};
 
-use self::constants::project_file_paths::CONFIGURATION_FILE_PATH;
+use self::constants::amazon_web_services::{
+    DEFAULT_EC2_ENVIRONMENT, MANAGED_BY_TAG_KEY, MANAGED_BY_TAG_VALUE,
+};
+use self::constants::github::{
+    DEFAULT_GITHUB_API_URL, DEFAULT_GITHUB_UPLOAD_URL, DEFAULT_SITE_BRANCH,
+    DEFAULT_SITE_ENVIRONMENT, DEFAULT_SITE_PAGES_BRANCH, DEFAULT_SITE_PAGES_BUILD_TIMEOUT_SECONDS,
+    DEFAULT_SITE_PAGES_PRESERVED_PATHS, DEFAULT_WEBHOOK_LISTEN_ADDRESS,
+    DEFAULT_WEBHOOK_MAX_ATTEMPTS,
+};
+use self::constants::networking::DEFAULT_PUBLIC_IP_RESOLVER_URL;
+use self::constants::project_file_paths::{CONFIGURATION_FILE_PATH, DEFAULT_WEBHOOK_QUEUE_PATH};
 
 pub mod constants;
 
@@ -15,8 +26,120 @@ pub mod constants;
 pub struct Configuration {
     pub log_level: LevelFilter,
     pub github_app_private_key_path: String,
+    /// More GitHub App private keys, tried in order after `github_app_private_key_path` when
+    /// GitHub rejects a JSON web token. Lets the app key be rotated without downtime.
+    #[serde(default)]
+    pub github_app_additional_private_key_paths: Vec<String>,
     pub github_app_id: u32,
     pub github_app_installation_id: u32,
+    /// Base URL of the GitHub REST API, e.g. `https://github.example.com/api/v3` for GitHub
+    /// Enterprise Server.
+    #[serde(default = "default_github_api_url")]
+    pub github_api_url: String,
+    /// Base URL for release asset uploads, e.g. `https://github.example.com/api/uploads` for
+    /// GitHub Enterprise Server.
+    #[serde(default = "default_github_upload_url")]
+    pub github_upload_url: String,
+    /// Secret configured on the GitHub App webhook, used to verify `X-Hub-Signature-256`.
+    #[serde(default)]
+    pub github_webhook_secret: String,
+    #[serde(default = "default_webhook_listen_address")]
+    pub webhook_listen_address: String,
+    /// Directory holding pending, processed and dead-lettered webhook deliveries.
+    #[serde(default = "default_webhook_queue_path")]
+    pub webhook_queue_path: String,
+    /// Handler attempts before a delivery is moved to the dead-letter area.
+    #[serde(default = "default_webhook_max_attempts")]
+    pub webhook_max_attempts: u32,
+    /// `owner/name` of the repository whose pushes trigger a site deploy.
+    #[serde(default)]
+    pub site_repository: String,
+    #[serde(default = "default_site_branch")]
+    pub site_branch: String,
+    /// GitHub deployment environment that site deploys are reported under.
+    #[serde(default = "default_site_environment")]
+    pub site_environment: String,
+    /// Public URL of the deployed site, linked from its GitHub deployments.
+    #[serde(default)]
+    pub site_url: String,
+    /// Directory of the Perseus app within `site_repository`, such as `frontend`, so build
+    /// diagnostics annotate the right files. Empty when the app is at the repository root.
+    #[serde(default)]
+    pub site_source_directory: String,
+    #[serde(default)]
+    pub site_publish_method: SitePublishMethod,
+    /// `owner/name` of the GitHub Pages repository. Defaults to `site_repository` when empty.
+    #[serde(default)]
+    pub site_pages_repository: String,
+    #[serde(default = "default_site_pages_branch")]
+    pub site_pages_branch: String,
+    /// Paths on the pages branch kept when the exported site replaces its contents.
+    #[serde(default = "default_site_pages_preserved_paths")]
+    pub site_pages_preserved_paths: Vec<String>,
+    /// How long a site deploy waits for GitHub Pages to build what was published. `0` skips
+    /// waiting.
+    #[serde(default = "default_site_pages_build_timeout_seconds")]
+    pub site_pages_build_timeout_seconds: u64,
+    /// Link to a deploy's logs, shown on its GitHub deployment statuses. `{environment}` and
+    /// `{ref}` are replaced with the deployment's environment and ref. `--log-url` overrides it.
+    #[serde(default)]
+    pub deployment_log_url: String,
+    /// `owner/name` of the repository EC2 rollouts are reported on. Defaults to
+    /// `site_repository` when empty.
+    #[serde(default)]
+    pub ec2_repository: String,
+    /// GitHub deployment environment that EC2 rollouts are reported under.
+    #[serde(default = "default_ec2_environment")]
+    pub ec2_environment: String,
+    /// URL linked from EC2 deployments, such as the load balancer in front of the instances.
+    #[serde(default)]
+    pub ec2_environment_url: String,
+    /// Tags every EC2 resource lorerunner creates carries, such as `project` and `environment`.
+    /// A tag with an empty value is only required by `aws tags audit`, since there is no value
+    /// to apply.
+    #[serde(default = "default_aws_required_tags")]
+    pub aws_required_tags: BTreeMap<String, String>,
+    /// Service answering with the caller's public IP address as plain text, used by
+    /// `aws sg allow-my-ip`.
+    #[serde(default = "default_public_ip_resolver_url")]
+    pub public_ip_resolver_url: String,
+}
+
+/// How the exported static site reaches the GitHub Pages branch.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SitePublishMethod {
+    /// Copy the export into the pages repository checked out at `GITHUB_PAGES_REPOSITORY_PATH`.
+    #[default]
+    LocalRepository,
+    /// Commit the export straight to the pages branch through the GitHub Git Data API.
+    GitDataApi,
+}
+
+impl Configuration {
+    /// Every configured GitHub App private key path, primary key first.
+    pub fn get_github_app_private_key_paths(&self) -> Vec<String> {
+        std::iter::once(&self.github_app_private_key_path)
+            .chain(&self.github_app_additional_private_key_paths)
+            .filter(|path| !path.is_empty())
+            .cloned()
+            .collect()
+    }
+
+    /// The GitHub Pages repository, falling back to `site_repository`.
+    pub fn get_site_pages_repository(&self) -> &str {
+        match self.site_pages_repository.is_empty() {
+            true => &self.site_repository,
+            false => &self.site_pages_repository,
+        }
+    }
+
+    /// The repository EC2 rollouts are reported on, falling back to `site_repository`.
+    pub fn get_ec2_repository(&self) -> &str {
+        match self.ec2_repository.is_empty() {
+            true => &self.site_repository,
+            false => &self.ec2_repository,
+        }
+    }
 }
 
 impl Configuration {
@@ -24,31 +147,116 @@ impl Configuration {
         Configuration {
             log_level: LevelFilter::Debug,
             github_app_private_key_path: String::new(),
+            github_app_additional_private_key_paths: vec![],
             github_app_id: 0_u32,
             github_app_installation_id: 0_u32,
+            github_api_url: default_github_api_url(),
+            github_upload_url: default_github_upload_url(),
+            github_webhook_secret: String::new(),
+            webhook_listen_address: default_webhook_listen_address(),
+            webhook_queue_path: default_webhook_queue_path(),
+            webhook_max_attempts: default_webhook_max_attempts(),
+            site_repository: String::new(),
+            site_branch: default_site_branch(),
+            site_environment: default_site_environment(),
+            site_url: String::new(),
+            site_source_directory: String::new(),
+            site_publish_method: SitePublishMethod::default(),
+            site_pages_repository: String::new(),
+            site_pages_branch: default_site_pages_branch(),
+            site_pages_preserved_paths: default_site_pages_preserved_paths(),
+            site_pages_build_timeout_seconds: default_site_pages_build_timeout_seconds(),
+            deployment_log_url: String::new(),
+            ec2_repository: String::new(),
+            ec2_environment: default_ec2_environment(),
+            ec2_environment_url: String::new(),
+            aws_required_tags: default_aws_required_tags(),
+            public_ip_resolver_url: default_public_ip_resolver_url(),
         }
     }
 }
 
+impl Default for Configuration {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn default_github_api_url() -> String {
+    DEFAULT_GITHUB_API_URL.to_string()
+}
+
+fn default_github_upload_url() -> String {
+    DEFAULT_GITHUB_UPLOAD_URL.to_string()
+}
+
+fn default_webhook_listen_address() -> String {
+    DEFAULT_WEBHOOK_LISTEN_ADDRESS.to_string()
+}
+
+fn default_webhook_queue_path() -> String {
+    DEFAULT_WEBHOOK_QUEUE_PATH.to_string()
+}
+
+fn default_webhook_max_attempts() -> u32 {
+    DEFAULT_WEBHOOK_MAX_ATTEMPTS
+}
+
+fn default_site_branch() -> String {
+    DEFAULT_SITE_BRANCH.to_string()
+}
+
+fn default_site_environment() -> String {
+    DEFAULT_SITE_ENVIRONMENT.to_string()
+}
+
+fn default_site_pages_branch() -> String {
+    DEFAULT_SITE_PAGES_BRANCH.to_string()
+}
+
+fn default_site_pages_preserved_paths() -> Vec<String> {
+    DEFAULT_SITE_PAGES_PRESERVED_PATHS
+        .iter()
+        .map(|path| path.to_string())
+        .collect()
+}
+
+fn default_site_pages_build_timeout_seconds() -> u64 {
+    DEFAULT_SITE_PAGES_BUILD_TIMEOUT_SECONDS
+}
+
+fn default_ec2_environment() -> String {
+    DEFAULT_EC2_ENVIRONMENT.to_string()
+}
+
+fn default_aws_required_tags() -> BTreeMap<String, String> {
+    BTreeMap::from([(
+        MANAGED_BY_TAG_KEY.to_string(),
+        MANAGED_BY_TAG_VALUE.to_string(),
+    )])
+}
+
+fn default_public_ip_resolver_url() -> String {
+    DEFAULT_PUBLIC_IP_RESOLVER_URL.to_string()
+}
+
 pub fn get_application_configuration() -> Result<Configuration> {
     if let CreateFileResult::FileCreated =
         create_file_if_missing(CONFIGURATION_FILE_PATH.to_string())?
     {
-        let file_content: String = ron::to_string(&Configuration::new()).expect(
-            "Failed to create default application configuration file when one was not found",
-        );
+        let file_content: String = ron::to_string(&Configuration::new())
+            .map_err(|error| anyhow!("Failed to serialize the default configuration: {}", error))?;
         std::fs::write(CONFIGURATION_FILE_PATH, file_content)?;
-        panic!("Application configuration file not found: {}\nCreating new file with default configuration.", CONFIGURATION_FILE_PATH);
+        return Err(anyhow!(
+            "Application configuration file not found: {}. Created it with the default \
+             configuration; fill it in and run again.",
+            CONFIGURATION_FILE_PATH
+        ));
     }
     let configuration_file_content: String =
         get_file_content_as_string(CONFIGURATION_FILE_PATH.to_string())?;
-    let configuration: Configuration = ron::from_str(&configuration_file_content).expect(
-        format!(
-            "Failed to parse ron configuration from file: {}",
-            CONFIGURATION_FILE_PATH
-        )
-        .as_str(),
-    );
+    let configuration: Configuration = ron::from_str(&configuration_file_content)
+        .map_err(|error| anyhow!("Failed to parse {}: {}", CONFIGURATION_FILE_PATH, error))?;
     Ok(configuration)
 }
 
@@ -1,13 +1,17 @@
 use anyhow::{anyhow, Result};
 use log::info;
 use std::fs::read_dir;
+use std::io::Cursor;
 use std::time::SystemTime;
 use std::{
     fs::ReadDir,
     path::{Path, PathBuf},
 };
 
-use crate::configuration::constants::project_file_paths::{DIST_FOLDER, FRONTEND_PATH, PKG_FOLDER};
+use crate::configuration::constants::{
+    http::OCTET_STREAM_CONTENT_TYPE,
+    project_file_paths::{DIST_FOLDER, FRONTEND_PATH, PKG_FOLDER},
+};
 
 pub fn get_directory<PATH>(directory_path: PATH) -> Result<Directory>
 where
@@ -97,8 +101,91 @@ pub fn get_file_content_as_string(file_path: String) -> Result<String> {
     Ok(std::fs::read_to_string(target_path)?)
 }
 
-pub fn copy_directory_to() -> Result<()> {
-    Ok(())
+pub fn get_file_content_as_bytes(file_path: &Path) -> Result<Vec<u8>> {
+    if !file_path.is_file() {
+        return Err(anyhow!(
+            "Failed to get file contents -- specified path is not a file: {}",
+            file_path.to_str().unwrap_or("invalid path string")
+        ));
+    }
+
+    Ok(std::fs::read(file_path)?)
+}
+
+/// Guesses a file's media type from its extension, for uploads that must declare one.
+pub fn get_content_type(file_path: &Path) -> &'static str {
+    let extension: String = file_path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+
+    match extension.as_str() {
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "js" | "mjs" => "text/javascript",
+        "json" | "map" => "application/json",
+        "wasm" => "application/wasm",
+        "txt" | "md" => "text/plain",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "ico" => "image/x-icon",
+        "woff2" => "font/woff2",
+        "zip" => "application/zip",
+        "gz" | "tgz" => "application/gzip",
+        "tar" => "application/x-tar",
+        _ => OCTET_STREAM_CONTENT_TYPE,
+    }
+}
+
+/// Extracts the zip archive in `content` into `destination`, creating it if needed. Entries whose
+/// paths would escape `destination` are rejected. Returns how many files were extracted.
+pub fn extract_zip_archive(content: &[u8], destination: &Path) -> Result<usize> {
+    let mut archive: zip::ZipArchive<Cursor<&[u8]>> = zip::ZipArchive::new(Cursor::new(content))?;
+    std::fs::create_dir_all(destination)?;
+    archive.extract(destination)?;
+
+    let file_count: usize = archive
+        .file_names()
+        .filter(|name| !name.ends_with('/'))
+        .count();
+    info!(
+        "Extracted {} file(s) to {}",
+        file_count,
+        destination.to_str().unwrap_or("invalid path string")
+    );
+    Ok(file_count)
+}
+
+/// Copies everything under `source` into `destination`, creating directories as needed and
+/// overwriting files that already exist. Returns how many files were copied.
+pub fn copy_directory_to(source: &Path, destination: &Path) -> Result<usize> {
+    if !source.is_dir() {
+        return Err(anyhow!(
+            "Cannot copy directory. The provided path ({}) is not a directory.",
+            source.to_str().unwrap_or("invalid path string")
+        ));
+    }
+
+    std::fs::create_dir_all(destination)?;
+    let mut copied: usize = 0;
+    for entry in read_dir(source)? {
+        let entry_path: PathBuf = entry?.path();
+        let Some(file_name) = entry_path.file_name() else {
+            continue;
+        };
+        let target_path: PathBuf = destination.join(file_name);
+        match entry_path.is_dir() {
+            true => copied += copy_directory_to(&entry_path, &target_path)?,
+            false => {
+                std::fs::copy(&entry_path, &target_path)?;
+                copied += 1;
+            }
+        }
+    }
+
+    Ok(copied)
 }
 
 pub struct Directory {
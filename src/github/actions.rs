@@ -0,0 +1,429 @@
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use crypto_box::{aead::OsRng, PublicKey};
+use log::info;
+use serde::Serialize;
+
+use crate::{
+    configuration::constants::github::{
+        GITHUB_ACTIONS_API_ROUTE, GITHUB_ACTIONS_ORGANIZATION_VISIBILITIES,
+        GITHUB_ENVIRONMENTS_API_ROUTE, GITHUB_ORGANIZATIONS_API_ROUTE, GITHUB_SECRETS_API_ROUTE,
+        GITHUB_SECRETS_PUBLIC_KEY_API_ROUTE, GITHUB_VARIABLES_API_ROUTE,
+    },
+    http::HttpTransport,
+};
+
+use super::{
+    deployments::validate_repository,
+    error::GitHubError,
+    github_app::{repository_route, Authentication, GitHubApp},
+    models::{ActionsPublicKey, ActionsSecret, ActionsSecrets, ActionsVariable, ActionsVariables},
+    pagination::{PaginationOptions, Paginator},
+};
+
+/// Where an Actions secret or variable lives.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ActionsScope {
+    /// `owner/name` of a repository.
+    Repository(String),
+    /// A deployment environment of an `owner/name` repository.
+    Environment {
+        repository: String,
+        environment: String,
+    },
+    /// An organization login. `visibility` decides which of its repositories can read values
+    /// written through this scope: `all` or `private`.
+    Organization {
+        organization: String,
+        visibility: String,
+    },
+}
+
+impl ActionsScope {
+    /// Checks that the scope names something that can be spliced into a route.
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            ActionsScope::Repository(repository) => validate_repository(repository),
+            ActionsScope::Environment {
+                repository,
+                environment,
+            } => match environment.is_empty() {
+                true => Err(anyhow!("Expected an environment name for {}", repository)),
+                false => validate_repository(repository),
+            },
+            ActionsScope::Organization {
+                organization,
+                visibility,
+            } => {
+                if organization.is_empty() || organization.contains('/') {
+                    return Err(anyhow!(
+                        "Expected an organization login, got {:?}",
+                        organization
+                    ));
+                }
+                match GITHUB_ACTIONS_ORGANIZATION_VISIBILITIES.contains(&visibility.as_str()) {
+                    true => Ok(()),
+                    false => Err(anyhow!(
+                        "Expected visibility {}, got {:?}. Values visible to selected \
+                         repositories have to be set up on GitHub.",
+                        GITHUB_ACTIONS_ORGANIZATION_VISIBILITIES.join(" or "),
+                        visibility
+                    )),
+                }
+            }
+        }
+    }
+
+    /// Route the `/secrets` and `/variables` endpoints of the scope hang off.
+    fn route(&self) -> String {
+        match self {
+            ActionsScope::Repository(repository) => format!(
+                "{}{}",
+                repository_route(repository),
+                GITHUB_ACTIONS_API_ROUTE
+            ),
+            ActionsScope::Environment {
+                repository,
+                environment,
+            } => format!(
+                "{}{}/{}",
+                repository_route(repository),
+                GITHUB_ENVIRONMENTS_API_ROUTE,
+                urlencoding::encode(environment)
+            ),
+            ActionsScope::Organization { organization, .. } => format!(
+                "{}/{}{}",
+                GITHUB_ORGANIZATIONS_API_ROUTE, organization, GITHUB_ACTIONS_API_ROUTE
+            ),
+        }
+    }
+
+    fn visibility(&self) -> Option<&str> {
+        match self {
+            ActionsScope::Organization { visibility, .. } => Some(visibility),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for ActionsScope {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ActionsScope::Repository(repository) => write!(formatter, "{}", repository),
+            ActionsScope::Environment {
+                repository,
+                environment,
+            } => write!(formatter, "{} ({})", repository, environment),
+            ActionsScope::Organization { organization, .. } => {
+                write!(formatter, "organization {}", organization)
+            }
+        }
+    }
+}
+
+/// Body of `PUT .../secrets/{secret_name}`.
+#[derive(Debug, Clone, Serialize)]
+struct EncryptedSecret<'a> {
+    /// Base64 encoded sealed box of the value.
+    encrypted_value: String,
+    key_id: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    visibility: Option<&'a str>,
+}
+
+/// Body of `POST .../variables` and `PATCH .../variables/{name}`.
+#[derive(Debug, Clone, Serialize)]
+struct NewVariable<'a> {
+    name: &'a str,
+    value: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    visibility: Option<&'a str>,
+}
+
+impl<T: HttpTransport> GitHubApp<T> {
+    /// Returns the key secrets written to `scope` must be sealed with.
+    pub fn get_actions_public_key(
+        &self,
+        scope: &ActionsScope,
+    ) -> Result<ActionsPublicKey, GitHubError> {
+        self.get(
+            Authentication::Installation,
+            &format!("{}{}", scope.route(), GITHUB_SECRETS_PUBLIC_KEY_API_ROUTE),
+        )
+    }
+
+    /// Lists the names of the secrets in `scope`. GitHub never returns their values.
+    pub fn list_actions_secrets(
+        &self,
+        scope: &ActionsScope,
+        options: PaginationOptions,
+    ) -> Paginator<'_, T, ActionsSecrets, ActionsSecret> {
+        Paginator::new(
+            self,
+            Authentication::Installation,
+            &format!("{}{}", scope.route(), GITHUB_SECRETS_API_ROUTE),
+            options,
+        )
+    }
+
+    /// Creates or replaces the secret `name` in `scope`, sealed against the scope's public key
+    /// so the plaintext never leaves this process.
+    pub fn set_actions_secret(&self, scope: &ActionsScope, name: &str, value: &str) -> Result<()> {
+        let public_key: ActionsPublicKey = self.get_actions_public_key(scope)?;
+        let encrypted_value: String = seal(&public_key, value.as_bytes())?;
+
+        self.send_json_without_response(
+            Authentication::Installation,
+            "PUT",
+            &secret_route(scope, name),
+            &EncryptedSecret {
+                encrypted_value,
+                key_id: &public_key.key_id,
+                visibility: scope.visibility(),
+            },
+        )?;
+        info!("Set secret {} in {}", name, scope);
+
+        Ok(())
+    }
+
+    pub fn delete_actions_secret(
+        &self,
+        scope: &ActionsScope,
+        name: &str,
+    ) -> Result<(), GitHubError> {
+        self.delete(Authentication::Installation, &secret_route(scope, name))
+    }
+
+    pub fn list_actions_variables(
+        &self,
+        scope: &ActionsScope,
+        options: PaginationOptions,
+    ) -> Paginator<'_, T, ActionsVariables, ActionsVariable> {
+        Paginator::new(
+            self,
+            Authentication::Installation,
+            &format!("{}{}", scope.route(), GITHUB_VARIABLES_API_ROUTE),
+            options,
+        )
+    }
+
+    /// Returns the variable `name` in `scope`, or `None` when it doesn't exist.
+    pub fn get_actions_variable(
+        &self,
+        scope: &ActionsScope,
+        name: &str,
+    ) -> Result<Option<ActionsVariable>, GitHubError> {
+        match self.get(Authentication::Installation, &variable_route(scope, name)) {
+            Ok(variable) => Ok(Some(variable)),
+            Err(error) if error.is_not_found() => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    pub fn create_actions_variable(
+        &self,
+        scope: &ActionsScope,
+        name: &str,
+        value: &str,
+    ) -> Result<(), GitHubError> {
+        self.send_json_without_response(
+            Authentication::Installation,
+            "POST",
+            &format!("{}{}", scope.route(), GITHUB_VARIABLES_API_ROUTE),
+            &NewVariable {
+                name,
+                value,
+                visibility: scope.visibility(),
+            },
+        )
+    }
+
+    pub fn update_actions_variable(
+        &self,
+        scope: &ActionsScope,
+        name: &str,
+        value: &str,
+    ) -> Result<(), GitHubError> {
+        self.send_json_without_response(
+            Authentication::Installation,
+            "PATCH",
+            &variable_route(scope, name),
+            &NewVariable {
+                name,
+                value,
+                visibility: scope.visibility(),
+            },
+        )
+    }
+
+    /// Creates the variable `name` in `scope`, or updates it when it already exists. Returns
+    /// `false` when it already held `value` and nothing was written.
+    pub fn set_actions_variable(
+        &self,
+        scope: &ActionsScope,
+        name: &str,
+        value: &str,
+    ) -> Result<bool, GitHubError> {
+        match self.get_actions_variable(scope, name)? {
+            Some(existing) if existing.value == value => {
+                info!("Variable {} in {} is already up to date", name, scope);
+                Ok(false)
+            }
+            Some(_) => {
+                self.update_actions_variable(scope, name, value)?;
+                info!("Updated variable {} in {}", name, scope);
+                Ok(true)
+            }
+            None => {
+                self.create_actions_variable(scope, name, value)?;
+                info!("Created variable {} in {}", name, scope);
+                Ok(true)
+            }
+        }
+    }
+
+    pub fn delete_actions_variable(
+        &self,
+        scope: &ActionsScope,
+        name: &str,
+    ) -> Result<(), GitHubError> {
+        self.delete(Authentication::Installation, &variable_route(scope, name))
+    }
+}
+
+/// Encrypts `plaintext` into a libsodium sealed box for `public_key`, base64 encoded the way
+/// GitHub expects `encrypted_value`.
+fn seal(public_key: &ActionsPublicKey, plaintext: &[u8]) -> Result<String> {
+    let key_bytes: Vec<u8> = STANDARD
+        .decode(&public_key.key)
+        .map_err(|error| anyhow!("GitHub returned a malformed public key: {}", error))?;
+    let public_key: PublicKey = PublicKey::from_slice(&key_bytes)
+        .map_err(|error| anyhow!("GitHub returned a malformed public key: {}", error))?;
+    let sealed: Vec<u8> = public_key
+        .seal(&mut OsRng, plaintext)
+        .map_err(|error| anyhow!("Failed to encrypt secret: {}", error))?;
+
+    Ok(STANDARD.encode(sealed))
+}
+
+fn secret_route(scope: &ActionsScope, name: &str) -> String {
+    format!(
+        "{}{}/{}",
+        scope.route(),
+        GITHUB_SECRETS_API_ROUTE,
+        urlencoding::encode(name)
+    )
+}
+
+fn variable_route(scope: &ActionsScope, name: &str) -> String {
+    format!(
+        "{}{}/{}",
+        scope.route(),
+        GITHUB_VARIABLES_API_ROUTE,
+        urlencoding::encode(name)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crypto_box::SecretKey;
+
+    use super::*;
+
+    fn organization(visibility: &str) -> ActionsScope {
+        ActionsScope::Organization {
+            organization: String::from("loremaster"),
+            visibility: visibility.to_string(),
+        }
+    }
+
+    #[test]
+    fn sealed_values_open_with_the_secret_key() {
+        let secret_key: SecretKey = SecretKey::generate(&mut OsRng);
+        let public_key: ActionsPublicKey = ActionsPublicKey {
+            key_id: String::from("568250167242549743"),
+            key: STANDARD.encode(secret_key.public_key().as_bytes()),
+        };
+
+        let sealed: String = seal(&public_key, b"hunter2").unwrap();
+        let unsealed: Vec<u8> = secret_key
+            .unseal(&STANDARD.decode(sealed).unwrap())
+            .unwrap();
+
+        assert_eq!(unsealed, b"hunter2");
+    }
+
+    #[test]
+    fn malformed_public_keys_are_rejected() {
+        let public_key = |key: &str| ActionsPublicKey {
+            key_id: String::from("568250167242549743"),
+            key: key.to_string(),
+        };
+
+        assert!(seal(&public_key("not base64!"), b"hunter2").is_err());
+        assert!(seal(&public_key(&STANDARD.encode([0u8; 16])), b"hunter2").is_err());
+    }
+
+    #[test]
+    fn routes_of_each_scope() {
+        assert_eq!(
+            ActionsScope::Repository(String::from("loremaster/lorerunner")).route(),
+            "/repos/loremaster/lorerunner/actions"
+        );
+        assert_eq!(
+            ActionsScope::Environment {
+                repository: String::from("loremaster/lorerunner"),
+                environment: String::from("staging & qa"),
+            }
+            .route(),
+            "/repos/loremaster/lorerunner/environments/staging%20%26%20qa"
+        );
+        assert_eq!(organization("private").route(), "/orgs/loremaster/actions");
+        assert_eq!(
+            secret_route(&organization("private"), "AWS_ROLE_ARN"),
+            "/orgs/loremaster/actions/secrets/AWS_ROLE_ARN"
+        );
+    }
+
+    #[test]
+    fn only_organization_scopes_have_a_visibility() {
+        assert_eq!(organization("all").visibility(), Some("all"));
+        assert_eq!(
+            ActionsScope::Repository(String::from("loremaster/lorerunner")).visibility(),
+            None
+        );
+    }
+
+    #[test]
+    fn validate_rejects_what_cannot_be_routed() {
+        assert!(
+            ActionsScope::Repository(String::from("loremaster/lorerunner"))
+                .validate()
+                .is_ok()
+        );
+        assert!(ActionsScope::Repository(String::from("lorerunner"))
+            .validate()
+            .is_err());
+        assert!(ActionsScope::Environment {
+            repository: String::from("loremaster/lorerunner"),
+            environment: String::new(),
+        }
+        .validate()
+        .is_err());
+        assert!(organization("private").validate().is_ok());
+        assert!(organization("all").validate().is_ok());
+        assert!(ActionsScope::Organization {
+            organization: String::from("loremaster/lorerunner"),
+            visibility: String::from("private"),
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn selected_visibility_is_rejected() {
+        assert!(organization("selected").validate().is_err());
+        assert!(organization("public").validate().is_err());
+    }
+}
@@ -0,0 +1,499 @@
+use anyhow::Result;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::{
+    configuration::{
+        constants::github::{
+            GITHUB_CHECK_RUNS_API_ROUTE, GITHUB_CHECK_RUN_MAX_ANNOTATIONS_PER_REQUEST,
+            GITHUB_CHECK_RUN_MAX_SUMMARY_CHARACTERS, SITE_BUILD_CHECK_RUN_NAME,
+        },
+        Configuration,
+    },
+    http::HttpTransport,
+};
+
+use super::{
+    deployments::validate_repository,
+    error::GitHubError,
+    github_app::{repository_route, Authentication, GitHubApp},
+    models::{AnnotationLevel, CheckRun, CheckRunConclusion, CheckRunStatus},
+};
+
+/// A comment on a specific line range of a file, shown on the pull request diff.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckRunAnnotation {
+    /// Path of the file relative to the repository root.
+    pub path: String,
+    pub start_line: u32,
+    pub end_line: u32,
+    pub annotation_level: AnnotationLevel,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_details: Option<String>,
+}
+
+/// The title, markdown summary and annotations shown on a check run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckRunOutput {
+    pub title: String,
+    /// Markdown, truncated to GitHub's 65535 character limit when sent.
+    pub summary: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub annotations: Vec<CheckRunAnnotation>,
+}
+
+impl CheckRunOutput {
+    pub fn new(title: &str, summary: &str) -> Self {
+        CheckRunOutput {
+            title: title.to_string(),
+            summary: summary.to_string(),
+            text: None,
+            annotations: vec![],
+        }
+    }
+}
+
+/// Body of `POST /repos/{owner}/{repo}/check-runs`.
+#[derive(Debug, Clone, Serialize)]
+pub struct NewCheckRun {
+    pub name: String,
+    pub head_sha: String,
+    pub status: CheckRunStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details_url: Option<String>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        with = "time::serde::rfc3339::option"
+    )]
+    pub started_at: Option<OffsetDateTime>,
+    /// Required by GitHub when `status` is `completed`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conclusion: Option<CheckRunConclusion>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        with = "time::serde::rfc3339::option"
+    )]
+    pub completed_at: Option<OffsetDateTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<CheckRunOutput>,
+}
+
+impl NewCheckRun {
+    /// A check run on `head_sha` that starts now.
+    pub fn new(name: &str, head_sha: &str) -> Self {
+        NewCheckRun {
+            name: name.to_string(),
+            head_sha: head_sha.to_string(),
+            status: CheckRunStatus::InProgress,
+            external_id: None,
+            details_url: None,
+            started_at: Some(OffsetDateTime::now_utc()),
+            conclusion: None,
+            completed_at: None,
+            output: None,
+        }
+    }
+}
+
+/// Body of `PATCH /repos/{owner}/{repo}/check-runs/{check_run_id}`. Fields left as `None` keep
+/// their current value.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CheckRunUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<CheckRunStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conclusion: Option<CheckRunConclusion>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        with = "time::serde::rfc3339::option"
+    )]
+    pub completed_at: Option<OffsetDateTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<CheckRunOutput>,
+}
+
+impl CheckRunUpdate {
+    /// Completes a check run now with `conclusion`.
+    pub fn complete(conclusion: CheckRunConclusion, output: CheckRunOutput) -> Self {
+        CheckRunUpdate {
+            status: Some(CheckRunStatus::Completed),
+            conclusion: Some(conclusion),
+            completed_at: Some(OffsetDateTime::now_utc()),
+            output: Some(output),
+            ..CheckRunUpdate::default()
+        }
+    }
+}
+
+impl<T: HttpTransport> GitHubApp<T> {
+    /// Creates a check run. Annotations beyond GitHub's per-request limit are sent in follow-up
+    /// updates, which GitHub appends to the run.
+    pub fn create_check_run(
+        &self,
+        repository: &str,
+        check_run: &NewCheckRun,
+    ) -> Result<CheckRun, GitHubError> {
+        let mut check_run: NewCheckRun = check_run.clone();
+        let remaining_annotations: Vec<CheckRunAnnotation> = match check_run.output.as_mut() {
+            Some(output) => prepare_output(output),
+            None => vec![],
+        };
+
+        let created: CheckRun = self.send_json(
+            Authentication::Installation,
+            "POST",
+            &check_runs_route(repository),
+            &check_run,
+        )?;
+        match check_run.output {
+            Some(output) => self
+                .append_check_run_annotations(
+                    repository,
+                    created.id,
+                    &output,
+                    remaining_annotations,
+                )
+                .map(|updated| updated.unwrap_or(created)),
+            None => Ok(created),
+        }
+    }
+
+    /// Updates a check run, sending annotations beyond GitHub's per-request limit in follow-up
+    /// updates.
+    pub fn update_check_run(
+        &self,
+        repository: &str,
+        check_run_id: u64,
+        update: &CheckRunUpdate,
+    ) -> Result<CheckRun, GitHubError> {
+        let mut update: CheckRunUpdate = update.clone();
+        let remaining_annotations: Vec<CheckRunAnnotation> = match update.output.as_mut() {
+            Some(output) => prepare_output(output),
+            None => vec![],
+        };
+
+        let updated: CheckRun = self.send_json(
+            Authentication::Installation,
+            "PATCH",
+            &check_run_route(repository, check_run_id),
+            &update,
+        )?;
+        match update.output {
+            Some(output) => self
+                .append_check_run_annotations(
+                    repository,
+                    check_run_id,
+                    &output,
+                    remaining_annotations,
+                )
+                .map(|appended| appended.unwrap_or(updated)),
+            None => Ok(updated),
+        }
+    }
+
+    pub fn get_check_run(
+        &self,
+        repository: &str,
+        check_run_id: u64,
+    ) -> Result<CheckRun, GitHubError> {
+        self.get(
+            Authentication::Installation,
+            &check_run_route(repository, check_run_id),
+        )
+    }
+
+    /// Sends `annotations` in batches alongside `output`'s title and summary, which GitHub
+    /// requires on every output. Returns the run after the last batch, if any were sent.
+    fn append_check_run_annotations(
+        &self,
+        repository: &str,
+        check_run_id: u64,
+        output: &CheckRunOutput,
+        annotations: Vec<CheckRunAnnotation>,
+    ) -> Result<Option<CheckRun>, GitHubError> {
+        let mut check_run: Option<CheckRun> = None;
+
+        for batch in annotations.chunks(GITHUB_CHECK_RUN_MAX_ANNOTATIONS_PER_REQUEST) {
+            let update: CheckRunUpdate = CheckRunUpdate {
+                output: Some(CheckRunOutput {
+                    title: output.title.clone(),
+                    summary: output.summary.clone(),
+                    text: None,
+                    annotations: batch.to_vec(),
+                }),
+                ..CheckRunUpdate::default()
+            };
+            check_run = Some(self.send_json(
+                Authentication::Installation,
+                "PATCH",
+                &check_run_route(repository, check_run_id),
+                &update,
+            )?);
+        }
+
+        Ok(check_run)
+    }
+}
+
+/// What a check run is reported on, for `track_check_run`.
+#[derive(Debug, Clone)]
+pub struct CheckRunTarget {
+    /// `owner/name` of the repository the check run is recorded on.
+    pub repository: String,
+    /// Full commit SHA the check run is attached to.
+    pub head_sha: String,
+    pub name: String,
+    pub details_url: Option<String>,
+}
+
+impl CheckRunTarget {
+    /// The site build check run on `head_sha` of the configured site repository.
+    pub fn for_site(configuration: &Configuration, head_sha: &str) -> Result<Self> {
+        validate_repository(&configuration.site_repository)?;
+
+        Ok(CheckRunTarget {
+            repository: configuration.site_repository.clone(),
+            head_sha: head_sha.to_string(),
+            name: SITE_BUILD_CHECK_RUN_NAME.to_string(),
+            details_url: match configuration.site_url.is_empty() {
+                true => None,
+                false => Some(configuration.site_url.clone()),
+            },
+        })
+    }
+}
+
+/// Runs `check` as a GitHub check run on `target`, reporting it in progress while it runs and
+/// completing it with the returned conclusion and output.
+///
+/// An error from `check` completes the run as a failure with the error as its summary before it
+/// is passed on. Failures reporting to GitHub are only logged, so a GitHub outage never
+/// fails the work being reported on.
+pub fn track_check_run<T, R, F>(
+    github_app: &GitHubApp<T>,
+    target: &CheckRunTarget,
+    check: F,
+) -> Result<R>
+where
+    T: HttpTransport,
+    F: FnOnce() -> Result<(R, CheckRunConclusion, CheckRunOutput)>,
+{
+    let mut new_check_run: NewCheckRun = NewCheckRun::new(&target.name, &target.head_sha);
+    new_check_run.details_url = target.details_url.clone();
+    let check_run: Option<CheckRun> =
+        match github_app.create_check_run(&target.repository, &new_check_run) {
+            Ok(check_run) => {
+                info!(
+                    "Started check run {} ({}) on {} {}",
+                    check_run.name, check_run.id, target.repository, target.head_sha
+                );
+                Some(check_run)
+            }
+            Err(error) => {
+                warn!(
+                    "Failed to start check run {} on {} {}: {}",
+                    target.name, target.repository, target.head_sha, error
+                );
+                None
+            }
+        };
+
+    let complete = |conclusion: CheckRunConclusion, output: CheckRunOutput| {
+        let Some(check_run) = check_run.as_ref() else {
+            return;
+        };
+        if let Err(error) = github_app.update_check_run(
+            &target.repository,
+            check_run.id,
+            &CheckRunUpdate::complete(conclusion, output),
+        ) {
+            warn!(
+                "Failed to complete check run {} ({}): {}",
+                check_run.name, check_run.id, error
+            );
+        }
+    };
+
+    match check() {
+        Ok((result, conclusion, output)) => {
+            complete(conclusion, output);
+            Ok(result)
+        }
+        Err(error) => {
+            complete(
+                CheckRunConclusion::Failure,
+                CheckRunOutput::new(
+                    &format!("{} failed", target.name),
+                    &format!("```\n{:#}\n```", error),
+                ),
+            );
+            Err(error)
+        }
+    }
+}
+
+/// Truncates the summary to GitHub's limit and splits off the annotations that don't fit in the
+/// first request.
+fn prepare_output(output: &mut CheckRunOutput) -> Vec<CheckRunAnnotation> {
+    if output.summary.chars().count() > GITHUB_CHECK_RUN_MAX_SUMMARY_CHARACTERS {
+        output.summary = output
+            .summary
+            .chars()
+            .take(GITHUB_CHECK_RUN_MAX_SUMMARY_CHARACTERS)
+            .collect();
+    }
+
+    match output.annotations.len() > GITHUB_CHECK_RUN_MAX_ANNOTATIONS_PER_REQUEST {
+        true => output
+            .annotations
+            .split_off(GITHUB_CHECK_RUN_MAX_ANNOTATIONS_PER_REQUEST),
+        false => vec![],
+    }
+}
+
+/// Turns the errors and warnings rustc printed in `output` into annotations. Diagnostics without
+/// a location, or located outside the crate such as in dependencies, are skipped. `path_prefix`
+/// is the crate's directory within the repository.
+pub fn parse_build_diagnostics(output: &str, path_prefix: &str) -> Vec<CheckRunAnnotation> {
+    let mut annotations: Vec<CheckRunAnnotation> = vec![];
+    let mut pending: Option<(AnnotationLevel, String)> = None;
+
+    for line in output.lines() {
+        if let Some((level, message)) = diagnostic_header(line) {
+            pending = Some((level, message));
+            continue;
+        }
+        let Some(location) = line.trim_start().strip_prefix("--> ") else {
+            continue;
+        };
+        let Some((annotation_level, message)) = pending.take() else {
+            continue;
+        };
+        // `path:line:column`, where the path itself may contain colons.
+        let mut parts = location.trim().rsplitn(3, ':');
+        let (Some(_column), Some(line_number), Some(path)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            continue;
+        };
+        let Ok(line_number) = line_number.parse::<u32>() else {
+            continue;
+        };
+        let path: &str = path.trim_start_matches("./");
+        if path.starts_with('/') || path.starts_with("..") {
+            continue;
+        }
+
+        annotations.push(CheckRunAnnotation {
+            path: match path_prefix.trim_matches('/') {
+                "" => path.to_string(),
+                prefix => format!("{}/{}", prefix, path),
+            },
+            start_line: line_number,
+            end_line: line_number,
+            annotation_level,
+            message,
+            title: None,
+            raw_details: None,
+        });
+    }
+
+    annotations
+}
+
+/// The level and message of a line like `error[E0425]: cannot find value` or `warning: unused`.
+fn diagnostic_header(line: &str) -> Option<(AnnotationLevel, String)> {
+    let (level, rest): (AnnotationLevel, &str) = match line {
+        _ if line.starts_with("error") => (AnnotationLevel::Failure, &line["error".len()..]),
+        _ if line.starts_with("warning") => (AnnotationLevel::Warning, &line["warning".len()..]),
+        _ => return None,
+    };
+    // Skip an error code such as `[E0425]`.
+    let rest: &str = match rest.strip_prefix('[') {
+        Some(rest) => rest.split_once(']')?.1,
+        None => rest,
+    };
+    let message: &str = rest.strip_prefix(": ")?;
+
+    Some((level, message.to_string()))
+}
+
+fn check_runs_route(repository: &str) -> String {
+    format!(
+        "{}{}",
+        repository_route(repository),
+        GITHUB_CHECK_RUNS_API_ROUTE
+    )
+}
+
+fn check_run_route(repository: &str, check_run_id: u64) -> String {
+    format!("{}/{}", check_runs_route(repository), check_run_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BUILD_OUTPUT: &str = "\
+   Compiling app v0.1.0 (/home/lore/site/frontend)
+warning: unused variable: `title`
+  --> src/templates/index.rs:12:9
+   |
+12 |     let title = \"Lore\";
+   |         ^^^^^ help: if this is intentional, prefix it with an underscore: `_title`
+
+error[E0425]: cannot find value `pages` in this scope
+ --> src/main.rs:7:5
+  |
+7 |     pages
+  |     ^^^^^ not found in this scope
+
+warning: unexpected `cfg` condition name
+   --> /home/lore/.cargo/registry/src/index.crates.io/sycamore-0.8.2/src/lib.rs:3:7
+
+error: could not compile `app` (bin \"app\") due to 1 previous error; 1 warning emitted
+";
+
+    #[test]
+    fn parse_build_diagnostics_annotates_crate_files() {
+        let annotations: Vec<CheckRunAnnotation> =
+            parse_build_diagnostics(BUILD_OUTPUT, "frontend/");
+
+        assert_eq!(annotations.len(), 2);
+        assert_eq!(annotations[0].path, "frontend/src/templates/index.rs");
+        assert_eq!(annotations[0].start_line, 12);
+        assert_eq!(annotations[0].annotation_level, AnnotationLevel::Warning);
+        assert_eq!(annotations[0].message, "unused variable: `title`");
+        assert_eq!(annotations[1].path, "frontend/src/main.rs");
+        assert_eq!(annotations[1].start_line, 7);
+        assert_eq!(annotations[1].annotation_level, AnnotationLevel::Failure);
+        assert_eq!(
+            annotations[1].message,
+            "cannot find value `pages` in this scope"
+        );
+    }
+
+    #[test]
+    fn parse_build_diagnostics_without_prefix_keeps_crate_paths() {
+        let annotations: Vec<CheckRunAnnotation> = parse_build_diagnostics(BUILD_OUTPUT, "");
+
+        assert_eq!(annotations[0].path, "src/templates/index.rs");
+    }
+
+    #[test]
+    fn parse_build_diagnostics_ignores_clean_builds() {
+        let output: &str = "   Compiling app v0.1.0\n    Finished release [optimized] target(s)\n";
+
+        assert!(parse_build_diagnostics(output, "").is_empty());
+    }
+}
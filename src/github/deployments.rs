@@ -0,0 +1,633 @@
+use std::future::Future;
+
+use anyhow::{anyhow, Result};
+use log::{info, warn};
+use serde::Serialize;
+
+use crate::{
+    configuration::{
+        constants::github::{
+            GITHUB_DEPLOYMENTS_API_ROUTE, GITHUB_DEPLOYMENT_STATUSES_API_ROUTE,
+            GITHUB_MAX_PER_PAGE, GITHUB_PREVIOUS_DEPLOYMENTS_SCAN_LIMIT,
+        },
+        Configuration,
+    },
+    http::HttpTransport,
+};
+
+use super::{
+    error::GitHubError,
+    github_app::{repository_route, Authentication, GitHubApp},
+    models::{Deployment, DeploymentState, DeploymentStatus},
+    pagination::{PaginationOptions, Paginator},
+};
+
+/// Body of `POST /repos/{owner}/{repo}/deployments`.
+#[derive(Debug, Clone, Serialize)]
+pub struct NewDeployment {
+    #[serde(rename = "ref")]
+    pub git_ref: String,
+    pub environment: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Lorerunner deploys exactly the ref it was given, so GitHub must not merge the default
+    /// branch into it first.
+    pub auto_merge: bool,
+    /// Commit status checks GitHub verifies before creating the deployment. Empty skips them,
+    /// since lorerunner is usually the thing those checks are waiting on.
+    pub required_contexts: Vec<String>,
+    pub production_environment: bool,
+    pub transient_environment: bool,
+}
+
+impl NewDeployment {
+    pub fn new(git_ref: &str, environment: &str) -> Self {
+        NewDeployment {
+            git_ref: git_ref.to_string(),
+            environment: environment.to_string(),
+            description: None,
+            auto_merge: false,
+            required_contexts: vec![],
+            production_environment: true,
+            transient_environment: false,
+        }
+    }
+}
+
+/// Body of `POST /repos/{owner}/{repo}/deployments/{deployment_id}/statuses`.
+#[derive(Debug, Clone, Serialize)]
+pub struct NewDeploymentStatus {
+    pub state: DeploymentState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub environment_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub log_url: Option<String>,
+    /// Lets GitHub mark earlier deployments to the same environment inactive on success. GitHub
+    /// skips production environments, which `deactivate_previous_deployments` covers.
+    pub auto_inactive: bool,
+}
+
+impl NewDeploymentStatus {
+    pub fn new(state: DeploymentState) -> Self {
+        NewDeploymentStatus {
+            state,
+            description: None,
+            environment_url: None,
+            log_url: None,
+            auto_inactive: true,
+        }
+    }
+}
+
+/// What is being deployed where, for `track_deployment`.
+#[derive(Debug, Clone)]
+pub struct DeploymentTarget {
+    /// `owner/name` of the repository the deployment is recorded on.
+    pub repository: String,
+    /// Branch, tag or commit SHA being shipped.
+    pub git_ref: String,
+    pub environment: String,
+    pub description: Option<String>,
+    pub environment_url: Option<String>,
+    pub log_url: Option<String>,
+}
+
+impl DeploymentTarget {
+    /// A deploy of the loremaster static site at `git_ref`, to the configured site repository and
+    /// environment.
+    pub fn for_site(configuration: &Configuration, git_ref: &str) -> Result<Self> {
+        validate_repository(&configuration.site_repository)?;
+
+        Ok(DeploymentTarget {
+            repository: configuration.site_repository.clone(),
+            git_ref: git_ref.to_string(),
+            environment: configuration.site_environment.clone(),
+            description: Some(String::from("Loremaster static site")),
+            environment_url: match configuration.site_url.is_empty() {
+                true => None,
+                false => Some(configuration.site_url.clone()),
+            },
+            log_url: configured_log_url(configuration, &configuration.site_environment, git_ref),
+        })
+    }
+
+    /// An EC2 rollout of `git_ref`, such as an instance launch or launch template promotion,
+    /// recorded on the configured EC2 repository and environment.
+    pub fn for_ec2(
+        configuration: &Configuration,
+        git_ref: &str,
+        description: &str,
+    ) -> Result<Self> {
+        let repository: &str = configuration.get_ec2_repository();
+        validate_repository(repository)?;
+
+        Ok(DeploymentTarget {
+            repository: repository.to_string(),
+            git_ref: git_ref.to_string(),
+            environment: configuration.ec2_environment.clone(),
+            description: Some(description.to_string()),
+            environment_url: match configuration.ec2_environment_url.is_empty() {
+                true => None,
+                false => Some(configuration.ec2_environment_url.clone()),
+            },
+            log_url: configured_log_url(configuration, &configuration.ec2_environment, git_ref),
+        })
+    }
+}
+
+/// `deployment_log_url` with `{environment}` and `{ref}` filled in, if one is configured.
+fn configured_log_url(
+    configuration: &Configuration,
+    environment: &str,
+    git_ref: &str,
+) -> Option<String> {
+    match configuration.deployment_log_url.is_empty() {
+        true => None,
+        false => Some(
+            configuration
+                .deployment_log_url
+                .replace("{environment}", &urlencoding::encode(environment))
+                .replace("{ref}", &urlencoding::encode(git_ref)),
+        ),
+    }
+}
+
+impl<T: HttpTransport> GitHubApp<T> {
+    pub fn create_deployment(
+        &self,
+        repository: &str,
+        deployment: &NewDeployment,
+    ) -> Result<Deployment, GitHubError> {
+        self.send_json(
+            Authentication::Installation,
+            "POST",
+            &deployments_route(repository),
+            deployment,
+        )
+    }
+
+    pub fn create_deployment_status(
+        &self,
+        repository: &str,
+        deployment_id: u64,
+        status: &NewDeploymentStatus,
+    ) -> Result<DeploymentStatus, GitHubError> {
+        self.send_json(
+            Authentication::Installation,
+            "POST",
+            &deployment_statuses_route(repository, deployment_id),
+            status,
+        )
+    }
+
+    /// Lists a repository's deployments, newest first, optionally limited to one environment.
+    pub fn list_deployments(
+        &self,
+        repository: &str,
+        environment: Option<&str>,
+        options: PaginationOptions,
+    ) -> Paginator<'_, T, Vec<Deployment>, Deployment> {
+        let route: String = match environment {
+            Some(environment) => format!(
+                "{}?environment={}",
+                deployments_route(repository),
+                urlencoding::encode(environment)
+            ),
+            None => deployments_route(repository),
+        };
+        Paginator::new(self, Authentication::Installation, &route, options)
+    }
+
+    /// Lists a deployment's statuses, newest first.
+    pub fn list_deployment_statuses(
+        &self,
+        repository: &str,
+        deployment_id: u64,
+        options: PaginationOptions,
+    ) -> Paginator<'_, T, Vec<DeploymentStatus>, DeploymentStatus> {
+        Paginator::new(
+            self,
+            Authentication::Installation,
+            &deployment_statuses_route(repository, deployment_id),
+            options,
+        )
+    }
+
+    /// Marks recent deployments to `current`'s environment inactive when their latest status is
+    /// still `success`, so only `current` shows as active in the repository.
+    ///
+    /// Returns how many deployments were deactivated.
+    pub fn deactivate_previous_deployments(
+        &self,
+        repository: &str,
+        current: &Deployment,
+    ) -> Result<u32, GitHubError> {
+        let options: PaginationOptions = PaginationOptions {
+            per_page: GITHUB_MAX_PER_PAGE,
+            max_pages: Some(1),
+        };
+        let mut deactivated: u32 = 0;
+
+        for deployment in self
+            .list_deployments(repository, Some(&current.environment), options)
+            .take(GITHUB_PREVIOUS_DEPLOYMENTS_SCAN_LIMIT)
+        {
+            let deployment: Deployment = deployment?;
+            if deployment.id == current.id {
+                continue;
+            }
+
+            let latest_status: Option<DeploymentStatus> = self
+                .list_deployment_statuses(
+                    repository,
+                    deployment.id,
+                    PaginationOptions {
+                        per_page: 1,
+                        max_pages: Some(1),
+                    },
+                )
+                .next()
+                .transpose()?;
+            if latest_status.map(|status| status.state) != Some(DeploymentState::Success) {
+                continue;
+            }
+
+            let mut status: NewDeploymentStatus =
+                NewDeploymentStatus::new(DeploymentState::Inactive);
+            status.description = Some(format!("Superseded by deployment {}", current.id));
+            self.create_deployment_status(repository, deployment.id, &status)?;
+            deactivated += 1;
+        }
+
+        Ok(deactivated)
+    }
+}
+
+/// Runs `deploy` as a GitHub deployment of `target`, reporting `in_progress` before it starts
+/// and `success` or `failure` once it finishes.
+///
+/// The deployment is created before `deploy` runs, so a GitHub failure at that point stops the
+/// rollout. Failures reporting the outcome are only logged, since the rollout itself already
+/// happened.
+pub fn track_deployment<T, F>(
+    github_app: &GitHubApp<T>,
+    target: &DeploymentTarget,
+    deploy: F,
+) -> Result<()>
+where
+    T: HttpTransport,
+    F: FnOnce() -> Result<()>,
+{
+    let deployment: Deployment = start_deployment(github_app, target)?;
+    finish_deployment(github_app, target, &deployment, deploy())
+}
+
+/// `track_deployment` for rollouts that run asynchronously, such as EC2 launches.
+pub async fn track_deployment_async<T, F>(
+    github_app: &GitHubApp<T>,
+    target: &DeploymentTarget,
+    deploy: F,
+) -> Result<()>
+where
+    T: HttpTransport,
+    F: Future<Output = Result<()>>,
+{
+    let deployment: Deployment = start_deployment(github_app, target)?;
+    finish_deployment(github_app, target, &deployment, deploy.await)
+}
+
+/// Creates the deployment and reports it `in_progress`.
+fn start_deployment<T: HttpTransport>(
+    github_app: &GitHubApp<T>,
+    target: &DeploymentTarget,
+) -> Result<Deployment> {
+    let mut new_deployment: NewDeployment =
+        NewDeployment::new(&target.git_ref, &target.environment);
+    new_deployment.description = target.description.clone();
+    let deployment: Deployment =
+        github_app.create_deployment(&target.repository, &new_deployment)?;
+    info!(
+        "Created deployment {} of {} ({}) to {}",
+        deployment.id, target.repository, deployment.sha, deployment.environment
+    );
+
+    report_deployment_status(
+        github_app,
+        target,
+        &deployment,
+        DeploymentState::InProgress,
+        None,
+    );
+
+    Ok(deployment)
+}
+
+/// Reports the rollout's outcome and, when it succeeded, marks earlier deployments inactive.
+fn finish_deployment<T: HttpTransport>(
+    github_app: &GitHubApp<T>,
+    target: &DeploymentTarget,
+    deployment: &Deployment,
+    outcome: Result<()>,
+) -> Result<()> {
+    match outcome {
+        Ok(()) => {
+            report_deployment_status(
+                github_app,
+                target,
+                deployment,
+                DeploymentState::Success,
+                None,
+            );
+            match github_app.deactivate_previous_deployments(&target.repository, deployment) {
+                Ok(deactivated) => info!(
+                    "Marked {} previous deployment(s) to {} inactive",
+                    deactivated, deployment.environment
+                ),
+                Err(error) => warn!(
+                    "Failed to mark previous deployments to {} inactive: {}",
+                    deployment.environment, error
+                ),
+            }
+            Ok(())
+        }
+        Err(error) => {
+            report_deployment_status(
+                github_app,
+                target,
+                deployment,
+                DeploymentState::Failure,
+                Some(format!("{:#}", error)),
+            );
+            Err(error)
+        }
+    }
+}
+
+fn report_deployment_status<T: HttpTransport>(
+    github_app: &GitHubApp<T>,
+    target: &DeploymentTarget,
+    deployment: &Deployment,
+    state: DeploymentState,
+    description: Option<String>,
+) {
+    let mut status: NewDeploymentStatus = NewDeploymentStatus::new(state);
+    status.environment_url = target.environment_url.clone();
+    status.log_url = target.log_url.clone();
+    // GitHub rejects descriptions longer than 140 characters.
+    status.description = description.map(|description| description.chars().take(140).collect());
+
+    if let Err(error) =
+        github_app.create_deployment_status(&target.repository, deployment.id, &status)
+    {
+        warn!(
+            "Failed to report {:?} for deployment {}: {}",
+            state, deployment.id, error
+        );
+    }
+}
+
+fn deployments_route(repository: &str) -> String {
+    format!(
+        "{}{}",
+        repository_route(repository),
+        GITHUB_DEPLOYMENTS_API_ROUTE
+    )
+}
+
+fn deployment_statuses_route(repository: &str, deployment_id: u64) -> String {
+    format!(
+        "{}/{}{}",
+        deployments_route(repository),
+        deployment_id,
+        GITHUB_DEPLOYMENT_STATUSES_API_ROUTE
+    )
+}
+
+/// Checks that `repository` looks like `owner/name` before it is spliced into a route.
+pub fn validate_repository(repository: &str) -> Result<()> {
+    let mut parts = repository.split('/');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(owner), Some(name), None) if !owner.is_empty() && !name.is_empty() => Ok(()),
+        _ => Err(anyhow!(
+            "Expected a repository in owner/name form, got {:?}",
+            repository
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        github::test_support::{
+            access_token_body, access_token_url, test_configuration, test_github_app, test_url,
+        },
+        http::{fake::FakeTransport, HttpRequest, HttpResponse},
+    };
+
+    const REPOSITORY: &str = "chronilore/loremaster";
+    const ENVIRONMENT: &str = "production";
+
+    fn transport_with_token() -> FakeTransport {
+        FakeTransport::new().respond_to(
+            "POST",
+            &access_token_url(),
+            HttpResponse::new(
+                201,
+                &access_token_body(
+                    "ghs_deployments",
+                    time::OffsetDateTime::now_utc() + time::Duration::hours(1),
+                ),
+            ),
+        )
+    }
+
+    fn target() -> DeploymentTarget {
+        DeploymentTarget {
+            repository: REPOSITORY.to_string(),
+            git_ref: String::from("main"),
+            environment: ENVIRONMENT.to_string(),
+            description: Some(String::from("Loremaster static site")),
+            environment_url: None,
+            log_url: None,
+        }
+    }
+
+    fn deployment_json(id: u64) -> serde_json::Value {
+        serde_json::json!({
+            "id": id,
+            "node_id": format!("DE_{}", id),
+            "sha": "0123456789abcdef0123456789abcdef01234567",
+            "ref": "main",
+            "task": "deploy",
+            "environment": ENVIRONMENT,
+            "description": null,
+            "creator": null,
+            "statuses_url": test_url(&format!("/repos/{}/deployments/{}/statuses", REPOSITORY, id)),
+            "created_at": "2024-06-01T12:00:00Z",
+            "updated_at": "2024-06-01T12:00:00Z",
+        })
+    }
+
+    fn status_json(id: u64, state: &str) -> serde_json::Value {
+        serde_json::json!({
+            "id": id,
+            "node_id": format!("DES_{}", id),
+            "state": state,
+            "description": null,
+            "environment": ENVIRONMENT,
+            "environment_url": null,
+            "log_url": null,
+            "creator": null,
+            "created_at": "2024-06-01T12:00:00Z",
+            "updated_at": "2024-06-01T12:00:00Z",
+        })
+    }
+
+    fn statuses_url(deployment_id: u64) -> String {
+        test_url(&format!(
+            "/repos/{}/deployments/{}/statuses",
+            REPOSITORY, deployment_id
+        ))
+    }
+
+    /// A transport that creates deployment 11 and accepts `statuses` status reports for it.
+    fn transport_for_deployment(statuses: usize) -> FakeTransport {
+        (0..statuses).fold(
+            transport_with_token().respond_to(
+                "POST",
+                &test_url(&format!("/repos/{}/deployments", REPOSITORY)),
+                HttpResponse::new(201, &deployment_json(11).to_string()),
+            ),
+            |transport, index| {
+                transport.respond_to(
+                    "POST",
+                    &statuses_url(11),
+                    HttpResponse::new(201, &status_json(index as u64, "queued").to_string()),
+                )
+            },
+        )
+    }
+
+    fn body(request: &HttpRequest) -> serde_json::Value {
+        serde_json::from_slice(&request.body.clone().unwrap_or_default()).unwrap()
+    }
+
+    fn posted_states(requests: &[HttpRequest], deployment_id: u64) -> Vec<String> {
+        requests
+            .iter()
+            .filter(|request| {
+                request.method == "POST" && request.url == statuses_url(deployment_id)
+            })
+            .map(|request| body(request)["state"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn failing_deploy_reports_failure_with_a_short_description() {
+        let github_app: GitHubApp<FakeTransport> = test_github_app(transport_for_deployment(2));
+
+        let result: Result<()> = track_deployment(&github_app, &target(), || {
+            Err(anyhow!("{}", "x".repeat(500)))
+        });
+
+        assert!(result.is_err());
+        let requests: Vec<HttpRequest> = github_app.transport().requests();
+        assert_eq!(posted_states(&requests, 11), vec!["in_progress", "failure"]);
+        let failure: serde_json::Value = requests
+            .iter()
+            .rev()
+            .find(|request| request.url == statuses_url(11))
+            .map(body)
+            .unwrap();
+        let description: &str = failure["description"].as_str().unwrap();
+        assert_eq!(description.chars().count(), 140);
+        assert!(requests
+            .iter()
+            .all(|request| !request.url.contains("/deployments?")));
+    }
+
+    #[test]
+    fn success_deactivates_only_earlier_successful_deployments() {
+        let latest_status = |deployment_id: u64, statuses: serde_json::Value| {
+            (
+                format!("{}?per_page=1", statuses_url(deployment_id)),
+                HttpResponse::new(200, &statuses.to_string()),
+            )
+        };
+        let transport: FakeTransport = [
+            latest_status(10, serde_json::json!([status_json(100, "success")])),
+            latest_status(9, serde_json::json!([status_json(90, "failure")])),
+            latest_status(8, serde_json::json!([status_json(80, "inactive")])),
+            latest_status(7, serde_json::json!([])),
+        ]
+        .into_iter()
+        .fold(
+            transport_for_deployment(2).respond_to(
+                "GET",
+                &test_url(&format!(
+                    "/repos/{}/deployments?environment={}&per_page={}",
+                    REPOSITORY, ENVIRONMENT, GITHUB_MAX_PER_PAGE
+                )),
+                HttpResponse::new(
+                    200,
+                    &serde_json::json!([
+                        deployment_json(11),
+                        deployment_json(10),
+                        deployment_json(9),
+                        deployment_json(8),
+                        deployment_json(7),
+                    ])
+                    .to_string(),
+                ),
+            ),
+            |transport, (url, response)| transport.respond_to("GET", &url, response),
+        )
+        .respond_to(
+            "POST",
+            &statuses_url(10),
+            HttpResponse::new(201, &status_json(101, "inactive").to_string()),
+        );
+        let github_app: GitHubApp<FakeTransport> = test_github_app(transport);
+
+        track_deployment(&github_app, &target(), || Ok(())).unwrap();
+
+        let requests: Vec<HttpRequest> = github_app.transport().requests();
+        assert_eq!(posted_states(&requests, 11), vec!["in_progress", "success"]);
+        assert_eq!(posted_states(&requests, 10), vec!["inactive"]);
+        for deployment_id in [9, 8, 7] {
+            assert!(posted_states(&requests, deployment_id).is_empty());
+        }
+    }
+
+    #[test]
+    fn log_url_placeholders_are_url_encoded() {
+        let configuration: Configuration = Configuration {
+            deployment_log_url: String::from(
+                "https://ci.example.com/logs?environment={environment}&ref={ref}",
+            ),
+            ..test_configuration()
+        };
+
+        assert_eq!(
+            configured_log_url(&configuration, "staging & qa", "feature/new-map").as_deref(),
+            Some(
+                "https://ci.example.com/logs?environment=staging%20%26%20qa&ref=feature%2Fnew-map"
+            )
+        );
+        assert_eq!(
+            configured_log_url(&test_configuration(), "production", "main"),
+            None
+        );
+    }
+
+    #[test]
+    fn validate_repository_expects_owner_and_name() {
+        assert!(validate_repository("chronilore/loremaster").is_ok());
+        for repository in ["", "loremaster", "/loremaster", "chronilore/", "a/b/c"] {
+            assert!(validate_repository(repository).is_err(), "{}", repository);
+        }
+    }
+}
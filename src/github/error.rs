@@ -0,0 +1,65 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum GitHubError {
+    /// GitHub answered, but with a non-success status code.
+    #[error("[{method}][{url}] GitHub responded with {status_code} {status_text}: {body}")]
+    Status {
+        method: String,
+        url: String,
+        status_code: u16,
+        status_text: String,
+        body: String,
+    },
+    /// The request failed before a complete response was received (DNS, TLS, connection reset).
+    #[error("[{method}][{url}] Request to GitHub failed: {source}")]
+    Transport {
+        method: String,
+        url: String,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    /// GitHub answered successfully, but the body did not match the expected model.
+    #[error("[{method}][{url}] Failed to decode GitHub response: {source}")]
+    Decode {
+        method: String,
+        url: String,
+        #[source]
+        source: serde_json::Error,
+    },
+    /// The request body could not be serialized to JSON.
+    #[error("[{method}][{url}] Failed to encode GitHub request: {source}")]
+    Encode {
+        method: String,
+        url: String,
+        #[source]
+        source: serde_json::Error,
+    },
+    /// A token for the request could not be created.
+    #[error("Failed to authenticate with GitHub: {0:#}")]
+    Authentication(anyhow::Error),
+}
+
+impl GitHubError {
+    /// Whether GitHub answered 404, which most lookups treat as "doesn't exist yet".
+    pub fn is_not_found(&self) -> bool {
+        matches!(
+            self,
+            GitHubError::Status {
+                status_code: 404,
+                ..
+            }
+        )
+    }
+
+    /// Whether GitHub answered 401, meaning it didn't accept the credential at all.
+    pub fn is_unauthorized(&self) -> bool {
+        matches!(
+            self,
+            GitHubError::Status {
+                status_code: 401,
+                ..
+            }
+        )
+    }
+}
@@ -0,0 +1,625 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use log::{debug, info, warn};
+use serde::Serialize;
+use sha1::{Digest, Sha1};
+
+use crate::{
+    configuration::constants::github::{
+        GITHUB_GIT_BLOBS_API_ROUTE, GITHUB_GIT_COMMITS_API_ROUTE, GITHUB_GIT_REFS_API_ROUTE,
+        GITHUB_GIT_REF_API_ROUTE, GITHUB_GIT_TREES_API_ROUTE, GIT_BLOB_OBJECT_TYPE,
+        GIT_BRANCH_REF_PREFIX, GIT_REGULAR_FILE_MODE, GIT_TREE_OBJECT_TYPE,
+    },
+    file_system::get_file_content_as_bytes,
+    http::HttpTransport,
+};
+
+use super::{
+    error::GitHubError,
+    github_app::{repository_route, Authentication, GitHubApp},
+    models::{GitCommit, GitObjectReference, GitReference, GitTree},
+};
+
+#[derive(Debug, Clone, Serialize)]
+struct NewBlob {
+    content: String,
+    encoding: &'static str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NewTreeEntry {
+    pub path: String,
+    pub mode: String,
+    #[serde(rename = "type")]
+    pub entry_type: String,
+    pub sha: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct NewTree<'a> {
+    tree: &'a [NewTreeEntry],
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct NewCommit<'a> {
+    message: &'a str,
+    tree: &'a str,
+    parents: Vec<&'a str>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct NewReference<'a> {
+    #[serde(rename = "ref")]
+    git_ref: String,
+    sha: &'a str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ReferenceUpdate<'a> {
+    sha: &'a str,
+    force: bool,
+}
+
+impl<T: HttpTransport> GitHubApp<T> {
+    /// Returns the head of `branch`, or `None` when the branch doesn't exist.
+    pub fn get_branch_reference(
+        &self,
+        repository: &str,
+        branch: &str,
+    ) -> Result<Option<GitReference>, GitHubError> {
+        let route: String = format!(
+            "{}{}/heads/{}",
+            repository_route(repository),
+            GITHUB_GIT_REF_API_ROUTE,
+            branch
+        );
+        match self.get(Authentication::Installation, &route) {
+            Ok(reference) => Ok(Some(reference)),
+            Err(error) if error.is_not_found() => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    pub fn get_git_commit(&self, repository: &str, sha: &str) -> Result<GitCommit, GitHubError> {
+        self.get(
+            Authentication::Installation,
+            &format!(
+                "{}{}/{}",
+                repository_route(repository),
+                GITHUB_GIT_COMMITS_API_ROUTE,
+                sha
+            ),
+        )
+    }
+
+    /// Returns a tree's direct entries.
+    pub fn get_git_tree(&self, repository: &str, sha: &str) -> Result<GitTree, GitHubError> {
+        self.get(
+            Authentication::Installation,
+            &format!(
+                "{}{}/{}",
+                repository_route(repository),
+                GITHUB_GIT_TREES_API_ROUTE,
+                sha
+            ),
+        )
+    }
+
+    /// Returns a tree with every nested entry listed under its full path.
+    pub fn get_git_tree_recursive(
+        &self,
+        repository: &str,
+        sha: &str,
+    ) -> Result<GitTree, GitHubError> {
+        self.get(
+            Authentication::Installation,
+            &format!(
+                "{}{}/{}?recursive=1",
+                repository_route(repository),
+                GITHUB_GIT_TREES_API_ROUTE,
+                sha
+            ),
+        )
+    }
+
+    pub fn create_git_blob(
+        &self,
+        repository: &str,
+        content: &[u8],
+    ) -> Result<GitObjectReference, GitHubError> {
+        self.send_json(
+            Authentication::Installation,
+            "POST",
+            &format!(
+                "{}{}",
+                repository_route(repository),
+                GITHUB_GIT_BLOBS_API_ROUTE
+            ),
+            &NewBlob {
+                content: STANDARD.encode(content),
+                encoding: "base64",
+            },
+        )
+    }
+
+    /// Creates a tree holding exactly `entries`. Paths may contain `/`, in which case GitHub
+    /// creates the intermediate trees.
+    pub fn create_git_tree(
+        &self,
+        repository: &str,
+        entries: &[NewTreeEntry],
+    ) -> Result<GitTree, GitHubError> {
+        self.send_json(
+            Authentication::Installation,
+            "POST",
+            &format!(
+                "{}{}",
+                repository_route(repository),
+                GITHUB_GIT_TREES_API_ROUTE
+            ),
+            &NewTree { tree: entries },
+        )
+    }
+
+    pub fn create_git_commit(
+        &self,
+        repository: &str,
+        message: &str,
+        tree_sha: &str,
+        parent_shas: &[String],
+    ) -> Result<GitCommit, GitHubError> {
+        self.send_json(
+            Authentication::Installation,
+            "POST",
+            &format!(
+                "{}{}",
+                repository_route(repository),
+                GITHUB_GIT_COMMITS_API_ROUTE
+            ),
+            &NewCommit {
+                message,
+                tree: tree_sha,
+                parents: parent_shas.iter().map(String::as_str).collect(),
+            },
+        )
+    }
+
+    pub fn create_branch_reference(
+        &self,
+        repository: &str,
+        branch: &str,
+        sha: &str,
+    ) -> Result<GitReference, GitHubError> {
+        self.send_json(
+            Authentication::Installation,
+            "POST",
+            &format!(
+                "{}{}",
+                repository_route(repository),
+                GITHUB_GIT_REFS_API_ROUTE
+            ),
+            &NewReference {
+                git_ref: format!("{}{}", GIT_BRANCH_REF_PREFIX, branch),
+                sha,
+            },
+        )
+    }
+
+    /// Moves `branch` to `sha`. GitHub refuses the update unless it fast-forwards.
+    pub fn update_branch_reference(
+        &self,
+        repository: &str,
+        branch: &str,
+        sha: &str,
+    ) -> Result<GitReference, GitHubError> {
+        self.send_json(
+            Authentication::Installation,
+            "PATCH",
+            &format!(
+                "{}{}/heads/{}",
+                repository_route(repository),
+                GITHUB_GIT_REFS_API_ROUTE,
+                branch
+            ),
+            &ReferenceUpdate { sha, force: false },
+        )
+    }
+
+    /// Commits the contents of `directory` to `branch`, replacing everything on the branch except
+    /// `preserved_paths`, without a local clone.
+    ///
+    /// Only blobs GitHub doesn't already have are uploaded, by comparing git object IDs computed
+    /// locally against the branch's current tree. Returns `None` when the branch already matches
+    /// `directory`.
+    pub fn publish_directory(
+        &self,
+        repository: &str,
+        branch: &str,
+        directory: &Path,
+        preserved_paths: &[String],
+        message: &str,
+    ) -> Result<Option<GitCommit>> {
+        let local_files: BTreeMap<String, PathBuf> = collect_files(directory)?;
+        if local_files.is_empty() {
+            return Err(anyhow!(
+                "Refusing to publish {} to {}. The directory is empty.",
+                directory.display(),
+                branch
+            ));
+        }
+
+        let head: Option<GitCommit> = match self.get_branch_reference(repository, branch)? {
+            Some(reference) => Some(self.get_git_commit(repository, &reference.object.sha)?),
+            None => None,
+        };
+        let remote_blobs: Option<HashMap<String, String>> = match &head {
+            Some(head) => self.list_blobs(repository, &head.tree.sha)?,
+            None => Some(HashMap::new()),
+        };
+        let remote_shas: HashSet<&str> = remote_blobs
+            .iter()
+            .flat_map(|blobs| blobs.values().map(String::as_str))
+            .collect();
+
+        let mut entries: Vec<NewTreeEntry> = vec![];
+        let mut uploaded: u32 = 0;
+        for (relative_path, local_path) in &local_files {
+            let content: Vec<u8> = get_file_content_as_bytes(local_path)?;
+            let mut sha: String = git_blob_sha(&content);
+            if !remote_shas.contains(sha.as_str()) {
+                debug!("Uploading {} ({} bytes)", relative_path, content.len());
+                sha = self.create_git_blob(repository, &content)?.sha;
+                uploaded += 1;
+            }
+            entries.push(blob_entry(relative_path, &sha));
+        }
+        for preserved_path in preserved_paths {
+            if local_files.contains_key(preserved_path) {
+                continue;
+            }
+            let sha: Option<String> = match (&remote_blobs, &head) {
+                (Some(blobs), _) => blobs.get(preserved_path).cloned(),
+                (None, Some(head)) => self.find_blob(repository, &head.tree.sha, preserved_path)?,
+                (None, None) => None,
+            };
+            if let Some(sha) = sha {
+                entries.push(blob_entry(preserved_path, &sha));
+            }
+        }
+
+        let tree: GitTree = self.create_git_tree(repository, &entries)?;
+        if head.as_ref().is_some_and(|head| head.tree.sha == tree.sha) {
+            info!("{} {} is already up to date", repository, branch);
+            return Ok(None);
+        }
+
+        let parent_shas: Vec<String> = head.iter().map(|head| head.sha.clone()).collect();
+        let commit: GitCommit =
+            self.create_git_commit(repository, message, &tree.sha, &parent_shas)?;
+        match head {
+            Some(_) => self.update_branch_reference(repository, branch, &commit.sha)?,
+            None => self.create_branch_reference(repository, branch, &commit.sha)?,
+        };
+        info!(
+            "Published {} file(s) to {} {} as {} ({} blob(s) uploaded)",
+            entries.len(),
+            repository,
+            branch,
+            commit.sha,
+            uploaded
+        );
+
+        Ok(Some(commit))
+    }
+
+    /// Maps each blob path in the tree to its SHA, or returns `None` when GitHub truncated the
+    /// listing and some blobs are missing from it.
+    fn list_blobs(
+        &self,
+        repository: &str,
+        tree_sha: &str,
+    ) -> Result<Option<HashMap<String, String>>, GitHubError> {
+        let tree: GitTree = self.get_git_tree_recursive(repository, tree_sha)?;
+        if tree.truncated {
+            warn!(
+                "Tree {} in {} is too large to list, uploading every file",
+                tree_sha, repository
+            );
+            return Ok(None);
+        }
+
+        Ok(Some(
+            tree.tree
+                .into_iter()
+                .filter(|entry| entry.entry_type == GIT_BLOB_OBJECT_TYPE)
+                .filter_map(|entry| Some((entry.path, entry.sha?)))
+                .collect(),
+        ))
+    }
+
+    /// Looks up the SHA of the blob at `path` by walking down one tree per directory, for when
+    /// the recursive listing was truncated.
+    fn find_blob(
+        &self,
+        repository: &str,
+        tree_sha: &str,
+        path: &str,
+    ) -> Result<Option<String>, GitHubError> {
+        let mut tree_sha: String = tree_sha.to_string();
+        let mut components = path.split('/').peekable();
+        while let Some(component) = components.next() {
+            let entry_type: &str = match components.peek() {
+                Some(_) => GIT_TREE_OBJECT_TYPE,
+                None => GIT_BLOB_OBJECT_TYPE,
+            };
+            let Some(sha) = self
+                .get_git_tree(repository, &tree_sha)?
+                .tree
+                .into_iter()
+                .find(|entry| entry.path == component && entry.entry_type == entry_type)
+                .and_then(|entry| entry.sha)
+            else {
+                return Ok(None);
+            };
+            tree_sha = sha;
+        }
+
+        Ok(Some(tree_sha))
+    }
+}
+
+/// The object ID git assigns to a blob with `content`.
+pub fn git_blob_sha(content: &[u8]) -> String {
+    let mut hasher: Sha1 = Sha1::new();
+    hasher.update(format!("blob {}\0", content.len()).as_bytes());
+    hasher.update(content);
+    hex::encode(hasher.finalize())
+}
+
+/// Lists every file under `root`, keyed by its `/`-separated path relative to `root`.
+fn collect_files(root: &Path) -> Result<BTreeMap<String, PathBuf>> {
+    let mut files: BTreeMap<String, PathBuf> = BTreeMap::new();
+    let mut directories: Vec<PathBuf> = vec![root.to_path_buf()];
+
+    while let Some(directory) = directories.pop() {
+        for entry in std::fs::read_dir(&directory)? {
+            let path: PathBuf = entry?.path();
+            if path.is_dir() {
+                if path.file_name().is_some_and(|name| name == ".git") {
+                    continue;
+                }
+                directories.push(path);
+                continue;
+            }
+
+            let relative_path: String = path
+                .strip_prefix(root)?
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            files.insert(relative_path, path);
+        }
+    }
+
+    Ok(files)
+}
+
+fn blob_entry(path: &str, sha: &str) -> NewTreeEntry {
+    NewTreeEntry {
+        path: path.to_string(),
+        mode: GIT_REGULAR_FILE_MODE.to_string(),
+        entry_type: GIT_BLOB_OBJECT_TYPE.to_string(),
+        sha: sha.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        github::test_support::{access_token_body, access_token_url, test_github_app, test_url},
+        http::{fake::FakeTransport, HttpRequest, HttpResponse},
+    };
+
+    const REPOSITORY: &str = "chronilore/loremaster.github.io";
+
+    #[test]
+    fn git_blob_sha_matches_git_hash_object() {
+        assert_eq!(
+            git_blob_sha(b""),
+            "e69de29bb2d1d6434b8b29ae775ad8c2e48c5391"
+        );
+        assert_eq!(
+            git_blob_sha(b"hello world\n"),
+            "3b18e512dba79e4c8300dd08aeb37f8e728b8dad"
+        );
+        assert_eq!(
+            git_blob_sha(b"what is up, doc?"),
+            "bd9dbf5aae1a3862dd1526723246b20206e5fc37"
+        );
+        assert_eq!(
+            git_blob_sha(&[0x00, 0xff, 0x10]),
+            "d553b66b6a09553981f4c9b617e12de89b8fe30c"
+        );
+    }
+
+    fn git_url(route: &str) -> String {
+        test_url(&format!("/repos/{}/git{}", REPOSITORY, route))
+    }
+
+    fn json(status: u16, body: serde_json::Value) -> HttpResponse {
+        HttpResponse::new(status, &body.to_string())
+    }
+
+    fn tree_entry(path: &str, entry_type: &str, sha: &str) -> serde_json::Value {
+        serde_json::json!({
+            "path": path,
+            "mode": match entry_type == GIT_TREE_OBJECT_TYPE {
+                true => "040000",
+                false => GIT_REGULAR_FILE_MODE,
+            },
+            "type": entry_type,
+            "sha": sha,
+        })
+    }
+
+    fn commit(sha: &str, tree_sha: &str) -> serde_json::Value {
+        serde_json::json!({
+            "sha": sha,
+            "node_id": format!("C_{}", sha),
+            "message": "Publish",
+            "tree": { "sha": tree_sha, "url": git_url(&format!("/trees/{}", tree_sha)) },
+            "parents": [],
+            "html_url": null,
+        })
+    }
+
+    fn reference(sha: &str) -> serde_json::Value {
+        serde_json::json!({
+            "ref": "refs/heads/gh-pages",
+            "node_id": "REF_gh-pages",
+            "url": git_url("/refs/heads/gh-pages"),
+            "object": {
+                "sha": sha,
+                "type": "commit",
+                "url": git_url(&format!("/commits/{}", sha)),
+            },
+        })
+    }
+
+    #[test]
+    fn truncated_tree_still_keeps_preserved_paths() {
+        let directory: PathBuf = std::env::temp_dir().join(format!(
+            "lorerunner-publish-{}-truncated",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(directory.join("index.html"), b"hello world\n").unwrap();
+        let root_tree: serde_json::Value = serde_json::json!({
+            "sha": "root-tree",
+            "tree": [
+                tree_entry("CNAME", GIT_BLOB_OBJECT_TYPE, "cname-blob"),
+                tree_entry(".well-known", GIT_TREE_OBJECT_TYPE, "well-known-tree"),
+            ],
+            "truncated": false,
+        });
+        let well_known_tree: serde_json::Value = serde_json::json!({
+            "sha": "well-known-tree",
+            "tree": [tree_entry("security.txt", GIT_BLOB_OBJECT_TYPE, "security-blob")],
+            "truncated": false,
+        });
+        let truncated_tree: serde_json::Value =
+            serde_json::json!({ "sha": "root-tree", "tree": [], "truncated": true });
+        let index_blob: serde_json::Value = serde_json::json!({
+            "sha": "3b18e512dba79e4c8300dd08aeb37f8e728b8dad",
+            "url": git_url("/blobs/3b18e512dba79e4c8300dd08aeb37f8e728b8dad"),
+        });
+        let new_tree: serde_json::Value =
+            serde_json::json!({ "sha": "new-tree", "tree": [], "truncated": false });
+        let token: HttpResponse = HttpResponse::new(
+            201,
+            &access_token_body(
+                "ghs_pages",
+                time::OffsetDateTime::now_utc() + time::Duration::hours(1),
+            ),
+        );
+        // The root tree is walked once for each preserved path missing from the export.
+        let github_app: GitHubApp<FakeTransport> = test_github_app(
+            FakeTransport::new()
+                .respond_to("POST", &access_token_url(), token)
+                .respond_to(
+                    "GET",
+                    &git_url("/ref/heads/gh-pages"),
+                    json(200, reference("head-commit")),
+                )
+                .respond_to(
+                    "GET",
+                    &git_url("/commits/head-commit"),
+                    json(200, commit("head-commit", "root-tree")),
+                )
+                .respond_to(
+                    "GET",
+                    &git_url("/trees/root-tree?recursive=1"),
+                    json(200, truncated_tree),
+                )
+                .respond_to("POST", &git_url("/blobs"), json(201, index_blob))
+                .respond_to(
+                    "GET",
+                    &git_url("/trees/root-tree"),
+                    json(200, root_tree.clone()),
+                )
+                .respond_to(
+                    "GET",
+                    &git_url("/trees/root-tree"),
+                    json(200, root_tree.clone()),
+                )
+                .respond_to("GET", &git_url("/trees/root-tree"), json(200, root_tree))
+                .respond_to(
+                    "GET",
+                    &git_url("/trees/well-known-tree"),
+                    json(200, well_known_tree),
+                )
+                .respond_to("POST", &git_url("/trees"), json(201, new_tree))
+                .respond_to(
+                    "POST",
+                    &git_url("/commits"),
+                    json(201, commit("new-commit", "new-tree")),
+                )
+                .respond_to(
+                    "PATCH",
+                    &git_url("/refs/heads/gh-pages"),
+                    json(200, reference("new-commit")),
+                ),
+        );
+
+        let published: Option<GitCommit> = github_app
+            .publish_directory(
+                REPOSITORY,
+                "gh-pages",
+                &directory,
+                &[
+                    String::from("CNAME"),
+                    String::from(".well-known/security.txt"),
+                    String::from("missing.txt"),
+                ],
+                "Publish",
+            )
+            .unwrap();
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(published.unwrap().sha, "new-commit");
+        let requests: Vec<HttpRequest> = github_app.transport().requests();
+        let tree_request: &HttpRequest = requests
+            .iter()
+            .find(|request| request.method == "POST" && request.url == git_url("/trees"))
+            .unwrap();
+        let tree: serde_json::Value =
+            serde_json::from_slice(tree_request.body.as_ref().unwrap()).unwrap();
+        let entries: Vec<(&str, &str)> = tree["tree"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| {
+                (
+                    entry["path"].as_str().unwrap(),
+                    entry["sha"].as_str().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                ("index.html", "3b18e512dba79e4c8300dd08aeb37f8e728b8dad"),
+                ("CNAME", "cname-blob"),
+                (".well-known/security.txt", "security-blob"),
+            ]
+        );
+    }
+}
@@ -1,128 +1,946 @@
-use anyhow::Result;
-use jwt_simple::prelude::{Claims, Duration, JWTClaims, RS256KeyPair, RSAKeyPairLike};
-use log::{error, info};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{anyhow, Result};
+use log::{debug, info, warn};
+use serde::{de::DeserializeOwned, Serialize};
+use time::OffsetDateTime;
 
 use crate::{
-    configuration::constants::{
-        github::{
-            GET_GITHUB_APP_API_ROUTE, GITHUB_API_ACCEPT_HEADER_VALUE, GITHUB_API_URL,
-            GITHUB_API_VERSION, MAX_GITHUB_JWT_EXPIRATION_MINUTES,
-        },
-        http::{
-            github::HTTP_HEADER_GITHUB_API_VERSION, HTTP_HEADER_ACCEPT, HTTP_HEADER_AUTHORIZATION,
+    configuration::{
+        constants::{
+            github::{
+                GET_GITHUB_APP_API_ROUTE, GITHUB_ACCESS_TOKENS_API_ROUTE,
+                GITHUB_API_ACCEPT_HEADER_VALUE, GITHUB_API_VERSION,
+                GITHUB_APP_INSTALLATIONS_API_ROUTE, GITHUB_INSTALLATION_API_ROUTE,
+                GITHUB_INSTALLATION_REPOSITORIES_API_ROUTE, GITHUB_ORGANIZATIONS_API_ROUTE,
+                GITHUB_RATE_LIMIT_API_ROUTE, GITHUB_RATE_LIMIT_MAX_RETRIES,
+                GITHUB_RATE_LIMIT_RESERVE, GITHUB_REPOSITORIES_API_ROUTE,
+                GITHUB_TOKEN_REFRESH_MARGIN_SECONDS, GITHUB_USERS_API_ROUTE,
+            },
+            http::{
+                github::HTTP_HEADER_GITHUB_API_VERSION, HTTP_HEADER_ACCEPT,
+                HTTP_HEADER_AUTHORIZATION, HTTP_HEADER_CONTENT_TYPE, JSON_CONTENT_TYPE,
+            },
         },
+        Configuration,
+    },
+    http::{
+        format_authorization_bearer_token, join_url, ureq_transport::UreqTransport, HttpRequest,
+        HttpResponse, HttpTransport,
+    },
+};
+
+use super::{
+    error::GitHubError,
+    models::{
+        ApiRoot, App, Installation, InstallationAccessToken, InstallationRepositories, RateLimit,
+        RateLimitOverview, Repository,
     },
-    file_system::get_file_content_as_string,
-    http::format_authorization_bearer_token,
+    pagination::{PaginationOptions, Paginator},
+    rate_limit::{parse_rate_limit_headers, rate_limit_resource, retry_delay, time_until_reset},
+    signing_keys::{json_web_token_lifetime, SigningKeys},
 };
 
-pub struct GitHubApp {
-    json_web_token: Option<String>,
+/// A GitHub App client acting on behalf of one installation.
+///
+/// `with_installation` and `for_repository` return clients for other installations that share the
+/// transport, the app JSON web token, the app's rate limits and the per-installation token cache.
+pub struct GitHubApp<T: HttpTransport = UreqTransport> {
+    transport: Arc<T>,
+    api_url: String,
+    upload_url: String,
+    signing_keys: Arc<SigningKeys>,
+    github_app_id: u32,
+    github_app_installation_id: u64,
+    /// The app JSON web token, paired with the index of the signing key that signed it.
+    json_web_token: Arc<Mutex<Option<(usize, AccessToken)>>>,
+    installation_access_tokens: Arc<Mutex<HashMap<u64, AccessToken>>>,
+    /// Installation IDs already looked up by `for_repository`, keyed by `owner/name`.
+    repository_installation_ids: Arc<Mutex<HashMap<String, u64>>>,
+    /// The latest budget GitHub reported for the app JSON web token, per `X-RateLimit-Resource`
+    /// since a spent `search` budget says nothing about `core`.
+    app_rate_limits: Arc<Mutex<HashMap<String, RateLimit>>>,
+    /// The same for this client's installation access token.
+    installation_rate_limits: Mutex<HashMap<String, RateLimit>>,
 }
 
-impl GitHubApp {
-    pub fn new(private_key_path: &str, github_app_id: u32) -> Result<Self> {
-        let json_web_token: String =
-            create_json_web_token(private_key_path, &github_app_id.to_string())?;
-        Ok(GitHubApp {
-            json_web_token: Some(json_web_token),
-        })
+/// Which credential a request is sent with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Authentication {
+    /// The app's own JSON web token, for `/app` endpoints.
+    App,
+    /// The selected installation's access token, for repository and organization endpoints.
+    Installation,
+}
+
+/// A bearer token together with the moment GitHub stops accepting it.
+#[derive(Debug, Clone)]
+struct AccessToken {
+    token: String,
+    expires_at: OffsetDateTime,
+}
+
+impl AccessToken {
+    /// Tokens are refreshed slightly ahead of their expiry so a request never leaves with a token
+    /// that lapses before GitHub receives it.
+    fn is_expiring(&self) -> bool {
+        OffsetDateTime::now_utc() + time::Duration::seconds(GITHUB_TOKEN_REFRESH_MARGIN_SECONDS)
+            >= self.expires_at
     }
+}
 
-    fn get_http_agent() -> ureq::Agent {
-        ureq::Agent::new()
+impl GitHubApp<UreqTransport> {
+    pub fn new(configuration: &Configuration) -> Result<Self> {
+        Self::with_transport(configuration, UreqTransport::new())
     }
+}
 
-    pub fn ping_github(&self) -> Result<()> {
-        info!("Pinging GitHub");
-        let http_agent: ureq::Agent = Self::get_http_agent();
-        let Some(token) = &self.json_web_token else {
-            panic!("Expected a auth token but there wasn't one!");
+impl<T: HttpTransport> GitHubApp<T> {
+    pub fn with_transport(configuration: &Configuration, transport: T) -> Result<Self> {
+        let github_app: GitHubApp<T> = GitHubApp {
+            transport: Arc::new(transport),
+            api_url: configuration.github_api_url.clone(),
+            upload_url: configuration.github_upload_url.clone(),
+            signing_keys: Arc::new(SigningKeys::load(
+                &configuration.get_github_app_private_key_paths(),
+            )?),
+            github_app_id: configuration.github_app_id,
+            github_app_installation_id: configuration.github_app_installation_id as u64,
+            json_web_token: Arc::new(Mutex::new(None)),
+            installation_access_tokens: Arc::new(Mutex::new(HashMap::new())),
+            repository_installation_ids: Arc::new(Mutex::new(HashMap::new())),
+            app_rate_limits: Arc::new(Mutex::new(HashMap::new())),
+            installation_rate_limits: Mutex::new(HashMap::new()),
         };
-        match http_agent
-            .get(GITHUB_API_URL)
-            .set(HTTP_HEADER_ACCEPT, GITHUB_API_ACCEPT_HEADER_VALUE)
-            .set(HTTP_HEADER_GITHUB_API_VERSION, GITHUB_API_VERSION)
-            .set(
-                HTTP_HEADER_AUTHORIZATION,
-                &format_authorization_bearer_token(token),
-            )
-            .call()
-        {
-            Ok(reply) => {
-                info!(
-                    "[GET][{}] Status Code: {} Status Text{}",
-                    GITHUB_API_URL,
-                    reply.status(),
-                    reply.status_text()
-                );
-                info!("\nBody: {}", reply.into_string()?);
-            }
-            Err(ureq::Error::Status(code, response)) => {
-                error!(
-                    "[GET][{}] Status Code: {} Status Text: {} - Response: {}",
-                    GITHUB_API_URL,
-                    code,
-                    response.status_text().to_string(),
-                    response.into_string()?
-                );
-            }
-            Err(error) => {
-                error!("{}", error.to_string());
+        // Sign the first token eagerly so a key that parses but can't sign fails at startup too.
+        github_app.get_json_web_token()?;
+
+        Ok(github_app)
+    }
+
+    /// A client for `installation_id` that shares this client's transport and token caches.
+    ///
+    /// The app's rate limits are shared too, while installation rate limits start afresh, since
+    /// GitHub budgets each installation on its own.
+    pub fn with_installation(&self, installation_id: u64) -> GitHubApp<T> {
+        GitHubApp {
+            transport: self.transport.clone(),
+            api_url: self.api_url.clone(),
+            upload_url: self.upload_url.clone(),
+            signing_keys: self.signing_keys.clone(),
+            github_app_id: self.github_app_id,
+            github_app_installation_id: installation_id,
+            json_web_token: self.json_web_token.clone(),
+            installation_access_tokens: self.installation_access_tokens.clone(),
+            repository_installation_ids: self.repository_installation_ids.clone(),
+            app_rate_limits: self.app_rate_limits.clone(),
+            installation_rate_limits: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// A client for the installation that can access `repository`, looked up once per repository.
+    pub fn for_repository(&self, repository: &str) -> Result<GitHubApp<T>, GitHubError> {
+        let cached_installation_id: Option<u64> = self
+            .repository_installation_ids
+            .lock()
+            .ok()
+            .and_then(|installation_ids| installation_ids.get(repository).copied());
+        let installation_id: u64 = match cached_installation_id {
+            Some(installation_id) => installation_id,
+            None => {
+                let installation: Installation = self.get_repository_installation(repository)?;
+                if let Ok(mut installation_ids) = self.repository_installation_ids.lock() {
+                    installation_ids.insert(repository.to_string(), installation.id);
+                }
+                installation.id
             }
         };
 
-        Ok(())
+        Ok(self.with_installation(installation_id))
     }
 
-    pub fn get_app_details(&self) -> Result<()> {
-        let http_agent: ureq::Agent = Self::get_http_agent();
-        let Some(token) = &self.json_web_token else {
-            panic!("Expected a auth token but there wasn't one!");
-        };
-        info!("[GET][{}{}]", GITHUB_API_URL, GET_GITHUB_APP_API_ROUTE);
-        match http_agent
-            .get(format!("{}{}", GITHUB_API_URL, GET_GITHUB_APP_API_ROUTE).as_str())
-            .set(HTTP_HEADER_ACCEPT, GITHUB_API_ACCEPT_HEADER_VALUE)
-            .set(HTTP_HEADER_GITHUB_API_VERSION, GITHUB_API_VERSION)
-            .set(
-                HTTP_HEADER_AUTHORIZATION,
-                &format_authorization_bearer_token(token),
-            )
-            .call()
+    /// A client for the installation on the organization or user `login`.
+    pub fn for_account(&self, login: &str) -> Result<GitHubApp<T>, GitHubError> {
+        Ok(self.with_installation(self.get_account_installation(login)?.id))
+    }
+
+    pub fn get_installation_id(&self) -> u64 {
+        self.github_app_installation_id
+    }
+
+    pub fn get_api_url(&self) -> &str {
+        &self.api_url
+    }
+
+    pub fn get_upload_url(&self) -> &str {
+        &self.upload_url
+    }
+
+    /// The transport requests go through, for tests to inspect what was sent.
+    #[cfg(test)]
+    pub(crate) fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    /// Returns the app JSON web token, minting a new one when the cached token is about to expire
+    /// or was signed with a key that is no longer active.
+    pub fn get_json_web_token(&self) -> Result<String, GitHubError> {
+        let mut cached_token = self.json_web_token.lock().map_err(|_| {
+            GitHubError::Authentication(anyhow!(
+                "GitHub App JSON web token cache lock was poisoned."
+            ))
+        })?;
+
+        let key_index: usize = self.signing_keys.active_index();
+        if let Some((_, access_token)) = cached_token
+            .as_ref()
+            .filter(|(signed_with, token)| *signed_with == key_index && !token.is_expiring())
+        {
+            return Ok(access_token.token.clone());
+        }
+
+        debug!("Minting GitHub App JSON web token");
+        let expires_at: OffsetDateTime = OffsetDateTime::now_utc()
+            + time::Duration::seconds(json_web_token_lifetime().as_secs() as i64);
+        let token: String = self
+            .signing_keys
+            .sign(key_index, &self.github_app_id.to_string())
+            .map_err(GitHubError::Authentication)?;
+        *cached_token = Some((
+            key_index,
+            AccessToken {
+                token: token.clone(),
+                expires_at,
+            },
+        ));
+
+        Ok(token)
+    }
+
+    /// Returns an access token for the selected installation, exchanging the app JSON web token
+    /// for a new one when the cached token is about to expire.
+    pub fn get_installation_access_token(&self) -> Result<String, GitHubError> {
+        let mut cached_tokens = self.installation_access_tokens.lock().map_err(|_| {
+            GitHubError::Authentication(anyhow!(
+                "GitHub installation access token cache lock was poisoned."
+            ))
+        })?;
+
+        if let Some(access_token) = cached_tokens
+            .get(&self.github_app_installation_id)
+            .filter(|token| !token.is_expiring())
         {
-            Ok(reply) => {
-                info!(
-                    "\n[GET][{}{}][Status Code: {}][Status Text: {}]",
-                    GITHUB_API_URL,
-                    GET_GITHUB_APP_API_ROUTE,
-                    reply.status(),
-                    reply.status_text()
-                );
-                info!("\nBody: {}", reply.into_string()?);
+            return Ok(access_token.token.clone());
+        }
+
+        if self.github_app_installation_id == 0 {
+            return Err(GitHubError::Authentication(anyhow!(
+                "Cannot create an installation access token. No GitHub App installation ID is configured or selected."
+            )));
+        }
+
+        let route: String = format!(
+            "{}/{}{}",
+            GITHUB_APP_INSTALLATIONS_API_ROUTE,
+            self.github_app_installation_id,
+            GITHUB_ACCESS_TOKENS_API_ROUTE
+        );
+        let request: HttpRequest = HttpRequest::new("POST", &self.api_url_for(&route));
+        let response: InstallationAccessToken =
+            decode(&request, &self.send_request(Authentication::App, &request)?)?;
+        debug!(
+            "Installation {} access token expires at {}",
+            self.github_app_installation_id, response.expires_at
+        );
+
+        cached_tokens.insert(
+            self.github_app_installation_id,
+            AccessToken {
+                token: response.token.clone(),
+                expires_at: response.expires_at,
+            },
+        );
+
+        Ok(response.token)
+    }
+
+    pub(crate) fn get_token(&self, authentication: Authentication) -> Result<String, GitHubError> {
+        match authentication {
+            Authentication::App => self.get_json_web_token(),
+            Authentication::Installation => self.get_installation_access_token(),
+        }
+    }
+
+    pub(crate) fn api_url_for(&self, route: &str) -> String {
+        join_url(&self.api_url, route)
+    }
+
+    pub(crate) fn upload_url_for(&self, route: &str) -> String {
+        join_url(&self.upload_url, route)
+    }
+
+    /// Sends a GET request to `route` and decodes the JSON response.
+    pub(crate) fn get<R: DeserializeOwned>(
+        &self,
+        authentication: Authentication,
+        route: &str,
+    ) -> Result<R, GitHubError> {
+        let request: HttpRequest = HttpRequest::new("GET", &self.api_url_for(route));
+        let response: HttpResponse = self.send_request(authentication, &request)?;
+        decode(&request, &response)
+    }
+
+    /// Downloads the raw body at `route`, following GitHub's redirect to blob storage for archives.
+    pub(crate) fn get_bytes(
+        &self,
+        authentication: Authentication,
+        route: &str,
+    ) -> Result<Vec<u8>, GitHubError> {
+        let request: HttpRequest = HttpRequest::new("GET", &self.api_url_for(route));
+        Ok(self.send_request(authentication, &request)?.body)
+    }
+
+    /// Sends a DELETE request to `route`. GitHub answers these with an empty body.
+    pub(crate) fn delete(
+        &self,
+        authentication: Authentication,
+        route: &str,
+    ) -> Result<(), GitHubError> {
+        let request: HttpRequest = HttpRequest::new("DELETE", &self.api_url_for(route));
+        self.send_request(authentication, &request)?;
+        Ok(())
+    }
+
+    /// Sends `body` as JSON to `route` with `method` and decodes the JSON response.
+    pub(crate) fn send_json<B: Serialize, R: DeserializeOwned>(
+        &self,
+        authentication: Authentication,
+        method: &str,
+        route: &str,
+        body: &B,
+    ) -> Result<R, GitHubError> {
+        let request: HttpRequest = self.json_request(method, route, body)?;
+        let response: HttpResponse = self.send_request(authentication, &request)?;
+        decode(&request, &response)
+    }
+
+    /// Sends `body` as JSON to `route` with `method`, for endpoints that answer `204 No Content`.
+    pub(crate) fn send_json_without_response<B: Serialize>(
+        &self,
+        authentication: Authentication,
+        method: &str,
+        route: &str,
+        body: &B,
+    ) -> Result<(), GitHubError> {
+        let request: HttpRequest = self.json_request(method, route, body)?;
+        self.send_request(authentication, &request)?;
+        Ok(())
+    }
+
+    fn json_request<B: Serialize>(
+        &self,
+        method: &str,
+        route: &str,
+        body: &B,
+    ) -> Result<HttpRequest, GitHubError> {
+        let url: String = self.api_url_for(route);
+        let body: Vec<u8> = serde_json::to_vec(body).map_err(|source| GitHubError::Encode {
+            method: method.to_string(),
+            url: url.clone(),
+            source,
+        })?;
+
+        Ok(HttpRequest::new(method, &url)
+            .header(HTTP_HEADER_CONTENT_TYPE, JSON_CONTENT_TYPE)
+            .body(body))
+    }
+
+    /// Sends `request` with GitHub's API headers and the credential for `authentication`, waiting
+    /// out rate limits. Non-success statuses are returned as `GitHubError::Status`.
+    ///
+    /// When GitHub answers 401 to the app JSON web token and more private keys are configured,
+    /// the request is retried with each of the other keys, since the active one may have just
+    /// been deleted from the app.
+    pub(crate) fn send_request(
+        &self,
+        authentication: Authentication,
+        request: &HttpRequest,
+    ) -> Result<HttpResponse, GitHubError> {
+        let mut fallbacks: usize = 0;
+        loop {
+            let key_index: usize = self.signing_keys.active_index();
+            match self.send_authenticated_request(authentication, request) {
+                Err(error)
+                    if authentication == Authentication::App
+                        && error.is_unauthorized()
+                        && fallbacks + 1 < self.signing_keys.len() =>
+                {
+                    fallbacks += 1;
+                    let next_index: usize = self.signing_keys.fall_back(key_index);
+                    warn!(
+                        "GitHub rejected the JSON web token signed with {}, retrying with {}",
+                        self.signing_key_path(key_index),
+                        self.signing_key_path(next_index)
+                    );
+                }
+                result => return result,
             }
-            Err(ureq::Error::Status(status_code, response)) => {
-                error!(
-                    "\nStatus Code: {} \nBody: {}\n",
-                    status_code,
-                    response.into_string()?
-                );
+        }
+    }
+
+    fn signing_key_path(&self, index: usize) -> &str {
+        self.signing_keys
+            .get(index)
+            .map(|key| key.path.as_str())
+            .unwrap_or_default()
+    }
+
+    fn send_authenticated_request(
+        &self,
+        authentication: Authentication,
+        request: &HttpRequest,
+    ) -> Result<HttpResponse, GitHubError> {
+        let token: String = self.get_token(authentication)?;
+        let mut request: HttpRequest = request.clone();
+        if !request
+            .headers
+            .iter()
+            .any(|(name, _)| name.eq_ignore_ascii_case(HTTP_HEADER_ACCEPT))
+        {
+            request = request.header(HTTP_HEADER_ACCEPT, GITHUB_API_ACCEPT_HEADER_VALUE);
+        }
+        let request: HttpRequest = request
+            .header(HTTP_HEADER_GITHUB_API_VERSION, GITHUB_API_VERSION)
+            .header(
+                HTTP_HEADER_AUTHORIZATION,
+                &format_authorization_bearer_token(&token),
+            );
+
+        let resource: &str = rate_limit_resource(
+            request
+                .url
+                .strip_prefix(self.api_url.trim_end_matches('/'))
+                .unwrap_or_default(),
+        );
+        let mut retries: u32 = 0;
+        let response: HttpResponse = loop {
+            self.wait_for_rate_limit_budget(authentication, resource);
+            info!("[{}][{}]", request.method, request.url);
+            let response: HttpResponse =
+                self.transport
+                    .send(&request)
+                    .map_err(|source| GitHubError::Transport {
+                        method: request.method.clone(),
+                        url: request.url.clone(),
+                        source,
+                    })?;
+            self.record_rate_limit(authentication, resource, &response);
+
+            match retry_delay(&response) {
+                Some(delay) if retries < GITHUB_RATE_LIMIT_MAX_RETRIES => {
+                    retries += 1;
+                    warn!(
+                        "[{}][{}] Rate limited by GitHub, retrying in {} seconds ({}/{})",
+                        request.method,
+                        request.url,
+                        delay.as_secs(),
+                        retries,
+                        GITHUB_RATE_LIMIT_MAX_RETRIES
+                    );
+                    std::thread::sleep(delay);
+                }
+                _ => break response,
             }
-            Err(error) => {
-                error!("{}", error.to_string());
+        };
+
+        debug!(
+            "[{}][{}][Status Code: {}][Status Text: {}]",
+            request.method, request.url, response.status, response.status_text
+        );
+        if !response.is_success() {
+            return Err(GitHubError::Status {
+                method: request.method,
+                url: request.url,
+                status_code: response.status,
+                status_text: response.status_text.clone(),
+                body: response.body_as_string(),
+            });
+        }
+
+        Ok(response)
+    }
+
+    /// The latest rate limit GitHub reported for each credential and resource used so far.
+    pub fn rate_limits(&self) -> Vec<(Authentication, String, RateLimit)> {
+        let mut rate_limits: Vec<(Authentication, String, RateLimit)> = Vec::new();
+        for authentication in [Authentication::App, Authentication::Installation] {
+            if let Ok(recorded) = self.rate_limit_store(authentication).lock() {
+                rate_limits.extend(recorded.iter().map(|(resource, rate_limit)| {
+                    (authentication, resource.clone(), rate_limit.clone())
+                }));
             }
+        }
+        rate_limits.sort_by(|left, right| left.1.cmp(&right.1));
+
+        rate_limits
+    }
+
+    /// Where the rate limits for `authentication` are kept.
+    fn rate_limit_store(
+        &self,
+        authentication: Authentication,
+    ) -> &Mutex<HashMap<String, RateLimit>> {
+        match authentication {
+            Authentication::App => &self.app_rate_limits,
+            Authentication::Installation => &self.installation_rate_limits,
+        }
+    }
+
+    /// Records the budget `response` reports, under the resource GitHub names or else the one
+    /// the request was expected to draw from.
+    fn record_rate_limit(
+        &self,
+        authentication: Authentication,
+        resource: &str,
+        response: &HttpResponse,
+    ) {
+        let Some(rate_limit) = parse_rate_limit_headers(response) else {
+            return;
         };
+        let resource: String = rate_limit
+            .resource
+            .clone()
+            .unwrap_or_else(|| resource.to_string());
+        debug!(
+            "GitHub rate limit ({:?} {}): {}/{} remaining, resets at {}",
+            authentication, resource, rate_limit.remaining, rate_limit.limit, rate_limit.reset
+        );
+        if let Ok(mut rate_limits) = self.rate_limit_store(authentication).lock() {
+            rate_limits.insert(resource, rate_limit);
+        }
+    }
 
-        Ok(())
+    /// Pauses until the rate limit window resets when the last response for the same credential
+    /// and resource showed the budget was nearly spent, rather than burning the remaining
+    /// requests and failing.
+    fn wait_for_rate_limit_budget(&self, authentication: Authentication, resource: &str) {
+        let Some(rate_limit) = self
+            .rate_limit_store(authentication)
+            .lock()
+            .ok()
+            .and_then(|rate_limits| rate_limits.get(resource).cloned())
+        else {
+            return;
+        };
+        if rate_limit.remaining > GITHUB_RATE_LIMIT_RESERVE
+            || rate_limit.reset <= OffsetDateTime::now_utc().unix_timestamp()
+        {
+            return;
+        }
+
+        let delay: std::time::Duration = time_until_reset(&rate_limit);
+        warn!(
+            "Only {}/{} GitHub {} requests remaining, pausing {} seconds until the window resets",
+            rate_limit.remaining,
+            rate_limit.limit,
+            resource,
+            delay.as_secs()
+        );
+        std::thread::sleep(delay);
+    }
+
+    /// Calls the GitHub API root to verify connectivity and app authentication.
+    pub fn ping_github(&self) -> Result<ApiRoot, GitHubError> {
+        info!("Pinging GitHub");
+        self.get(Authentication::App, "")
+    }
+
+    pub fn get_app_details(&self) -> Result<App, GitHubError> {
+        self.get(Authentication::App, GET_GITHUB_APP_API_ROUTE)
+    }
+
+    pub fn get_installation(&self) -> Result<Installation, GitHubError> {
+        self.get(
+            Authentication::App,
+            &format!(
+                "{}/{}",
+                GITHUB_APP_INSTALLATIONS_API_ROUTE, self.github_app_installation_id
+            ),
+        )
+    }
+
+    /// Returns the installation that can access `repository`.
+    pub fn get_repository_installation(
+        &self,
+        repository: &str,
+    ) -> Result<Installation, GitHubError> {
+        self.get(
+            Authentication::App,
+            &format!(
+                "{}{}",
+                repository_route(repository),
+                GITHUB_INSTALLATION_API_ROUTE
+            ),
+        )
+    }
+
+    /// Returns the installation on the organization or user `login`.
+    pub fn get_account_installation(&self, login: &str) -> Result<Installation, GitHubError> {
+        let organization_route: String = format!(
+            "{}/{}{}",
+            GITHUB_ORGANIZATIONS_API_ROUTE, login, GITHUB_INSTALLATION_API_ROUTE
+        );
+        match self.get(Authentication::App, &organization_route) {
+            Err(error) if error.is_not_found() => self.get(
+                Authentication::App,
+                &format!(
+                    "{}/{}{}",
+                    GITHUB_USERS_API_ROUTE, login, GITHUB_INSTALLATION_API_ROUTE
+                ),
+            ),
+            result => result,
+        }
+    }
+
+    pub fn list_installations(
+        &self,
+        options: PaginationOptions,
+    ) -> Paginator<'_, T, Vec<Installation>, Installation> {
+        Paginator::new(
+            self,
+            Authentication::App,
+            GITHUB_APP_INSTALLATIONS_API_ROUTE,
+            options,
+        )
+    }
+
+    /// Lists the repositories the selected installation has been granted access to.
+    pub fn list_installation_repositories(
+        &self,
+        options: PaginationOptions,
+    ) -> Paginator<'_, T, InstallationRepositories, Repository> {
+        Paginator::new(
+            self,
+            Authentication::Installation,
+            GITHUB_INSTALLATION_REPOSITORIES_API_ROUTE,
+            options,
+        )
+    }
+
+    pub fn get_rate_limit(&self) -> Result<RateLimitOverview, GitHubError> {
+        self.get(Authentication::Installation, GITHUB_RATE_LIMIT_API_ROUTE)
     }
 }
 
-pub fn create_json_web_token(key_path: &str, issuer: &str) -> Result<String> {
-    let private_key_string: String = get_file_content_as_string(String::from(key_path))?;
-    let key_pair: RS256KeyPair = RS256KeyPair::from_pem(&private_key_string)?;
-    let claims: JWTClaims<jwt_simple::prelude::NoCustomClaims> =
-        Claims::create(Duration::from_mins(MAX_GITHUB_JWT_EXPIRATION_MINUTES)).with_issuer(issuer);
-    let token: String = key_pair.sign(claims)?;
+/// Decodes a successful JSON response into `R`.
+pub(crate) fn decode<R: DeserializeOwned>(
+    request: &HttpRequest,
+    response: &HttpResponse,
+) -> Result<R, GitHubError> {
+    serde_json::from_slice(&response.body).map_err(|source| GitHubError::Decode {
+        method: request.method.clone(),
+        url: request.url.clone(),
+        source,
+    })
+}
+
+/// Route of a repository's endpoints, for `repository` in `owner/name` form.
+pub(crate) fn repository_route(repository: &str) -> String {
+    format!("{}/{}", GITHUB_REPOSITORIES_API_ROUTE, repository)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        github::test_support::{
+            access_token_body, access_token_url, authorization, installation_json,
+            test_configuration, test_github_app, test_url, TEST_PRIVATE_KEY_PATHS,
+        },
+        http::fake::FakeTransport,
+    };
+
+    fn installation_url() -> String {
+        test_url(&format!(
+            "{}/{}",
+            GITHUB_APP_INSTALLATIONS_API_ROUTE,
+            test_configuration().github_app_installation_id
+        ))
+    }
+
+    #[test]
+    fn installation_access_token_is_exchanged_once_and_cached() {
+        let github_app: GitHubApp<FakeTransport> =
+            test_github_app(FakeTransport::new().respond_to(
+                "POST",
+                &access_token_url(),
+                HttpResponse::new(
+                    201,
+                    &access_token_body(
+                        "ghs_cached",
+                        OffsetDateTime::now_utc() + time::Duration::hours(1),
+                    ),
+                ),
+            ));
+
+        assert_eq!(
+            github_app.get_installation_access_token().unwrap(),
+            "ghs_cached"
+        );
+        assert_eq!(
+            github_app.get_installation_access_token().unwrap(),
+            "ghs_cached"
+        );
+
+        let requests: Vec<HttpRequest> = github_app.transport.requests();
+        assert_eq!(requests.len(), 1);
+        let app_token: String = github_app.get_json_web_token().unwrap();
+        assert_eq!(
+            authorization(&requests[0]),
+            Some(format_authorization_bearer_token(&app_token).as_str())
+        );
+    }
+
+    #[test]
+    fn installation_access_token_is_refreshed_before_it_expires() {
+        let github_app: GitHubApp<FakeTransport> = test_github_app(
+            FakeTransport::new()
+                .respond_to(
+                    "POST",
+                    &access_token_url(),
+                    HttpResponse::new(
+                        201,
+                        &access_token_body(
+                            "ghs_expiring",
+                            OffsetDateTime::now_utc() + time::Duration::seconds(10),
+                        ),
+                    ),
+                )
+                .respond_to(
+                    "POST",
+                    &access_token_url(),
+                    HttpResponse::new(
+                        201,
+                        &access_token_body(
+                            "ghs_refreshed",
+                            OffsetDateTime::now_utc() + time::Duration::hours(1),
+                        ),
+                    ),
+                )
+                .respond_to(
+                    "GET",
+                    &test_url(GITHUB_RATE_LIMIT_API_ROUTE),
+                    HttpResponse::new(200, "{}"),
+                ),
+        );
+
+        assert_eq!(
+            github_app.get_installation_access_token().unwrap(),
+            "ghs_expiring"
+        );
+        assert_eq!(
+            github_app.get_installation_access_token().unwrap(),
+            "ghs_refreshed"
+        );
+        github_app
+            .get_bytes(Authentication::Installation, GITHUB_RATE_LIMIT_API_ROUTE)
+            .unwrap();
+
+        let requests: Vec<HttpRequest> = github_app.transport.requests();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(authorization(&requests[2]), Some("Bearer ghs_refreshed"));
+    }
+
+    #[test]
+    fn rejected_json_web_token_falls_back_to_the_next_key() {
+        let configuration: Configuration = Configuration {
+            github_app_additional_private_key_paths: vec![TEST_PRIVATE_KEY_PATHS[1].to_string()],
+            ..test_configuration()
+        };
+        let github_app: GitHubApp<FakeTransport> = GitHubApp::with_transport(
+            &configuration,
+            FakeTransport::new()
+                .respond_to(
+                    "GET",
+                    &installation_url(),
+                    HttpResponse::new(401, r#"{"message":"Bad credentials"}"#),
+                )
+                .respond_to(
+                    "GET",
+                    &installation_url(),
+                    HttpResponse::new(200, &installation_json(5678).to_string()),
+                ),
+        )
+        .unwrap();
+
+        let installation: Installation = github_app.get_installation().unwrap();
 
-    Ok(token)
+        assert_eq!(installation.id, 5678);
+        assert_eq!(github_app.signing_keys.active_index(), 1);
+        let requests: Vec<HttpRequest> = github_app.transport.requests();
+        assert_eq!(requests.len(), 2);
+        assert_ne!(authorization(&requests[0]), authorization(&requests[1]));
+        assert_eq!(
+            authorization(&requests[1]),
+            Some(
+                format_authorization_bearer_token(&github_app.get_json_web_token().unwrap())
+                    .as_str()
+            )
+        );
+    }
+
+    #[test]
+    fn rejected_json_web_token_with_a_single_key_is_returned() {
+        let github_app: GitHubApp<FakeTransport> =
+            test_github_app(FakeTransport::new().respond_to(
+                "GET",
+                &installation_url(),
+                HttpResponse::new(401, r#"{"message":"Bad credentials"}"#),
+            ));
+
+        let error: GitHubError = github_app.get_installation().unwrap_err();
+
+        assert!(error.is_unauthorized());
+        assert_eq!(github_app.transport.requests().len(), 1);
+    }
+
+    #[test]
+    fn spent_search_budget_does_not_hold_up_core_requests() {
+        let search_route: &str = "/search/issues?q=lore";
+        let reset: String = (OffsetDateTime::now_utc().unix_timestamp() + 3600).to_string();
+        let github_app: GitHubApp<FakeTransport> = test_github_app(
+            FakeTransport::new()
+                .respond_to(
+                    "GET",
+                    &test_url(search_route),
+                    HttpResponse::new(200, "{}")
+                        .with_header("X-RateLimit-Limit", "30")
+                        .with_header("X-RateLimit-Remaining", "0")
+                        .with_header("X-RateLimit-Reset", &reset)
+                        .with_header("X-RateLimit-Resource", "search"),
+                )
+                .respond_to(
+                    "GET",
+                    &test_url(GET_GITHUB_APP_API_ROUTE),
+                    HttpResponse::new(200, "{}")
+                        .with_header("X-RateLimit-Limit", "5000")
+                        .with_header("X-RateLimit-Remaining", "4999")
+                        .with_header("X-RateLimit-Reset", &reset)
+                        .with_header("X-RateLimit-Resource", "core"),
+                ),
+        );
+
+        github_app
+            .get_bytes(Authentication::App, search_route)
+            .unwrap();
+        // Would sleep for an hour if the spent search budget were applied to core.
+        github_app
+            .get_bytes(Authentication::App, GET_GITHUB_APP_API_ROUTE)
+            .unwrap();
+
+        let rate_limits: Vec<(Authentication, String, RateLimit)> = github_app.rate_limits();
+        assert_eq!(rate_limits.len(), 2);
+        assert_eq!(rate_limits[0].1, "core");
+        assert_eq!(rate_limits[0].2.remaining, 4999);
+        assert_eq!(rate_limits[1].1, "search");
+        assert_eq!(rate_limits[1].2.remaining, 0);
+    }
+
+    #[test]
+    fn installation_clients_share_app_rate_limits_only() {
+        let reset: String = (OffsetDateTime::now_utc().unix_timestamp() + 3600).to_string();
+        let github_app: GitHubApp<FakeTransport> = test_github_app(
+            FakeTransport::new()
+                .respond_to(
+                    "GET",
+                    &test_url(GET_GITHUB_APP_API_ROUTE),
+                    HttpResponse::new(200, "{}")
+                        .with_header("X-RateLimit-Limit", "5000")
+                        .with_header("X-RateLimit-Remaining", "4000")
+                        .with_header("X-RateLimit-Reset", &reset)
+                        .with_header("X-RateLimit-Resource", "core"),
+                )
+                .respond_to(
+                    "POST",
+                    &access_token_url(),
+                    HttpResponse::new(
+                        201,
+                        &access_token_body(
+                            "ghs_parent",
+                            OffsetDateTime::now_utc() + time::Duration::hours(1),
+                        ),
+                    ),
+                )
+                .respond_to(
+                    "GET",
+                    &test_url(GITHUB_RATE_LIMIT_API_ROUTE),
+                    HttpResponse::new(200, "{}")
+                        .with_header("X-RateLimit-Limit", "5000")
+                        .with_header("X-RateLimit-Remaining", "100")
+                        .with_header("X-RateLimit-Reset", &reset)
+                        .with_header("X-RateLimit-Resource", "core"),
+                ),
+        );
+
+        github_app
+            .get_bytes(Authentication::App, GET_GITHUB_APP_API_ROUTE)
+            .unwrap();
+        github_app
+            .get_bytes(Authentication::Installation, GITHUB_RATE_LIMIT_API_ROUTE)
+            .unwrap();
+        let other_installation: GitHubApp<FakeTransport> = github_app.with_installation(99);
+
+        let rate_limits: Vec<(Authentication, String, RateLimit)> =
+            other_installation.rate_limits();
+        assert_eq!(rate_limits.len(), 1);
+        assert_eq!(rate_limits[0].0, Authentication::App);
+        assert_eq!(rate_limits[0].2.remaining, 4000);
+        assert_eq!(github_app.rate_limits().len(), 2);
+    }
+
+    #[test]
+    fn list_installations_follows_link_headers() {
+        let first_page_url: String = test_url("/app/installations?per_page=2");
+        let second_page_url: String = test_url("/app/installations?per_page=2&page=2");
+        let github_app: GitHubApp<FakeTransport> = test_github_app(
+            FakeTransport::new()
+                .respond_to(
+                    "GET",
+                    &first_page_url,
+                    HttpResponse::new(
+                        200,
+                        &serde_json::json!([installation_json(1), installation_json(2)])
+                            .to_string(),
+                    )
+                    .with_header(
+                        "Link",
+                        &format!(
+                            "<{}>; rel=\"next\", <{}>; rel=\"last\"",
+                            second_page_url, second_page_url
+                        ),
+                    ),
+                )
+                .respond_to(
+                    "GET",
+                    &second_page_url,
+                    HttpResponse::new(200, &serde_json::json!([installation_json(3)]).to_string())
+                        .with_header("Link", &format!("<{}>; rel=\"first\"", first_page_url)),
+                ),
+        );
+
+        let installation_ids: Vec<u64> = github_app
+            .list_installations(PaginationOptions {
+                per_page: 2,
+                max_pages: None,
+            })
+            .map(|installation| installation.map(|installation| installation.id))
+            .collect::<Result<Vec<u64>, GitHubError>>()
+            .unwrap();
+
+        assert_eq!(installation_ids, vec![1, 2, 3]);
+        assert_eq!(github_app.transport.requests().len(), 2);
+    }
 }
@@ -1 +1,16 @@
+pub mod actions;
+pub mod check_runs;
+pub mod deployments;
+pub mod error;
+pub mod git_data;
 pub mod github_app;
+pub mod models;
+pub mod pages;
+pub mod pagination;
+pub mod rate_limit;
+pub mod releases;
+pub mod signing_keys;
+#[cfg(test)]
+pub(crate) mod test_support;
+pub mod webhooks;
+pub mod workflows;
@@ -0,0 +1,541 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+/// Hypermedia URL templates returned by the root of the GitHub REST API.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiRoot {
+    #[serde(flatten)]
+    pub urls: HashMap<String, String>,
+}
+
+/// A user or organization that owns an app, installation or repository.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Account {
+    pub login: String,
+    pub id: u64,
+    pub node_id: String,
+    pub html_url: String,
+    #[serde(rename = "type")]
+    pub account_type: String,
+    #[serde(default)]
+    pub site_admin: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct App {
+    pub id: u64,
+    pub slug: Option<String>,
+    pub node_id: String,
+    pub owner: Option<Account>,
+    pub name: String,
+    pub description: Option<String>,
+    pub external_url: String,
+    pub html_url: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+    #[serde(default)]
+    pub permissions: HashMap<String, String>,
+    #[serde(default)]
+    pub events: Vec<String>,
+    pub installations_count: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Installation {
+    pub id: u64,
+    pub account: Option<Account>,
+    pub repository_selection: String,
+    pub access_tokens_url: String,
+    pub repositories_url: String,
+    pub html_url: String,
+    pub app_id: u64,
+    pub app_slug: Option<String>,
+    pub target_id: u64,
+    pub target_type: String,
+    #[serde(default)]
+    pub permissions: HashMap<String, String>,
+    #[serde(default)]
+    pub events: Vec<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub suspended_at: Option<OffsetDateTime>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstallationAccessToken {
+    pub token: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub expires_at: OffsetDateTime,
+    #[serde(default)]
+    pub permissions: HashMap<String, String>,
+    pub repository_selection: Option<String>,
+}
+
+/// Timestamps are left out on purpose: webhook payloads encode them as Unix seconds while the
+/// REST API uses RFC 3339 strings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Repository {
+    pub id: u64,
+    pub node_id: String,
+    pub name: String,
+    pub full_name: String,
+    pub owner: Account,
+    pub private: bool,
+    pub html_url: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub fork: bool,
+    pub url: String,
+    pub default_branch: Option<String>,
+    pub visibility: Option<String>,
+    #[serde(default)]
+    pub archived: bool,
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstallationRepositories {
+    pub total_count: u64,
+    pub repositories: Vec<Repository>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateLimit {
+    pub limit: u32,
+    pub remaining: u32,
+    /// Unix timestamp (seconds) at which the window resets.
+    pub reset: i64,
+    pub used: u32,
+    pub resource: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateLimitResources {
+    pub core: RateLimit,
+    pub search: Option<RateLimit>,
+    pub graphql: Option<RateLimit>,
+    #[serde(flatten)]
+    pub other: HashMap<String, RateLimit>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateLimitOverview {
+    pub resources: RateLimitResources,
+    pub rate: RateLimit,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Release {
+    pub id: u64,
+    pub node_id: String,
+    pub tag_name: String,
+    pub target_commitish: String,
+    pub name: Option<String>,
+    pub body: Option<String>,
+    pub draft: bool,
+    pub prerelease: bool,
+    pub html_url: String,
+    pub upload_url: String,
+    #[serde(default)]
+    pub assets: Vec<ReleaseAsset>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub published_at: Option<OffsetDateTime>,
+}
+
+/// Release notes GitHub generates from the pull requests merged between two tags.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeneratedReleaseNotes {
+    pub name: String,
+    pub body: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReleaseAsset {
+    pub id: u64,
+    pub name: String,
+    pub label: Option<String>,
+    pub content_type: String,
+    pub size: u64,
+    pub state: String,
+    pub url: String,
+    pub browser_download_url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowRun {
+    pub id: u64,
+    pub name: Option<String>,
+    pub workflow_id: u64,
+    pub head_branch: Option<String>,
+    pub head_sha: String,
+    pub run_number: u64,
+    pub run_attempt: Option<u32>,
+    pub event: String,
+    pub status: Option<WorkflowRunStatus>,
+    pub conclusion: Option<WorkflowRunConclusion>,
+    pub html_url: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeploymentState {
+    Error,
+    Failure,
+    Inactive,
+    InProgress,
+    Queued,
+    Pending,
+    Success,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Deployment {
+    pub id: u64,
+    pub node_id: String,
+    pub sha: String,
+    #[serde(rename = "ref")]
+    pub git_ref: String,
+    pub task: String,
+    pub environment: String,
+    pub description: Option<String>,
+    pub creator: Option<Account>,
+    pub statuses_url: String,
+    #[serde(default)]
+    pub transient_environment: bool,
+    #[serde(default)]
+    pub production_environment: bool,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeploymentStatus {
+    pub id: u64,
+    pub node_id: String,
+    pub state: DeploymentState,
+    pub description: Option<String>,
+    pub environment: Option<String>,
+    pub environment_url: Option<String>,
+    pub log_url: Option<String>,
+    pub creator: Option<Account>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckRunStatus {
+    Queued,
+    InProgress,
+    Completed,
+    Waiting,
+    Requested,
+    Pending,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckRunConclusion {
+    ActionRequired,
+    Cancelled,
+    Failure,
+    Neutral,
+    Success,
+    Skipped,
+    Stale,
+    TimedOut,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnnotationLevel {
+    Notice,
+    Warning,
+    Failure,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckRun {
+    pub id: u64,
+    pub node_id: String,
+    pub head_sha: String,
+    pub external_id: Option<String>,
+    pub name: String,
+    pub status: CheckRunStatus,
+    pub conclusion: Option<CheckRunConclusion>,
+    pub html_url: Option<String>,
+    pub details_url: Option<String>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub started_at: Option<OffsetDateTime>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub completed_at: Option<OffsetDateTime>,
+    pub output: CheckRunOutputSummary,
+}
+
+/// The output of a check run as GitHub returns it. Annotations are only counted here and must be
+/// fetched from `annotations_url`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckRunOutputSummary {
+    pub title: Option<String>,
+    pub summary: Option<String>,
+    pub text: Option<String>,
+    pub annotations_count: u32,
+    pub annotations_url: String,
+}
+
+/// A branch or tag, as returned by the Git Data API.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitReference {
+    #[serde(rename = "ref")]
+    pub git_ref: String,
+    pub node_id: String,
+    pub url: String,
+    pub object: GitObject,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitObject {
+    pub sha: String,
+    #[serde(rename = "type")]
+    pub object_type: String,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitObjectReference {
+    pub sha: String,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitCommit {
+    pub sha: String,
+    pub node_id: String,
+    pub message: String,
+    pub tree: GitObjectReference,
+    pub parents: Vec<GitObjectReference>,
+    pub html_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitTree {
+    pub sha: String,
+    pub tree: Vec<GitTreeEntry>,
+    /// Set when a recursive listing exceeded GitHub's limits and entries are missing.
+    #[serde(default)]
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitTreeEntry {
+    pub path: String,
+    pub mode: String,
+    #[serde(rename = "type")]
+    pub entry_type: String,
+    pub sha: Option<String>,
+    pub size: Option<u64>,
+}
+
+/// A repository's GitHub Pages site.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pages {
+    pub url: String,
+    pub status: Option<String>,
+    pub cname: Option<String>,
+    #[serde(default)]
+    pub custom_404: bool,
+    pub html_url: Option<String>,
+    /// `legacy` when Pages builds from a branch, `workflow` when a GitHub Actions workflow deploys.
+    pub build_type: Option<String>,
+    pub source: Option<PagesSource>,
+    #[serde(default)]
+    pub public: bool,
+    pub https_enforced: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PagesSource {
+    pub branch: String,
+    /// `/` or `/docs`.
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PageBuildStatus {
+    Queued,
+    Building,
+    Built,
+    Errored,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageBuild {
+    pub url: String,
+    pub status: PageBuildStatus,
+    pub error: PageBuildError,
+    pub pusher: Option<Account>,
+    /// SHA of the pages branch commit that was built.
+    pub commit: Option<String>,
+    /// Build time in milliseconds.
+    pub duration: Option<u64>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageBuildError {
+    pub message: Option<String>,
+}
+
+/// Response to a Pages build request. The build itself is fetched separately.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageBuildRequest {
+    pub url: String,
+    pub status: String,
+}
+
+/// An Actions secret. GitHub never returns secret values.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActionsSecret {
+    pub name: String,
+    /// Which repositories can use an organization secret: `all`, `private` or `selected`.
+    pub visibility: Option<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActionsSecrets {
+    pub total_count: u32,
+    pub secrets: Vec<ActionsSecret>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActionsVariable {
+    pub name: String,
+    pub value: String,
+    /// Which repositories can use an organization variable: `all`, `private` or `selected`.
+    pub visibility: Option<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActionsVariables {
+    pub total_count: u32,
+    pub variables: Vec<ActionsVariable>,
+}
+
+/// The public key Actions secrets for a scope must be sealed with.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActionsPublicKey {
+    pub key_id: String,
+    /// Base64 encoded Curve25519 public key.
+    pub key: String,
+}
+
+/// Status of a workflow run, job or step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkflowRunStatus {
+    Requested,
+    Queued,
+    Pending,
+    Waiting,
+    InProgress,
+    Completed,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkflowRunConclusion {
+    Success,
+    Failure,
+    Cancelled,
+    Skipped,
+    Neutral,
+    TimedOut,
+    ActionRequired,
+    Stale,
+    StartupFailure,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowRuns {
+    pub total_count: u32,
+    pub workflow_runs: Vec<WorkflowRun>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowJob {
+    pub id: u64,
+    pub name: String,
+    pub status: WorkflowRunStatus,
+    pub conclusion: Option<WorkflowRunConclusion>,
+    pub html_url: Option<String>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub started_at: Option<OffsetDateTime>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub completed_at: Option<OffsetDateTime>,
+    #[serde(default)]
+    pub steps: Vec<WorkflowStep>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowJobs {
+    pub total_count: u32,
+    pub jobs: Vec<WorkflowJob>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowStep {
+    pub number: u32,
+    pub name: String,
+    pub status: WorkflowRunStatus,
+    pub conclusion: Option<WorkflowRunConclusion>,
+}
+
+/// A file uploaded by a workflow run with `actions/upload-artifact`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Artifact {
+    pub id: u64,
+    pub name: String,
+    pub size_in_bytes: u64,
+    /// Expired artifacts are listed but can no longer be downloaded.
+    pub expired: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Artifacts {
+    pub total_count: u32,
+    pub artifacts: Vec<Artifact>,
+}
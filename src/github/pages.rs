@@ -0,0 +1,273 @@
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use log::{debug, info};
+use serde::Serialize;
+
+use crate::{
+    configuration::constants::github::{
+        GITHUB_PAGES_API_ROUTE, GITHUB_PAGES_BUILDS_API_ROUTE,
+        GITHUB_PAGES_BUILD_POLL_INTERVAL_SECONDS, GITHUB_PAGES_LATEST_BUILD_API_ROUTE,
+    },
+    http::HttpTransport,
+};
+
+use super::{
+    error::GitHubError,
+    github_app::{repository_route, Authentication, GitHubApp},
+    models::{PageBuild, PageBuildRequest, PageBuildStatus, Pages, PagesSource},
+};
+
+/// Body of `PUT /repos/{owner}/{repo}/pages`. Fields left as `None` keep their current value.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PagesUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cname: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub https_enforced: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<PagesSource>,
+}
+
+impl<T: HttpTransport> GitHubApp<T> {
+    /// Returns the repository's Pages site, or `None` when Pages isn't enabled.
+    pub fn get_pages(&self, repository: &str) -> Result<Option<Pages>, GitHubError> {
+        match self.get(Authentication::Installation, &pages_route(repository)) {
+            Ok(pages) => Ok(Some(pages)),
+            Err(error) if error.is_not_found() => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    pub fn update_pages(&self, repository: &str, update: &PagesUpdate) -> Result<(), GitHubError> {
+        self.send_json_without_response(
+            Authentication::Installation,
+            "PUT",
+            &pages_route(repository),
+            update,
+        )
+    }
+
+    /// Asks GitHub to build the Pages site from the latest commit on its source branch.
+    pub fn request_pages_build(&self, repository: &str) -> Result<PageBuildRequest, GitHubError> {
+        self.send_json(
+            Authentication::Installation,
+            "POST",
+            &format!(
+                "{}{}",
+                repository_route(repository),
+                GITHUB_PAGES_BUILDS_API_ROUTE
+            ),
+            &serde_json::json!({}),
+        )
+    }
+
+    /// Returns the most recent Pages build, or `None` when the site has never been built.
+    pub fn get_latest_pages_build(
+        &self,
+        repository: &str,
+    ) -> Result<Option<PageBuild>, GitHubError> {
+        let route: String = format!(
+            "{}{}",
+            repository_route(repository),
+            GITHUB_PAGES_LATEST_BUILD_API_ROUTE
+        );
+        match self.get(Authentication::Installation, &route) {
+            Ok(build) => Ok(Some(build)),
+            Err(error) if error.is_not_found() => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Polls the latest Pages build until it is built or errored, failing when it errors or
+    /// `timeout` passes first.
+    ///
+    /// Builds rejected by `is_expected_build` are treated as not started yet, so a build that
+    /// finished before the one being waited for isn't mistaken for its result.
+    pub fn wait_for_pages_build<F>(
+        &self,
+        repository: &str,
+        timeout: Duration,
+        is_expected_build: F,
+    ) -> Result<PageBuild>
+    where
+        F: Fn(&PageBuild) -> bool,
+    {
+        let started: Instant = Instant::now();
+
+        loop {
+            let build: Option<PageBuild> = self
+                .get_latest_pages_build(repository)?
+                .filter(|build| is_expected_build(build));
+
+            match build {
+                Some(build) if build.status == PageBuildStatus::Built => {
+                    info!(
+                        "Pages build of {} finished in {} ms",
+                        build.commit.as_deref().unwrap_or_default(),
+                        build.duration.unwrap_or_default()
+                    );
+                    return Ok(build);
+                }
+                Some(build) if build.status == PageBuildStatus::Errored => {
+                    return Err(anyhow!(
+                        "Pages build of {} in {} failed: {}",
+                        build.commit.as_deref().unwrap_or_default(),
+                        repository,
+                        build.error.message.as_deref().unwrap_or("no error message")
+                    ));
+                }
+                Some(build) => debug!("Pages build is {:?}", build.status),
+                None => debug!("Waiting for the Pages build to start"),
+            }
+
+            if started.elapsed() >= timeout {
+                return Err(anyhow!(
+                    "Timed out after {} seconds waiting for the Pages build of {}",
+                    timeout.as_secs(),
+                    repository
+                ));
+            }
+            std::thread::sleep(Duration::from_secs(
+                GITHUB_PAGES_BUILD_POLL_INTERVAL_SECONDS,
+            ));
+        }
+    }
+}
+
+fn pages_route(repository: &str) -> String {
+    format!("{}{}", repository_route(repository), GITHUB_PAGES_API_ROUTE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        github::test_support::{access_token_body, access_token_url, test_github_app, test_url},
+        http::{fake::FakeTransport, HttpResponse},
+    };
+
+    const REPOSITORY: &str = "chronilore/loremaster";
+    const EXPECTED_COMMIT: &str = "0123456789abcdef0123456789abcdef01234567";
+    const OTHER_COMMIT: &str = "89abcdef0123456789abcdef0123456789abcdef";
+
+    fn transport_with_token() -> FakeTransport {
+        FakeTransport::new().respond_to(
+            "POST",
+            &access_token_url(),
+            HttpResponse::new(
+                201,
+                &access_token_body(
+                    "ghs_pages",
+                    time::OffsetDateTime::now_utc() + time::Duration::hours(1),
+                ),
+            ),
+        )
+    }
+
+    fn latest_build_url() -> String {
+        test_url(&format!("/repos/{}/pages/builds/latest", REPOSITORY))
+    }
+
+    fn build_response(commit: &str, status: &str, error: Option<&str>) -> HttpResponse {
+        HttpResponse::new(
+            200,
+            &serde_json::json!({
+                "url": test_url(&format!("/repos/{}/pages/builds/5", REPOSITORY)),
+                "status": status,
+                "error": { "message": error },
+                "pusher": null,
+                "commit": commit,
+                "duration": 2104,
+                "created_at": "2024-06-01T12:00:00Z",
+                "updated_at": "2024-06-01T12:00:30Z",
+            })
+            .to_string(),
+        )
+    }
+
+    fn wait_for_expected_build(transport: FakeTransport) -> Result<PageBuild> {
+        test_github_app(transport).wait_for_pages_build(REPOSITORY, Duration::ZERO, |build| {
+            build.commit.as_deref() == Some(EXPECTED_COMMIT)
+        })
+    }
+
+    #[test]
+    fn built_build_is_returned() {
+        let build: PageBuild = wait_for_expected_build(transport_with_token().respond_to(
+            "GET",
+            &latest_build_url(),
+            build_response(EXPECTED_COMMIT, "built", None),
+        ))
+        .unwrap();
+
+        assert_eq!(build.status, PageBuildStatus::Built);
+    }
+
+    #[test]
+    fn errored_build_fails_with_its_message() {
+        let error: anyhow::Error = wait_for_expected_build(transport_with_token().respond_to(
+            "GET",
+            &latest_build_url(),
+            build_response(EXPECTED_COMMIT, "errored", Some("Page build failed.")),
+        ))
+        .unwrap_err();
+
+        assert!(
+            error.to_string().contains("Page build failed."),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn build_of_another_commit_is_not_taken_as_the_result() {
+        // The earlier build succeeded, but the one being waited for hasn't started yet.
+        let error: anyhow::Error = wait_for_expected_build(transport_with_token().respond_to(
+            "GET",
+            &latest_build_url(),
+            build_response(OTHER_COMMIT, "built", None),
+        ))
+        .unwrap_err();
+
+        assert!(error.to_string().contains("Timed out"), "{}", error);
+    }
+
+    #[test]
+    fn missing_site_and_build_are_none() {
+        let github_app: GitHubApp<FakeTransport> = test_github_app(
+            transport_with_token()
+                .respond_to(
+                    "GET",
+                    &test_url(&format!("/repos/{}/pages", REPOSITORY)),
+                    HttpResponse::new(404, r#"{"message":"Not Found"}"#),
+                )
+                .respond_to(
+                    "GET",
+                    &latest_build_url(),
+                    HttpResponse::new(404, r#"{"message":"Not Found"}"#),
+                ),
+        );
+
+        assert!(github_app.get_pages(REPOSITORY).unwrap().is_none());
+        assert!(github_app
+            .get_latest_pages_build(REPOSITORY)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn other_errors_are_not_mistaken_for_a_missing_site() {
+        let github_app: GitHubApp<FakeTransport> =
+            test_github_app(transport_with_token().respond_to(
+                "GET",
+                &test_url(&format!("/repos/{}/pages", REPOSITORY)),
+                HttpResponse::new(
+                    403,
+                    r#"{"message":"Resource not accessible by integration"}"#,
+                ),
+            ));
+
+        assert!(github_app.get_pages(REPOSITORY).is_err());
+    }
+}
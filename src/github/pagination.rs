@@ -0,0 +1,266 @@
+use std::{collections::VecDeque, marker::PhantomData};
+
+use serde::de::DeserializeOwned;
+
+use crate::{
+    configuration::constants::{
+        github::{GITHUB_DEFAULT_PER_PAGE, GITHUB_MAX_PER_PAGE},
+        http::HTTP_HEADER_LINK,
+    },
+    http::{HttpRequest, HttpResponse, HttpTransport},
+};
+
+use super::{
+    error::GitHubError,
+    github_app::{decode, Authentication, GitHubApp},
+    models::{
+        ActionsSecret, ActionsSecrets, ActionsVariable, ActionsVariables, Artifact, Artifacts,
+        InstallationRepositories, Repository, WorkflowJob, WorkflowJobs, WorkflowRun, WorkflowRuns,
+    },
+};
+
+#[derive(Debug, Clone, Copy)]
+pub struct PaginationOptions {
+    /// Items requested per page. GitHub caps this at 100.
+    pub per_page: u32,
+    /// Stop after this many pages even if GitHub reports more.
+    pub max_pages: Option<u32>,
+}
+
+impl Default for PaginationOptions {
+    fn default() -> Self {
+        PaginationOptions {
+            per_page: GITHUB_DEFAULT_PER_PAGE,
+            max_pages: None,
+        }
+    }
+}
+
+/// The body of one page of a list endpoint.
+///
+/// Most endpoints return a bare JSON array; a few wrap the items in an object with a total count.
+pub trait Page<Item> {
+    fn into_items(self) -> Vec<Item>;
+}
+
+impl<Item> Page<Item> for Vec<Item> {
+    fn into_items(self) -> Vec<Item> {
+        self
+    }
+}
+
+impl Page<Repository> for InstallationRepositories {
+    fn into_items(self) -> Vec<Repository> {
+        self.repositories
+    }
+}
+
+impl Page<ActionsSecret> for ActionsSecrets {
+    fn into_items(self) -> Vec<ActionsSecret> {
+        self.secrets
+    }
+}
+
+impl Page<ActionsVariable> for ActionsVariables {
+    fn into_items(self) -> Vec<ActionsVariable> {
+        self.variables
+    }
+}
+
+impl Page<WorkflowRun> for WorkflowRuns {
+    fn into_items(self) -> Vec<WorkflowRun> {
+        self.workflow_runs
+    }
+}
+
+impl Page<WorkflowJob> for WorkflowJobs {
+    fn into_items(self) -> Vec<WorkflowJob> {
+        self.jobs
+    }
+}
+
+impl Page<Artifact> for Artifacts {
+    fn into_items(self) -> Vec<Artifact> {
+        self.artifacts
+    }
+}
+
+/// Iterates over every item of a GitHub list endpoint, following `Link: rel="next"` headers.
+///
+/// Pages are fetched lazily as the iterator is consumed. A failed page is yielded as an error and
+/// ends the iteration.
+pub struct Paginator<'a, T: HttpTransport, P, Item> {
+    github_app: &'a GitHubApp<T>,
+    authentication: Authentication,
+    next_url: Option<String>,
+    max_pages: Option<u32>,
+    pages_fetched: u32,
+    items: VecDeque<Item>,
+    page: PhantomData<P>,
+}
+
+impl<'a, T, P, Item> Paginator<'a, T, P, Item>
+where
+    T: HttpTransport,
+    P: Page<Item> + DeserializeOwned,
+{
+    pub(crate) fn new(
+        github_app: &'a GitHubApp<T>,
+        authentication: Authentication,
+        route: &str,
+        options: PaginationOptions,
+    ) -> Self {
+        let separator: char = match route.contains('?') {
+            true => '&',
+            false => '?',
+        };
+        let first_url: String = format!(
+            "{}{}per_page={}",
+            github_app.api_url_for(route),
+            separator,
+            options.per_page.clamp(1, GITHUB_MAX_PER_PAGE)
+        );
+
+        Paginator {
+            github_app,
+            authentication,
+            next_url: Some(first_url),
+            max_pages: options.max_pages,
+            pages_fetched: 0,
+            items: VecDeque::new(),
+            page: PhantomData,
+        }
+    }
+
+    fn fetch_page(&mut self, url: &str) -> Result<(), GitHubError> {
+        let request: HttpRequest = HttpRequest::new("GET", url);
+        let response: HttpResponse = self
+            .github_app
+            .send_request(self.authentication, &request)?;
+        let page: P = decode(&request, &response)?;
+
+        self.pages_fetched += 1;
+        self.next_url = response.header(HTTP_HEADER_LINK).and_then(parse_next_link);
+        self.items.extend(page.into_items());
+
+        Ok(())
+    }
+}
+
+impl<T, P, Item> Iterator for Paginator<'_, T, P, Item>
+where
+    T: HttpTransport,
+    P: Page<Item> + DeserializeOwned,
+{
+    type Item = Result<Item, GitHubError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.items.pop_front() {
+                return Some(Ok(item));
+            }
+            if self
+                .max_pages
+                .is_some_and(|max_pages| self.pages_fetched >= max_pages)
+            {
+                return None;
+            }
+
+            let url: String = self.next_url.take()?;
+            if let Err(error) = self.fetch_page(&url) {
+                return Some(Err(error));
+            }
+        }
+    }
+}
+
+/// Extracts the `rel="next"` target from a `Link` header such as
+/// `<https://api.github.com/repositories?page=2>; rel="next", <...>; rel="last"`.
+pub fn parse_next_link(link_header: &str) -> Option<String> {
+    link_header.split(',').find_map(|link| {
+        let mut parts = link.split(';');
+        let target: &str = parts.next()?.trim();
+        let is_next: bool = parts.any(|parameter| {
+            let parameter: &str = parameter.trim();
+            parameter == "rel=\"next\"" || parameter == "rel=next"
+        });
+
+        match is_next {
+            true => target
+                .strip_prefix('<')
+                .and_then(|target| target.strip_suffix('>'))
+                .map(String::from),
+            false => None,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        github::test_support::{installation_json, test_github_app, test_url},
+        http::fake::FakeTransport,
+    };
+
+    #[test]
+    fn parse_next_link_picks_next_among_several_rels() {
+        let link_header: &str = "<https://api.github.com/repositories?page=1>; rel=\"prev\", \
+            <https://api.github.com/repositories?page=3>; rel=\"next\", \
+            <https://api.github.com/repositories?page=5>; rel=\"last\", \
+            <https://api.github.com/repositories?page=1>; rel=\"first\"";
+
+        assert_eq!(
+            parse_next_link(link_header).as_deref(),
+            Some("https://api.github.com/repositories?page=3")
+        );
+    }
+
+    #[test]
+    fn parse_next_link_without_next_is_none() {
+        let link_header: &str = "<https://api.github.com/repositories?page=4>; rel=\"prev\", \
+            <https://api.github.com/repositories?page=1>; rel=\"first\"";
+
+        assert_eq!(parse_next_link(link_header), None);
+        assert_eq!(parse_next_link(""), None);
+    }
+
+    #[test]
+    fn parse_next_link_keeps_the_query_string() {
+        let link_header: &str = "<https://github.example.com/api/v3/repos/chronilore/loremaster/actions/runs?status=completed&per_page=100&page=2>; rel=\"next\"";
+
+        assert_eq!(
+            parse_next_link(link_header).as_deref(),
+            Some("https://github.example.com/api/v3/repos/chronilore/loremaster/actions/runs?status=completed&per_page=100&page=2")
+        );
+    }
+
+    #[test]
+    fn max_pages_stops_iteration() {
+        let first_page_url: String = test_url("/app/installations?per_page=1");
+        let second_page_url: String = test_url("/app/installations?per_page=1&page=2");
+        let third_page_url: String = test_url("/app/installations?per_page=1&page=3");
+        let page = |id: u64, next_url: &str| -> HttpResponse {
+            HttpResponse::new(200, &serde_json::json!([installation_json(id)]).to_string())
+                .with_header(HTTP_HEADER_LINK, &format!("<{}>; rel=\"next\"", next_url))
+        };
+        let github_app: GitHubApp<FakeTransport> = test_github_app(
+            FakeTransport::new()
+                .respond_to("GET", &first_page_url, page(1, &second_page_url))
+                .respond_to("GET", &second_page_url, page(2, &third_page_url))
+                .respond_to("GET", &third_page_url, page(3, &third_page_url)),
+        );
+
+        let installation_ids: Vec<u64> = github_app
+            .list_installations(PaginationOptions {
+                per_page: 1,
+                max_pages: Some(2),
+            })
+            .map(|installation| installation.map(|installation| installation.id))
+            .collect::<Result<Vec<u64>, GitHubError>>()
+            .unwrap();
+
+        assert_eq!(installation_ids, vec![1, 2]);
+        assert_eq!(github_app.transport().requests().len(), 2);
+    }
+}
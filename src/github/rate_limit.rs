@@ -0,0 +1,232 @@
+use std::time::Duration;
+
+use time::OffsetDateTime;
+
+use crate::{
+    configuration::constants::{
+        github::{
+            GITHUB_CODE_SEARCH_API_ROUTE, GITHUB_CODE_SEARCH_RATE_LIMIT_RESOURCE,
+            GITHUB_CORE_RATE_LIMIT_RESOURCE, GITHUB_GRAPHQL_API_ROUTE,
+            GITHUB_GRAPHQL_RATE_LIMIT_RESOURCE, GITHUB_SEARCH_API_ROUTE,
+            GITHUB_SEARCH_RATE_LIMIT_RESOURCE, GITHUB_SECONDARY_RATE_LIMIT_MESSAGE,
+            GITHUB_SECONDARY_RATE_LIMIT_WAIT_SECONDS,
+        },
+        http::{
+            github::{
+                HTTP_HEADER_RATE_LIMIT_LIMIT, HTTP_HEADER_RATE_LIMIT_REMAINING,
+                HTTP_HEADER_RATE_LIMIT_RESET, HTTP_HEADER_RATE_LIMIT_RESOURCE,
+                HTTP_HEADER_RATE_LIMIT_USED,
+            },
+            HTTP_HEADER_RETRY_AFTER,
+        },
+    },
+    http::HttpResponse,
+};
+
+use super::models::RateLimit;
+
+/// Reads the `X-RateLimit-*` headers GitHub attaches to every API response.
+pub fn parse_rate_limit_headers(response: &HttpResponse) -> Option<RateLimit> {
+    let number = |name: &str| -> Option<i64> { response.header(name)?.trim().parse().ok() };
+
+    Some(RateLimit {
+        limit: number(HTTP_HEADER_RATE_LIMIT_LIMIT)? as u32,
+        remaining: number(HTTP_HEADER_RATE_LIMIT_REMAINING)? as u32,
+        reset: number(HTTP_HEADER_RATE_LIMIT_RESET)?,
+        used: number(HTTP_HEADER_RATE_LIMIT_USED).unwrap_or_default() as u32,
+        resource: response
+            .header(HTTP_HEADER_RATE_LIMIT_RESOURCE)
+            .map(String::from),
+    })
+}
+
+/// The rate limit bucket a request to `route` draws from, before GitHub confirms it with
+/// `X-RateLimit-Resource`.
+pub fn rate_limit_resource(route: &str) -> &'static str {
+    let is_under = |prefix: &str| -> bool {
+        route
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with(['/', '?']))
+    };
+
+    if is_under(GITHUB_CODE_SEARCH_API_ROUTE) {
+        GITHUB_CODE_SEARCH_RATE_LIMIT_RESOURCE
+    } else if is_under(GITHUB_SEARCH_API_ROUTE) {
+        GITHUB_SEARCH_RATE_LIMIT_RESOURCE
+    } else if is_under(GITHUB_GRAPHQL_API_ROUTE) {
+        GITHUB_GRAPHQL_RATE_LIMIT_RESOURCE
+    } else {
+        GITHUB_CORE_RATE_LIMIT_RESOURCE
+    }
+}
+
+/// How long to wait before retrying a response that GitHub rate limited, or `None` when the
+/// response wasn't rate limited.
+///
+/// A 403 is only retried when the primary budget is spent or its message names a secondary rate
+/// limit. Anything else is a genuine permission failure.
+pub fn retry_delay(response: &HttpResponse) -> Option<Duration> {
+    if response.status != 403 && response.status != 429 {
+        return None;
+    }
+
+    if let Some(retry_after) = response
+        .header(HTTP_HEADER_RETRY_AFTER)
+        .and_then(|value| value.trim().parse::<u64>().ok())
+    {
+        return Some(Duration::from_secs(retry_after));
+    }
+
+    if let Some(rate_limit) = parse_rate_limit_headers(response) {
+        if rate_limit.remaining == 0 {
+            return Some(time_until_reset(&rate_limit));
+        }
+    }
+
+    let is_secondary_rate_limit: bool = response.status == 429
+        || response
+            .body_as_string()
+            .to_lowercase()
+            .contains(GITHUB_SECONDARY_RATE_LIMIT_MESSAGE);
+    match is_secondary_rate_limit {
+        true => Some(Duration::from_secs(
+            GITHUB_SECONDARY_RATE_LIMIT_WAIT_SECONDS,
+        )),
+        false => None,
+    }
+}
+
+/// Time left in the rate limit window, plus a second so the request lands after the reset.
+pub fn time_until_reset(rate_limit: &RateLimit) -> Duration {
+    let seconds: i64 = rate_limit.reset - OffsetDateTime::now_utc().unix_timestamp();
+    Duration::from_secs(seconds.max(0) as u64 + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rate_limited_response(status: u16, remaining: u32, reset: i64) -> HttpResponse {
+        HttpResponse::new(status, r#"{"message":"API rate limit exceeded"}"#)
+            .with_header("x-ratelimit-limit", "5000")
+            .with_header("x-ratelimit-remaining", &remaining.to_string())
+            .with_header("x-ratelimit-reset", &reset.to_string())
+            .with_header("x-ratelimit-used", &(5000 - remaining).to_string())
+            .with_header("x-ratelimit-resource", "core")
+    }
+
+    #[test]
+    fn parse_rate_limit_headers_reads_every_header() {
+        let rate_limit: RateLimit =
+            parse_rate_limit_headers(&rate_limited_response(200, 4990, 1_700_000_000)).unwrap();
+
+        assert_eq!(rate_limit.limit, 5000);
+        assert_eq!(rate_limit.remaining, 4990);
+        assert_eq!(rate_limit.reset, 1_700_000_000);
+        assert_eq!(rate_limit.used, 10);
+        assert_eq!(rate_limit.resource.as_deref(), Some("core"));
+    }
+
+    #[test]
+    fn parse_rate_limit_headers_tolerates_missing_optional_headers() {
+        let response: HttpResponse = HttpResponse::new(200, "{}")
+            .with_header("X-RateLimit-Limit", "60")
+            .with_header("X-RateLimit-Remaining", " 59 ")
+            .with_header("X-RateLimit-Reset", "1700000000");
+
+        let rate_limit: RateLimit = parse_rate_limit_headers(&response).unwrap();
+
+        assert_eq!(rate_limit.remaining, 59);
+        assert_eq!(rate_limit.used, 0);
+        assert_eq!(rate_limit.resource, None);
+    }
+
+    #[test]
+    fn parse_rate_limit_headers_needs_limit_remaining_and_reset() {
+        let without_reset: HttpResponse = HttpResponse::new(200, "{}")
+            .with_header("X-RateLimit-Limit", "60")
+            .with_header("X-RateLimit-Remaining", "59");
+        let malformed: HttpResponse = HttpResponse::new(200, "{}")
+            .with_header("X-RateLimit-Limit", "sixty")
+            .with_header("X-RateLimit-Remaining", "59")
+            .with_header("X-RateLimit-Reset", "1700000000");
+
+        assert!(parse_rate_limit_headers(&HttpResponse::new(200, "{}")).is_none());
+        assert!(parse_rate_limit_headers(&without_reset).is_none());
+        assert!(parse_rate_limit_headers(&malformed).is_none());
+    }
+
+    #[test]
+    fn retry_delay_ignores_responses_that_were_not_rate_limited() {
+        let reset: i64 = OffsetDateTime::now_utc().unix_timestamp() + 600;
+
+        assert_eq!(retry_delay(&rate_limited_response(200, 0, reset)), None);
+        assert_eq!(retry_delay(&rate_limited_response(404, 0, reset)), None);
+        assert_eq!(
+            retry_delay(&HttpResponse::new(
+                403,
+                r#"{"message":"Resource not accessible by integration"}"#
+            )),
+            None
+        );
+        assert_eq!(
+            retry_delay(
+                &HttpResponse::new(
+                    403,
+                    r#"{"message":"Resource not accessible by integration"}"#
+                )
+                .with_header("x-ratelimit-limit", "5000")
+                .with_header("x-ratelimit-remaining", "4999")
+                .with_header("x-ratelimit-reset", &reset.to_string())
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn retry_delay_honours_retry_after() {
+        let reset: i64 = OffsetDateTime::now_utc().unix_timestamp() + 600;
+        let response: HttpResponse =
+            rate_limited_response(403, 0, reset).with_header("Retry-After", "7");
+
+        assert_eq!(retry_delay(&response), Some(Duration::from_secs(7)));
+    }
+
+    #[test]
+    fn retry_delay_waits_for_a_spent_budget_to_reset() {
+        let reset: i64 = OffsetDateTime::now_utc().unix_timestamp() + 600;
+
+        let delay: Duration = retry_delay(&rate_limited_response(403, 0, reset)).unwrap();
+
+        assert!(delay > Duration::from_secs(590));
+        assert!(delay <= Duration::from_secs(601));
+    }
+
+    #[test]
+    fn retry_delay_waits_a_minute_for_secondary_rate_limits() {
+        let reset: i64 = OffsetDateTime::now_utc().unix_timestamp() + 600;
+        let secondary: HttpResponse = HttpResponse::new(
+            403,
+            r#"{"message":"You have exceeded a secondary rate limit. Please wait a few minutes before you try again."}"#,
+        )
+        .with_header("x-ratelimit-limit", "5000")
+        .with_header("x-ratelimit-remaining", "4000")
+        .with_header("x-ratelimit-reset", &reset.to_string());
+        let minute: Option<Duration> = Some(Duration::from_secs(
+            GITHUB_SECONDARY_RATE_LIMIT_WAIT_SECONDS,
+        ));
+
+        assert_eq!(retry_delay(&secondary), minute);
+        assert_eq!(retry_delay(&HttpResponse::new(429, "{}")), minute);
+    }
+
+    #[test]
+    fn rate_limit_resource_follows_the_route() {
+        assert_eq!(rate_limit_resource("/repos/chronilore/loremaster"), "core");
+        assert_eq!(rate_limit_resource(""), "core");
+        assert_eq!(rate_limit_resource("/search/issues?q=lore"), "search");
+        assert_eq!(rate_limit_resource("/search/code?q=lore"), "code_search");
+        assert_eq!(rate_limit_resource("/graphql"), "graphql");
+        assert_eq!(rate_limit_resource("/searches"), "core");
+    }
+}
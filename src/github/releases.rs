@@ -0,0 +1,438 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use log::info;
+use serde::Serialize;
+
+use crate::{
+    configuration::constants::{
+        github::{
+            GITHUB_ASSETS_API_ROUTE, GITHUB_GENERATE_RELEASE_NOTES_API_ROUTE, GITHUB_MAX_PER_PAGE,
+            GITHUB_RELEASES_API_ROUTE, GITHUB_RELEASE_ASSETS_API_ROUTE,
+            GITHUB_RELEASE_TAGS_API_ROUTE,
+        },
+        http::HTTP_HEADER_CONTENT_TYPE,
+    },
+    file_system::{get_content_type, get_file_content_as_bytes},
+    http::{HttpRequest, HttpResponse, HttpTransport},
+};
+
+use super::{
+    error::GitHubError,
+    github_app::{decode, repository_route, Authentication, GitHubApp},
+    models::{GeneratedReleaseNotes, Release, ReleaseAsset},
+    pagination::{PaginationOptions, Paginator},
+};
+
+/// Body of `POST /repos/{owner}/{repo}/releases` and `PATCH /repos/{owner}/{repo}/releases/{id}`.
+#[derive(Debug, Clone, Serialize)]
+pub struct NewRelease {
+    pub tag_name: String,
+    /// Branch or commit the tag is created from when it doesn't exist yet.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_commitish: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    pub draft: bool,
+    pub prerelease: bool,
+    /// Appends GitHub's generated notes to `body`.
+    pub generate_release_notes: bool,
+    /// Tag the generated notes start from. GitHub picks the previous release when omitted.
+    #[serde(skip)]
+    pub previous_tag_name: Option<String>,
+}
+
+impl NewRelease {
+    pub fn new(tag_name: &str) -> Self {
+        NewRelease {
+            tag_name: tag_name.to_string(),
+            target_commitish: None,
+            name: None,
+            body: None,
+            draft: false,
+            prerelease: false,
+            generate_release_notes: false,
+            previous_tag_name: None,
+        }
+    }
+}
+
+/// Body of `POST /repos/{owner}/{repo}/releases/generate-notes`.
+#[derive(Debug, Clone, Serialize)]
+struct ReleaseNotesRequest<'a> {
+    tag_name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target_commitish: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    previous_tag_name: Option<&'a str>,
+}
+
+impl<T: HttpTransport> GitHubApp<T> {
+    /// Lists the releases of `repository`, newest first, including drafts.
+    pub fn list_releases(
+        &self,
+        repository: &str,
+        options: PaginationOptions,
+    ) -> Paginator<'_, T, Vec<Release>, Release> {
+        Paginator::new(
+            self,
+            Authentication::Installation,
+            &format!(
+                "{}{}",
+                repository_route(repository),
+                GITHUB_RELEASES_API_ROUTE
+            ),
+            options,
+        )
+    }
+
+    /// Returns the release for `tag_name`, or `None` when the tag has no release.
+    ///
+    /// `GET /releases/tags/{tag}` only finds published releases, so drafts are looked up by
+    /// listing the repository's releases.
+    pub fn get_release_by_tag(
+        &self,
+        repository: &str,
+        tag_name: &str,
+    ) -> Result<Option<Release>, GitHubError> {
+        let route: String = format!(
+            "{}{}/{}",
+            repository_route(repository),
+            GITHUB_RELEASE_TAGS_API_ROUTE,
+            urlencoding::encode(tag_name)
+        );
+        match self.get(Authentication::Installation, &route) {
+            Ok(release) => return Ok(Some(release)),
+            Err(error) if error.is_not_found() => {}
+            Err(error) => return Err(error),
+        }
+
+        for release in self.list_releases(
+            repository,
+            PaginationOptions {
+                per_page: GITHUB_MAX_PER_PAGE,
+                max_pages: None,
+            },
+        ) {
+            let release: Release = release?;
+            if release.draft && release.tag_name == tag_name {
+                return Ok(Some(release));
+            }
+        }
+
+        Ok(None)
+    }
+
+    pub fn create_release(
+        &self,
+        repository: &str,
+        release: &NewRelease,
+    ) -> Result<Release, GitHubError> {
+        self.send_json(
+            Authentication::Installation,
+            "POST",
+            &format!(
+                "{}{}",
+                repository_route(repository),
+                GITHUB_RELEASES_API_ROUTE
+            ),
+            release,
+        )
+    }
+
+    pub fn update_release(
+        &self,
+        repository: &str,
+        release_id: u64,
+        release: &NewRelease,
+    ) -> Result<Release, GitHubError> {
+        self.send_json(
+            Authentication::Installation,
+            "PATCH",
+            &format!(
+                "{}{}/{}",
+                repository_route(repository),
+                GITHUB_RELEASES_API_ROUTE,
+                release_id
+            ),
+            release,
+        )
+    }
+
+    /// Generates release notes for `tag_name` from the pull requests merged since
+    /// `previous_tag_name`, without creating a release.
+    pub fn generate_release_notes(
+        &self,
+        repository: &str,
+        tag_name: &str,
+        target_commitish: Option<&str>,
+        previous_tag_name: Option<&str>,
+    ) -> Result<GeneratedReleaseNotes, GitHubError> {
+        self.send_json(
+            Authentication::Installation,
+            "POST",
+            &format!(
+                "{}{}",
+                repository_route(repository),
+                GITHUB_GENERATE_RELEASE_NOTES_API_ROUTE
+            ),
+            &ReleaseNotesRequest {
+                tag_name,
+                target_commitish,
+                previous_tag_name,
+            },
+        )
+    }
+
+    /// Creates the release for `release.tag_name`, or updates it when one already exists.
+    ///
+    /// GitHub only generates notes when a release is created, so for an existing release the notes
+    /// are generated separately and appended to `release.body`.
+    pub fn create_or_update_release(
+        &self,
+        repository: &str,
+        release: &NewRelease,
+    ) -> Result<Release, GitHubError> {
+        let Some(existing) = self.get_release_by_tag(repository, &release.tag_name)? else {
+            info!("Creating release {} in {}", release.tag_name, repository);
+            return match release.previous_tag_name {
+                // Creating with `generate_release_notes` can't pick the starting tag.
+                Some(_) if release.generate_release_notes => {
+                    let release: NewRelease = self.with_generated_notes(repository, release)?;
+                    self.create_release(repository, &release)
+                }
+                _ => self.create_release(repository, release),
+            };
+        };
+
+        info!(
+            "Updating release {} ({}) in {}",
+            existing.tag_name, existing.id, repository
+        );
+        let release: NewRelease = match release.generate_release_notes {
+            true => self.with_generated_notes(repository, release)?,
+            false => release.clone(),
+        };
+        self.update_release(repository, existing.id, &release)
+    }
+
+    pub fn delete_release_asset(&self, repository: &str, asset_id: u64) -> Result<(), GitHubError> {
+        self.delete(
+            Authentication::Installation,
+            &format!(
+                "{}{}/{}",
+                repository_route(repository),
+                GITHUB_RELEASE_ASSETS_API_ROUTE,
+                asset_id
+            ),
+        )
+    }
+
+    /// Uploads `content` as an asset of `release_id` through the configured upload URL.
+    pub fn upload_release_asset(
+        &self,
+        repository: &str,
+        release_id: u64,
+        name: &str,
+        content_type: &str,
+        content: Vec<u8>,
+    ) -> Result<ReleaseAsset, GitHubError> {
+        let route: String = format!(
+            "{}{}/{}{}?name={}",
+            repository_route(repository),
+            GITHUB_RELEASES_API_ROUTE,
+            release_id,
+            GITHUB_ASSETS_API_ROUTE,
+            urlencoding::encode(name)
+        );
+        let request: HttpRequest = HttpRequest::new("POST", &self.upload_url_for(&route))
+            .header(HTTP_HEADER_CONTENT_TYPE, content_type)
+            .body(content);
+        let response: HttpResponse = self.send_request(Authentication::Installation, &request)?;
+        decode(&request, &response)
+    }
+
+    /// Uploads the file at `path` to `release`, named after the file and typed by its extension.
+    /// An existing asset with the same name is deleted first, since GitHub rejects duplicates.
+    pub fn replace_release_asset(
+        &self,
+        repository: &str,
+        release: &Release,
+        path: &Path,
+    ) -> Result<ReleaseAsset> {
+        let name: &str = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| anyhow!("Release asset path has no file name: {}", path.display()))?;
+
+        if let Some(existing) = release.assets.iter().find(|asset| asset.name == name) {
+            info!(
+                "Replacing release asset {} ({}) on {}",
+                existing.name, existing.id, release.tag_name
+            );
+            self.delete_release_asset(repository, existing.id)?;
+        }
+
+        let content_type: &str = get_content_type(path);
+        let asset: ReleaseAsset = self.upload_release_asset(
+            repository,
+            release.id,
+            name,
+            content_type,
+            get_file_content_as_bytes(path)?,
+        )?;
+        info!(
+            "Uploaded release asset {} ({}, {} bytes) to {}",
+            asset.name, asset.content_type, asset.size, release.tag_name
+        );
+
+        Ok(asset)
+    }
+
+    fn with_generated_notes(
+        &self,
+        repository: &str,
+        release: &NewRelease,
+    ) -> Result<NewRelease, GitHubError> {
+        let notes: GeneratedReleaseNotes = self.generate_release_notes(
+            repository,
+            &release.tag_name,
+            release.target_commitish.as_deref(),
+            release.previous_tag_name.as_deref(),
+        )?;
+
+        let mut release: NewRelease = release.clone();
+        release.generate_release_notes = false;
+        release.name = release.name.or(Some(notes.name));
+        release.body = Some(match release.body {
+            Some(body) => format!("{}\n\n{}", body, notes.body),
+            None => notes.body,
+        });
+
+        Ok(release)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        github::test_support::{access_token_body, access_token_url, test_github_app, test_url},
+        http::fake::FakeTransport,
+    };
+
+    const REPOSITORY: &str = "chronilore/loremaster";
+
+    fn release_json(id: u64, tag_name: &str, draft: bool) -> serde_json::Value {
+        serde_json::json!({
+            "id": id,
+            "node_id": format!("RE_{}", id),
+            "tag_name": tag_name,
+            "target_commitish": "main",
+            "name": tag_name,
+            "body": null,
+            "draft": draft,
+            "prerelease": false,
+            "html_url": format!("https://github.example.com/{}/releases/{}", REPOSITORY, id),
+            "upload_url": format!("https://uploads.github.example.com/repos/{}/releases/{}/assets{{?name,label}}", REPOSITORY, id),
+            "assets": [],
+            "published_at": null,
+        })
+    }
+
+    fn transport_with_token() -> FakeTransport {
+        FakeTransport::new().respond_to(
+            "POST",
+            &access_token_url(),
+            HttpResponse::new(
+                201,
+                &access_token_body(
+                    "ghs_releases",
+                    time::OffsetDateTime::now_utc() + time::Duration::hours(1),
+                ),
+            ),
+        )
+    }
+
+    #[test]
+    fn rerunning_a_draft_release_updates_the_existing_draft() {
+        let releases_url: String = test_url(&format!("/repos/{}/releases", REPOSITORY));
+        let mut release: NewRelease = NewRelease::new("v1.0.0");
+        release.draft = true;
+        let github_app: GitHubApp<FakeTransport> = test_github_app(
+            transport_with_token()
+                .respond_to(
+                    "GET",
+                    &test_url(&format!("/repos/{}/releases/tags/v1.0.0", REPOSITORY)),
+                    HttpResponse::new(404, r#"{"message":"Not Found"}"#),
+                )
+                .respond_to(
+                    "GET",
+                    &format!("{}?per_page={}", releases_url, GITHUB_MAX_PER_PAGE),
+                    HttpResponse::new(
+                        200,
+                        &serde_json::json!([
+                            release_json(9, "v1.1.0", true),
+                            release_json(8, "v1.0.0", false),
+                            release_json(7, "v1.0.0", true),
+                        ])
+                        .to_string(),
+                    ),
+                )
+                .respond_to(
+                    "PATCH",
+                    &format!("{}/7", releases_url),
+                    HttpResponse::new(200, &release_json(7, "v1.0.0", true).to_string()),
+                ),
+        );
+
+        let updated: Release = github_app
+            .create_or_update_release(REPOSITORY, &release)
+            .unwrap();
+
+        assert_eq!(updated.id, 7);
+        let requests: Vec<HttpRequest> = github_app.transport().requests();
+        assert!(requests
+            .iter()
+            .all(|request| !(request.method == "POST" && request.url == releases_url)));
+        assert_eq!(requests.last().unwrap().method, "PATCH");
+    }
+
+    #[test]
+    fn tag_without_any_release_is_created() {
+        let releases_url: String = test_url(&format!("/repos/{}/releases", REPOSITORY));
+        let github_app: GitHubApp<FakeTransport> = test_github_app(
+            transport_with_token()
+                .respond_to(
+                    "GET",
+                    &test_url(&format!("/repos/{}/releases/tags/v2.0.0", REPOSITORY)),
+                    HttpResponse::new(404, r#"{"message":"Not Found"}"#),
+                )
+                .respond_to(
+                    "GET",
+                    &format!("{}?per_page={}", releases_url, GITHUB_MAX_PER_PAGE),
+                    HttpResponse::new(
+                        200,
+                        &serde_json::json!([release_json(7, "v1.0.0", true)]).to_string(),
+                    ),
+                )
+                .respond_to(
+                    "POST",
+                    &releases_url,
+                    HttpResponse::new(201, &release_json(10, "v2.0.0", false).to_string()),
+                ),
+        );
+
+        let created: Release = github_app
+            .create_or_update_release(REPOSITORY, &NewRelease::new("v2.0.0"))
+            .unwrap();
+
+        assert_eq!(created.id, 10);
+        assert_eq!(
+            github_app.transport().requests().last().unwrap().method,
+            "POST"
+        );
+    }
+}
@@ -0,0 +1,84 @@
+//! Builders shared by the GitHub tests, which drive `GitHubApp` through a `FakeTransport`.
+
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+use crate::{configuration::Configuration, http::fake::FakeTransport};
+
+use super::github_app::GitHubApp;
+
+pub const TEST_API_URL: &str = "https://github.example.com/api/v3";
+pub const TEST_APP_ID: u32 = 1234;
+pub const TEST_INSTALLATION_ID: u32 = 5678;
+/// Throwaway keys generated for the tests, one PKCS#1 and one PKCS#8.
+pub const TEST_PRIVATE_KEY_PATHS: [&str; 2] = [
+    concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/src/github/testdata/github_app_key_1.pem"
+    ),
+    concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/src/github/testdata/github_app_key_2.pem"
+    ),
+];
+
+pub fn test_configuration() -> Configuration {
+    Configuration {
+        github_app_private_key_path: TEST_PRIVATE_KEY_PATHS[0].to_string(),
+        github_app_id: TEST_APP_ID,
+        github_app_installation_id: TEST_INSTALLATION_ID,
+        github_api_url: TEST_API_URL.to_string(),
+        ..Configuration::new()
+    }
+}
+
+pub fn test_github_app(transport: FakeTransport) -> GitHubApp<FakeTransport> {
+    GitHubApp::with_transport(&test_configuration(), transport)
+        .expect("Test GitHub App should load its keys")
+}
+
+pub fn test_url(route: &str) -> String {
+    format!("{}{}", TEST_API_URL, route)
+}
+
+pub fn access_token_url() -> String {
+    test_url(&format!(
+        "/app/installations/{}/access_tokens",
+        TEST_INSTALLATION_ID
+    ))
+}
+
+/// The body GitHub answers an installation token exchange with.
+pub fn access_token_body(token: &str, expires_at: OffsetDateTime) -> String {
+    serde_json::json!({
+        "token": token,
+        "expires_at": expires_at.format(&Rfc3339).expect("Timestamps should format"),
+    })
+    .to_string()
+}
+
+/// The `Authorization` header `request` was sent with.
+pub fn authorization(request: &crate::http::HttpRequest) -> Option<&str> {
+    request
+        .headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("Authorization"))
+        .map(|(_, value)| value.as_str())
+}
+
+/// An installation as GitHub lists it under `/app/installations`.
+pub fn installation_json(id: u64) -> serde_json::Value {
+    serde_json::json!({
+        "id": id,
+        "account": null,
+        "repository_selection": "selected",
+        "access_tokens_url": test_url(&format!("/app/installations/{}/access_tokens", id)),
+        "repositories_url": test_url("/installation/repositories"),
+        "html_url": format!("https://github.example.com/settings/installations/{}", id),
+        "app_id": TEST_APP_ID,
+        "app_slug": "lorerunner",
+        "target_id": id,
+        "target_type": "Organization",
+        "created_at": "2024-01-01T00:00:00Z",
+        "updated_at": "2024-01-01T00:00:00Z",
+    })
+}
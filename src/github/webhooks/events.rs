@@ -0,0 +1,302 @@
+use serde::{Deserialize, Serialize};
+
+use crate::github::models::{Account, Release, Repository, WorkflowRun};
+
+pub const PING_EVENT: &str = "ping";
+pub const PUSH_EVENT: &str = "push";
+pub const RELEASE_EVENT: &str = "release";
+pub const WORKFLOW_RUN_EVENT: &str = "workflow_run";
+pub const INSTALLATION_EVENT: &str = "installation";
+
+/// A webhook delivery, typed by its `X-GitHub-Event` header.
+#[derive(Debug, Clone)]
+pub enum WebhookEvent {
+    Ping(PingEvent),
+    Push(PushEvent),
+    Release(ReleaseEvent),
+    WorkflowRun(WorkflowRunEvent),
+    Installation(InstallationEvent),
+    /// An event lorerunner doesn't act on. The payload is not parsed.
+    Unsupported(String),
+}
+
+impl WebhookEvent {
+    pub fn parse(event_name: &str, payload: &[u8]) -> Result<Self, serde_json::Error> {
+        let event: WebhookEvent = match event_name {
+            PING_EVENT => WebhookEvent::Ping(serde_json::from_slice(payload)?),
+            PUSH_EVENT => WebhookEvent::Push(serde_json::from_slice(payload)?),
+            RELEASE_EVENT => WebhookEvent::Release(serde_json::from_slice(payload)?),
+            WORKFLOW_RUN_EVENT => WebhookEvent::WorkflowRun(serde_json::from_slice(payload)?),
+            INSTALLATION_EVENT => WebhookEvent::Installation(serde_json::from_slice(payload)?),
+            other => WebhookEvent::Unsupported(other.to_string()),
+        };
+
+        Ok(event)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PingEvent {
+    pub zen: String,
+    pub hook_id: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PushEvent {
+    #[serde(rename = "ref")]
+    pub git_ref: String,
+    pub before: String,
+    pub after: String,
+    #[serde(default)]
+    pub created: bool,
+    #[serde(default)]
+    pub deleted: bool,
+    #[serde(default)]
+    pub forced: bool,
+    pub head_commit: Option<PushCommit>,
+    pub repository: Repository,
+    pub sender: Option<Account>,
+    /// The installation the delivery was sent for. Present on every GitHub App delivery.
+    pub installation: Option<InstallationReference>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PushCommit {
+    pub id: String,
+    pub message: String,
+    pub timestamp: String,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReleaseEvent {
+    pub action: String,
+    pub release: Release,
+    pub repository: Repository,
+    pub sender: Option<Account>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowRunEvent {
+    pub action: String,
+    pub workflow_run: WorkflowRun,
+    pub repository: Repository,
+    pub sender: Option<Account>,
+}
+
+/// Webhook payloads describe installations with Unix timestamps, so this is a slimmer shape than
+/// the REST API's `Installation`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstallationEvent {
+    pub action: String,
+    pub installation: InstallationReference,
+    pub repositories: Option<Vec<RepositoryReference>>,
+    pub sender: Option<Account>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstallationReference {
+    pub id: u64,
+    pub account: Option<Account>,
+    pub app_id: Option<u64>,
+    pub target_type: Option<String>,
+    pub repository_selection: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RepositoryReference {
+    pub id: u64,
+    pub name: String,
+    pub full_name: String,
+    pub private: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{configuration::Configuration, github::webhooks::handle_event};
+
+    fn account() -> serde_json::Value {
+        serde_json::json!({
+            "login": "chronilore",
+            "id": 1,
+            "node_id": "O_1",
+            "html_url": "https://github.example.com/chronilore",
+            "type": "Organization",
+        })
+    }
+
+    fn repository() -> serde_json::Value {
+        serde_json::json!({
+            "id": 2,
+            "node_id": "R_2",
+            "name": "loremaster",
+            "full_name": "chronilore/loremaster",
+            "owner": account(),
+            "private": false,
+            "html_url": "https://github.example.com/chronilore/loremaster",
+            "description": null,
+            "url": "https://github.example.com/api/v3/repos/chronilore/loremaster",
+            "default_branch": "main",
+            "visibility": "public",
+        })
+    }
+
+    fn installation() -> serde_json::Value {
+        serde_json::json!({ "id": 5678, "account": account(), "app_id": 1234 })
+    }
+
+    fn parse(event_name: &str, payload: serde_json::Value) -> WebhookEvent {
+        WebhookEvent::parse(event_name, payload.to_string().as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn push_payload_is_a_push_event() {
+        let event: WebhookEvent = parse(
+            PUSH_EVENT,
+            serde_json::json!({
+                "ref": "refs/heads/main",
+                "before": "0000000000000000000000000000000000000000",
+                "after": "0123456789abcdef0123456789abcdef01234567",
+                "created": true,
+                "head_commit": {
+                    "id": "0123456789abcdef0123456789abcdef01234567",
+                    "message": "Add the map of the northern reaches",
+                    "timestamp": "2024-06-01T12:00:00Z",
+                    "url": "https://github.example.com/chronilore/loremaster/commit/0123456",
+                },
+                "repository": repository(),
+                "sender": account(),
+                "installation": installation(),
+            }),
+        );
+
+        let WebhookEvent::Push(push) = event else {
+            panic!("Expected a push event, got {:?}", event);
+        };
+        assert_eq!(push.git_ref, "refs/heads/main");
+        assert!(push.created && !push.deleted && !push.forced);
+        assert_eq!(
+            push.installation.map(|installation| installation.id),
+            Some(5678)
+        );
+    }
+
+    #[test]
+    fn release_payload_is_a_release_event() {
+        let event: WebhookEvent = parse(
+            RELEASE_EVENT,
+            serde_json::json!({
+                "action": "published",
+                "release": {
+                    "id": 9,
+                    "node_id": "RE_9",
+                    "tag_name": "v1.0.0",
+                    "target_commitish": "main",
+                    "name": "v1.0.0",
+                    "body": null,
+                    "draft": false,
+                    "prerelease": false,
+                    "html_url": "https://github.example.com/chronilore/loremaster/releases/9",
+                    "upload_url": "https://uploads.github.example.com/repos/chronilore/loremaster/releases/9/assets{?name,label}",
+                    "published_at": "2024-06-01T12:00:00Z",
+                },
+                "repository": repository(),
+                "sender": account(),
+            }),
+        );
+
+        let WebhookEvent::Release(release) = event else {
+            panic!("Expected a release event, got {:?}", event);
+        };
+        assert_eq!(release.action, "published");
+        assert_eq!(release.release.tag_name, "v1.0.0");
+    }
+
+    #[test]
+    fn workflow_run_payload_is_a_workflow_run_event() {
+        let event: WebhookEvent = parse(
+            WORKFLOW_RUN_EVENT,
+            serde_json::json!({
+                "action": "completed",
+                "workflow_run": {
+                    "id": 8,
+                    "name": "Deploy",
+                    "workflow_id": 42,
+                    "head_branch": "main",
+                    "head_sha": "0123456789abcdef0123456789abcdef01234567",
+                    "run_number": 8,
+                    "run_attempt": 1,
+                    "event": "push",
+                    "status": "completed",
+                    "conclusion": "success",
+                    "html_url": "https://github.example.com/chronilore/loremaster/actions/runs/8",
+                    "created_at": "2024-06-01T12:00:00Z",
+                    "updated_at": "2024-06-01T12:05:00Z",
+                },
+                "repository": repository(),
+                "sender": account(),
+            }),
+        );
+
+        let WebhookEvent::WorkflowRun(workflow_run) = event else {
+            panic!("Expected a workflow_run event, got {:?}", event);
+        };
+        assert_eq!(workflow_run.workflow_run.id, 8);
+        assert_eq!(
+            workflow_run.workflow_run.conclusion,
+            Some(crate::github::models::WorkflowRunConclusion::Success)
+        );
+    }
+
+    #[test]
+    fn installation_payload_is_an_installation_event() {
+        let event: WebhookEvent = parse(
+            INSTALLATION_EVENT,
+            serde_json::json!({
+                "action": "created",
+                "installation": installation(),
+                "repositories": [
+                    { "id": 2, "name": "loremaster", "full_name": "chronilore/loremaster", "private": false },
+                ],
+                "sender": account(),
+            }),
+        );
+
+        let WebhookEvent::Installation(installation) = event else {
+            panic!("Expected an installation event, got {:?}", event);
+        };
+        assert_eq!(installation.action, "created");
+        assert_eq!(installation.installation.id, 5678);
+        assert_eq!(
+            installation
+                .repositories
+                .map(|repositories| repositories.len()),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn ping_payload_is_a_ping_event() {
+        let event: WebhookEvent = parse(
+            PING_EVENT,
+            serde_json::json!({ "zen": "Design for failure.", "hook_id": 3 }),
+        );
+
+        assert!(matches!(event, WebhookEvent::Ping(ping) if ping.zen == "Design for failure."));
+    }
+
+    #[test]
+    fn unknown_event_is_accepted_without_parsing_its_payload() {
+        let event: WebhookEvent = WebhookEvent::parse("check_suite", b"not even json").unwrap();
+
+        assert!(matches!(&event, WebhookEvent::Unsupported(name) if name == "check_suite"));
+        assert!(handle_event(&event, &Configuration::new()).is_ok());
+    }
+
+    #[test]
+    fn malformed_payload_of_a_known_event_is_an_error() {
+        assert!(WebhookEvent::parse(PUSH_EVENT, b"{}").is_err());
+        assert!(WebhookEvent::parse(RELEASE_EVENT, b"not json").is_err());
+    }
+}
@@ -0,0 +1,148 @@
+use anyhow::Result;
+use log::{debug, info};
+
+use crate::{
+    configuration::{constants::github::GITHUB_CHECK_RUN_MAX_TEXT_CHARACTERS, Configuration},
+    github::{
+        check_runs::{
+            parse_build_diagnostics, track_check_run, CheckRunAnnotation, CheckRunOutput,
+            CheckRunTarget,
+        },
+        deployments::{track_deployment, DeploymentTarget},
+        github_app::GitHubApp,
+        models::{AnnotationLevel, CheckRunConclusion},
+    },
+    workshop::{deploy_site, SiteExportError},
+};
+
+use self::events::{PushEvent, WebhookEvent};
+
+pub mod events;
+pub mod queue;
+pub mod server;
+pub mod signature;
+
+pub fn handle_event(event: &WebhookEvent, configuration: &Configuration) -> Result<()> {
+    match event {
+        WebhookEvent::Ping(ping) => info!("GitHub says: {}", ping.zen),
+        WebhookEvent::Push(push) => handle_push(push, configuration)?,
+        WebhookEvent::Release(release) => info!(
+            "Release {} was {} in {}",
+            release.release.tag_name, release.action, release.repository.full_name
+        ),
+        WebhookEvent::WorkflowRun(workflow_run_event) => {
+            let workflow_run = &workflow_run_event.workflow_run;
+            info!(
+                "Workflow run {} ({}) was {} in {} - status: {:?}, conclusion: {:?}",
+                workflow_run.name.as_deref().unwrap_or_default(),
+                workflow_run.id,
+                workflow_run_event.action,
+                workflow_run_event.repository.full_name,
+                workflow_run.status,
+                workflow_run.conclusion
+            );
+        }
+        WebhookEvent::Installation(installation) => info!(
+            "Installation {} was {}",
+            installation.installation.id, installation.action
+        ),
+        WebhookEvent::Unsupported(event_name) => debug!("Ignoring {} event", event_name),
+    }
+
+    Ok(())
+}
+
+/// Deploys the loremaster static site when the configured site branch receives new commits,
+/// reporting it to GitHub as a deployment of the pushed commit with a site build check run.
+fn handle_push(push: &PushEvent, configuration: &Configuration) -> Result<()> {
+    let site_ref: String = format!("refs/heads/{}", configuration.site_branch);
+    if push.repository.full_name != configuration.site_repository
+        || push.git_ref != site_ref
+        || push.deleted
+    {
+        debug!(
+            "Ignoring push to {} {}",
+            push.repository.full_name, push.git_ref
+        );
+        return Ok(());
+    }
+
+    info!(
+        "Push to {} {} ({}), deploying loremaster static site",
+        push.repository.full_name, push.git_ref, push.after
+    );
+    let configured_app: GitHubApp = GitHubApp::new(configuration)?;
+    let github_app: GitHubApp = match &push.installation {
+        Some(installation) => configured_app.with_installation(installation.id),
+        None => configured_app,
+    };
+    let target: DeploymentTarget = DeploymentTarget::for_site(configuration, &push.after)?;
+    let check_target: CheckRunTarget = CheckRunTarget::for_site(configuration, &push.after)?;
+    track_deployment(&github_app, &target, || {
+        // The check run reports the build either way; the deployment fails with the build.
+        track_check_run(&github_app, &check_target, || {
+            let (outcome, build_output): (Result<()>, String) =
+                match deploy_site(&github_app, configuration, Some(&push.after)) {
+                    Ok(build_output) => (Ok(()), build_output),
+                    Err(error) => {
+                        let build_output: String = error
+                            .downcast_ref::<SiteExportError>()
+                            .map(|export_error| export_error.output.clone())
+                            .unwrap_or_default();
+                        (Err(error), build_output)
+                    }
+                };
+            let (conclusion, output): (CheckRunConclusion, CheckRunOutput) =
+                site_build_check_run_output(&outcome, &build_output, &push.after, configuration);
+            Ok((outcome, conclusion, output))
+        })?
+    })
+}
+
+/// The check run conclusion and output for a site deploy of `head_sha`, with the compiler's
+/// diagnostics as annotations.
+fn site_build_check_run_output(
+    outcome: &Result<()>,
+    build_output: &str,
+    head_sha: &str,
+    configuration: &Configuration,
+) -> (CheckRunConclusion, CheckRunOutput) {
+    let annotations: Vec<CheckRunAnnotation> =
+        parse_build_diagnostics(build_output, &configuration.site_source_directory);
+    let warning_count: usize = annotations
+        .iter()
+        .filter(|annotation| annotation.annotation_level == AnnotationLevel::Warning)
+        .count();
+
+    let (conclusion, mut output): (CheckRunConclusion, CheckRunOutput) = match outcome {
+        Ok(()) => (
+            CheckRunConclusion::Success,
+            CheckRunOutput::new(
+                "Site built",
+                &format!(
+                    "The loremaster static site was exported at `{}` and published to GitHub Pages with {} warning(s).",
+                    head_sha, warning_count
+                ),
+            ),
+        ),
+        Err(error) => (
+            CheckRunConclusion::Failure,
+            CheckRunOutput::new(
+                "Site build failed",
+                &format!("```\n{:#}\n```", error),
+            ),
+        ),
+    };
+    if !build_output.is_empty() {
+        // The end of the build output is where the failure is, so that is what is kept.
+        let character_count: usize = build_output.chars().count();
+        let build_output_tail: String = build_output
+            .chars()
+            .skip(character_count.saturating_sub(GITHUB_CHECK_RUN_MAX_TEXT_CHARACTERS - 8))
+            .collect();
+        output.text = Some(format!("```\n{}\n```", build_output_tail));
+    }
+    output.annotations = annotations;
+
+    (conclusion, output)
+}
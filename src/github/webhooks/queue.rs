@@ -0,0 +1,449 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{anyhow, Result};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::{
+    configuration::{
+        constants::github::{
+            WEBHOOK_QUEUE_POLL_INTERVAL_SECONDS, WEBHOOK_RETRY_BACKOFF_BASE_SECONDS,
+            WEBHOOK_RETRY_BACKOFF_MAX_SECONDS,
+        },
+        Configuration,
+    },
+    file_system::get_file_content_as_string,
+};
+
+use super::{events::WebhookEvent, handle_event};
+
+const DELIVERY_FILE_EXTENSION: &str = "ron";
+
+/// A webhook delivery as persisted on disk, keyed by its `X-GitHub-Delivery` ID.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedDelivery {
+    pub delivery_id: String,
+    pub event_name: String,
+    pub payload: String,
+    pub attempts: u32,
+    #[serde(with = "time::serde::rfc3339")]
+    pub received_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub next_attempt_at: OffsetDateTime,
+    pub last_error: Option<String>,
+}
+
+impl QueuedDelivery {
+    pub fn new(delivery_id: &str, event_name: &str, payload: String) -> Self {
+        let now: OffsetDateTime = OffsetDateTime::now_utc();
+        QueuedDelivery {
+            delivery_id: delivery_id.to_string(),
+            event_name: event_name.to_string(),
+            payload,
+            attempts: 0,
+            received_at: now,
+            next_attempt_at: now,
+            last_error: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeliveryState {
+    Pending,
+    Processed,
+    DeadLetter,
+}
+
+impl DeliveryState {
+    fn folder_name(&self) -> &'static str {
+        match self {
+            DeliveryState::Pending => "pending",
+            DeliveryState::Processed => "processed",
+            DeliveryState::DeadLetter => "dead_letter",
+        }
+    }
+}
+
+pub enum EnqueueResult {
+    Enqueued,
+    Duplicate,
+}
+
+/// On-disk queue that processes each webhook delivery once, retrying failed handlers with
+/// exponential backoff before moving them to a dead-letter area.
+///
+/// A delivery lives in exactly one of the `pending`, `processed` or `dead_letter` folders, so a
+/// redelivery of an ID already on disk is recognised no matter how far it got.
+pub struct DeliveryQueue {
+    root_path: PathBuf,
+    max_attempts: u32,
+    lock: Mutex<()>,
+}
+
+impl DeliveryQueue {
+    pub fn open(configuration: &Configuration) -> Result<Self> {
+        let root_path: PathBuf = PathBuf::from(&configuration.webhook_queue_path);
+        for state in [
+            DeliveryState::Pending,
+            DeliveryState::Processed,
+            DeliveryState::DeadLetter,
+        ] {
+            std::fs::create_dir_all(root_path.join(state.folder_name()))?;
+        }
+
+        Ok(DeliveryQueue {
+            root_path,
+            max_attempts: configuration.webhook_max_attempts.max(1),
+            lock: Mutex::new(()),
+        })
+    }
+
+    pub fn enqueue(&self, delivery: &QueuedDelivery) -> Result<EnqueueResult> {
+        validate_delivery_id(&delivery.delivery_id)?;
+        let _guard = self.lock()?;
+
+        let already_known: bool = [
+            DeliveryState::Pending,
+            DeliveryState::Processed,
+            DeliveryState::DeadLetter,
+        ]
+        .iter()
+        .any(|state| self.delivery_path(*state, &delivery.delivery_id).exists());
+        if already_known {
+            return Ok(EnqueueResult::Duplicate);
+        }
+
+        self.write(DeliveryState::Pending, delivery)?;
+        Ok(EnqueueResult::Enqueued)
+    }
+
+    pub fn list(&self, state: DeliveryState) -> Result<Vec<QueuedDelivery>> {
+        let _guard = self.lock()?;
+        let mut deliveries: Vec<QueuedDelivery> = vec![];
+
+        for entry in std::fs::read_dir(self.root_path.join(state.folder_name()))? {
+            let path: PathBuf = entry?.path();
+            if path.extension().and_then(|extension| extension.to_str())
+                != Some(DELIVERY_FILE_EXTENSION)
+            {
+                continue;
+            }
+            deliveries.push(read_delivery(&path)?);
+        }
+        deliveries.sort_by_key(|delivery| delivery.received_at);
+
+        Ok(deliveries)
+    }
+
+    /// Pending deliveries whose backoff has elapsed, oldest first.
+    pub fn due_deliveries(&self) -> Result<Vec<QueuedDelivery>> {
+        let now: OffsetDateTime = OffsetDateTime::now_utc();
+        Ok(self
+            .list(DeliveryState::Pending)?
+            .into_iter()
+            .filter(|delivery| delivery.next_attempt_at <= now)
+            .collect())
+    }
+
+    pub fn complete(&self, delivery: &QueuedDelivery, from: DeliveryState) -> Result<()> {
+        let _guard = self.lock()?;
+        let mut delivery: QueuedDelivery = delivery.clone();
+        delivery.attempts += 1;
+        delivery.last_error = None;
+        self.write(DeliveryState::Processed, &delivery)?;
+        std::fs::remove_file(self.delivery_path(from, &delivery.delivery_id))?;
+
+        Ok(())
+    }
+
+    /// Records a failed attempt and either schedules a retry or dead-letters the delivery.
+    pub fn fail(
+        &self,
+        delivery: &QueuedDelivery,
+        from: DeliveryState,
+        failure: &anyhow::Error,
+    ) -> Result<DeliveryState> {
+        let _guard = self.lock()?;
+        let mut delivery: QueuedDelivery = delivery.clone();
+        delivery.attempts += 1;
+        delivery.last_error = Some(format!("{:#}", failure));
+
+        let next_state: DeliveryState = match delivery.attempts >= self.max_attempts {
+            true => DeliveryState::DeadLetter,
+            false => {
+                delivery.next_attempt_at =
+                    OffsetDateTime::now_utc() + retry_backoff(delivery.attempts);
+                DeliveryState::Pending
+            }
+        };
+
+        self.write(next_state, &delivery)?;
+        if next_state != from {
+            std::fs::remove_file(self.delivery_path(from, &delivery.delivery_id))?;
+        }
+
+        Ok(next_state)
+    }
+
+    /// Runs a dead-lettered delivery's handler again right away.
+    pub fn replay(&self, delivery_id: &str, configuration: &Configuration) -> Result<()> {
+        validate_delivery_id(delivery_id)?;
+        let delivery_path: PathBuf = self.delivery_path(DeliveryState::DeadLetter, delivery_id);
+        if !delivery_path.exists() {
+            return Err(anyhow!(
+                "No dead-lettered webhook delivery with ID {}",
+                delivery_id
+            ));
+        }
+        let delivery: QueuedDelivery = read_delivery(&delivery_path)?;
+
+        info!(
+            "[{}] Replaying {} delivery",
+            delivery.delivery_id, delivery.event_name
+        );
+        match process_delivery(&delivery, configuration) {
+            Ok(()) => self.complete(&delivery, DeliveryState::DeadLetter),
+            Err(failure) => {
+                self.fail(&delivery, DeliveryState::DeadLetter, &failure)?;
+                Err(failure)
+            }
+        }
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, ()>> {
+        self.lock
+            .lock()
+            .map_err(|_| anyhow!("Webhook delivery queue lock was poisoned."))
+    }
+
+    fn delivery_path(&self, state: DeliveryState, delivery_id: &str) -> PathBuf {
+        self.root_path
+            .join(state.folder_name())
+            .join(format!("{}.{}", delivery_id, DELIVERY_FILE_EXTENSION))
+    }
+
+    /// Writes through a temporary file so a crash never leaves a half-written delivery behind.
+    fn write(&self, state: DeliveryState, delivery: &QueuedDelivery) -> Result<()> {
+        let path: PathBuf = self.delivery_path(state, &delivery.delivery_id);
+        let temporary_path: PathBuf = path.with_extension("tmp");
+        std::fs::write(
+            &temporary_path,
+            ron::ser::to_string_pretty(delivery, ron::ser::PrettyConfig::default())?,
+        )?;
+        std::fs::rename(temporary_path, path)?;
+
+        Ok(())
+    }
+}
+
+/// Processes due deliveries one at a time until the process is stopped.
+pub async fn run_worker(queue: Arc<DeliveryQueue>, configuration: Arc<Configuration>) {
+    loop {
+        match queue.due_deliveries() {
+            Ok(deliveries) => {
+                for delivery in deliveries {
+                    process_pending_delivery(queue.clone(), configuration.clone(), delivery).await;
+                }
+            }
+            Err(failure) => error!("Failed to read webhook delivery queue: {:?}", failure),
+        }
+
+        tokio::time::sleep(std::time::Duration::from_secs(
+            WEBHOOK_QUEUE_POLL_INTERVAL_SECONDS,
+        ))
+        .await;
+    }
+}
+
+async fn process_pending_delivery(
+    queue: Arc<DeliveryQueue>,
+    configuration: Arc<Configuration>,
+    delivery: QueuedDelivery,
+) {
+    let handler_delivery: QueuedDelivery = delivery.clone();
+    // Handlers block (the site deploy shells out), and a panicking handler must not kill the worker.
+    let result: Result<()> =
+        tokio::task::spawn_blocking(move || process_delivery(&handler_delivery, &configuration))
+            .await
+            .unwrap_or_else(|join_error| Err(anyhow!("Webhook handler panicked: {}", join_error)));
+
+    let outcome: Result<()> = match result {
+        Ok(()) => {
+            info!(
+                "[{}] Processed {} delivery",
+                delivery.delivery_id, delivery.event_name
+            );
+            queue.complete(&delivery, DeliveryState::Pending)
+        }
+        Err(failure) => {
+            warn!(
+                "[{}] Attempt {} of {} delivery failed: {:?}",
+                delivery.delivery_id,
+                delivery.attempts + 1,
+                delivery.event_name,
+                failure
+            );
+            queue
+                .fail(&delivery, DeliveryState::Pending, &failure)
+                .map(|state| {
+                    if state == DeliveryState::DeadLetter {
+                        error!(
+                            "[{}] Moved {} delivery to the dead-letter area",
+                            delivery.delivery_id, delivery.event_name
+                        );
+                    }
+                })
+        }
+    };
+
+    if let Err(failure) = outcome {
+        error!(
+            "[{}] Failed to update webhook delivery queue: {:?}",
+            delivery.delivery_id, failure
+        );
+    }
+}
+
+fn process_delivery(delivery: &QueuedDelivery, configuration: &Configuration) -> Result<()> {
+    let event: WebhookEvent =
+        WebhookEvent::parse(&delivery.event_name, delivery.payload.as_bytes())?;
+    handle_event(&event, configuration)
+}
+
+fn read_delivery(path: &Path) -> Result<QueuedDelivery> {
+    let content: String = get_file_content_as_string(path.to_string_lossy().to_string())?;
+    Ok(ron::from_str(&content)?)
+}
+
+fn retry_backoff(attempts: u32) -> time::Duration {
+    let multiplier: i64 = 1_i64 << attempts.saturating_sub(1).min(16);
+    time::Duration::seconds(
+        WEBHOOK_RETRY_BACKOFF_BASE_SECONDS
+            .saturating_mul(multiplier)
+            .min(WEBHOOK_RETRY_BACKOFF_MAX_SECONDS),
+    )
+}
+
+/// Delivery IDs become file names, so only GUID-like IDs are accepted.
+fn validate_delivery_id(delivery_id: &str) -> Result<()> {
+    match !delivery_id.is_empty()
+        && delivery_id
+            .chars()
+            .all(|character| character.is_ascii_alphanumeric() || character == '-')
+    {
+        true => Ok(()),
+        false => Err(anyhow!("Invalid webhook delivery ID: {:?}", delivery_id)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DELIVERY_ID: &str = "72d3162e-cc78-11e3-81ab-4c9367dc0958";
+
+    /// A queue in a fresh temporary directory, removed by the caller.
+    fn open_queue(name: &str, max_attempts: u32) -> (DeliveryQueue, Configuration) {
+        let queue_path: PathBuf = std::env::temp_dir().join(format!(
+            "lorerunner-webhook-queue-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = std::fs::remove_dir_all(&queue_path);
+        let configuration: Configuration = Configuration {
+            webhook_queue_path: queue_path.to_string_lossy().to_string(),
+            webhook_max_attempts: max_attempts,
+            ..Configuration::new()
+        };
+
+        (DeliveryQueue::open(&configuration).unwrap(), configuration)
+    }
+
+    #[test]
+    fn redelivered_id_is_a_duplicate() {
+        let (queue, configuration) = open_queue("duplicate", 3);
+        let delivery: QueuedDelivery = QueuedDelivery::new(DELIVERY_ID, "ping", String::new());
+
+        let first: EnqueueResult = queue.enqueue(&delivery).unwrap();
+        let second: EnqueueResult = queue.enqueue(&delivery).unwrap();
+        queue.complete(&delivery, DeliveryState::Pending).unwrap();
+        let after_processing: EnqueueResult = queue.enqueue(&delivery).unwrap();
+        std::fs::remove_dir_all(&configuration.webhook_queue_path).unwrap();
+
+        assert!(matches!(first, EnqueueResult::Enqueued));
+        assert!(matches!(second, EnqueueResult::Duplicate));
+        assert!(matches!(after_processing, EnqueueResult::Duplicate));
+    }
+
+    #[test]
+    fn invalid_delivery_ids_are_refused() {
+        let (queue, configuration) = open_queue("invalid-id", 3);
+
+        let empty = queue.enqueue(&QueuedDelivery::new("", "ping", String::new()));
+        let traversal = queue.enqueue(&QueuedDelivery::new("../escape", "ping", String::new()));
+        std::fs::remove_dir_all(&configuration.webhook_queue_path).unwrap();
+
+        assert!(empty.is_err());
+        assert!(traversal.is_err());
+    }
+
+    #[tokio::test]
+    async fn failing_handler_is_dead_lettered_after_max_attempts() {
+        let (queue, configuration) = open_queue("dead-letter", 2);
+        let queue: Arc<DeliveryQueue> = Arc::new(queue);
+        let configuration: Arc<Configuration> = Arc::new(configuration);
+        queue
+            .enqueue(&QueuedDelivery::new(
+                DELIVERY_ID,
+                "push",
+                String::from("not a push payload"),
+            ))
+            .unwrap();
+
+        let first_attempt: QueuedDelivery = queue.list(DeliveryState::Pending).unwrap().remove(0);
+        process_pending_delivery(queue.clone(), configuration.clone(), first_attempt).await;
+        let retried: Vec<QueuedDelivery> = queue.list(DeliveryState::Pending).unwrap();
+        let due_after_first: usize = queue.due_deliveries().unwrap().len();
+
+        process_pending_delivery(queue.clone(), configuration.clone(), retried[0].clone()).await;
+        let pending: Vec<QueuedDelivery> = queue.list(DeliveryState::Pending).unwrap();
+        let dead_letter: Vec<QueuedDelivery> = queue.list(DeliveryState::DeadLetter).unwrap();
+        std::fs::remove_dir_all(&configuration.webhook_queue_path).unwrap();
+
+        assert_eq!(retried.len(), 1);
+        assert_eq!(retried[0].attempts, 1);
+        assert!(retried[0].last_error.is_some());
+        assert_eq!(due_after_first, 0);
+        assert!(pending.is_empty());
+        assert_eq!(dead_letter.len(), 1);
+        assert_eq!(dead_letter[0].delivery_id, DELIVERY_ID);
+        assert_eq!(dead_letter[0].attempts, 2);
+    }
+
+    #[test]
+    fn retry_backoff_doubles_up_to_the_cap() {
+        assert_eq!(
+            retry_backoff(1),
+            time::Duration::seconds(WEBHOOK_RETRY_BACKOFF_BASE_SECONDS)
+        );
+        assert_eq!(
+            retry_backoff(2),
+            time::Duration::seconds(WEBHOOK_RETRY_BACKOFF_BASE_SECONDS * 2)
+        );
+        assert_eq!(
+            retry_backoff(3),
+            time::Duration::seconds(WEBHOOK_RETRY_BACKOFF_BASE_SECONDS * 4)
+        );
+        for attempts in [8, 17, 64, u32::MAX] {
+            assert_eq!(
+                retry_backoff(attempts),
+                time::Duration::seconds(WEBHOOK_RETRY_BACKOFF_MAX_SECONDS)
+            );
+        }
+    }
+}
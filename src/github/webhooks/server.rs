@@ -0,0 +1,133 @@
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    routing::post,
+    Router,
+};
+use log::{error, info, warn};
+
+use crate::configuration::{
+    constants::{
+        github::GITHUB_WEBHOOK_ROUTE,
+        http::github::{
+            HTTP_HEADER_GITHUB_DELIVERY, HTTP_HEADER_GITHUB_EVENT, HTTP_HEADER_HUB_SIGNATURE_256,
+        },
+    },
+    Configuration,
+};
+
+use super::{
+    events::WebhookEvent,
+    queue::{run_worker, DeliveryQueue, EnqueueResult, QueuedDelivery},
+    signature::verify_signature,
+};
+
+struct WebhookState {
+    configuration: Arc<Configuration>,
+    queue: Arc<DeliveryQueue>,
+}
+
+/// Listens for GitHub webhook deliveries until the process is stopped.
+pub async fn serve(configuration: Configuration, listen_address: &str) -> Result<()> {
+    if configuration.github_webhook_secret.is_empty() {
+        return Err(anyhow!(
+            "Refusing to accept webhooks without a configured github_webhook_secret."
+        ));
+    }
+
+    let configuration: Arc<Configuration> = Arc::new(configuration);
+    let queue: Arc<DeliveryQueue> = Arc::new(DeliveryQueue::open(&configuration)?);
+    // Deliveries left pending by a previous run are picked up by the worker on its first pass.
+    tokio::spawn(run_worker(queue.clone(), configuration.clone()));
+
+    let router: Router = Router::new()
+        .route(GITHUB_WEBHOOK_ROUTE, post(receive_webhook))
+        .with_state(Arc::new(WebhookState {
+            configuration,
+            queue,
+        }));
+    let listener: tokio::net::TcpListener = tokio::net::TcpListener::bind(listen_address).await?;
+    info!(
+        "Listening for GitHub webhook deliveries on {}{}",
+        listen_address, GITHUB_WEBHOOK_ROUTE
+    );
+    axum::serve(listener, router).await?;
+
+    Ok(())
+}
+
+async fn receive_webhook(
+    State(state): State<Arc<WebhookState>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> StatusCode {
+    let header = |name: &str| -> String {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_string()
+    };
+    let delivery_id: String = header(HTTP_HEADER_GITHUB_DELIVERY);
+    let event_name: String = header(HTTP_HEADER_GITHUB_EVENT);
+
+    if !verify_signature(
+        &state.configuration.github_webhook_secret,
+        &body,
+        &header(HTTP_HEADER_HUB_SIGNATURE_256),
+    ) {
+        warn!(
+            "[{}] Rejected delivery with an invalid signature",
+            delivery_id
+        );
+        return StatusCode::UNAUTHORIZED;
+    }
+
+    // The delivery ID names the queued file and is what duplicates are detected by.
+    if delivery_id.is_empty() {
+        warn!(
+            "Rejected {} delivery without an {} header",
+            event_name, HTTP_HEADER_GITHUB_DELIVERY
+        );
+        return StatusCode::BAD_REQUEST;
+    }
+
+    // Parse up front so malformed payloads are rejected instead of being retried later.
+    if let Err(error) = WebhookEvent::parse(&event_name, &body) {
+        warn!(
+            "[{}] Rejected malformed {} payload: {}",
+            delivery_id, event_name, error
+        );
+        return StatusCode::BAD_REQUEST;
+    }
+
+    let delivery: QueuedDelivery = QueuedDelivery::new(
+        &delivery_id,
+        &event_name,
+        String::from_utf8_lossy(&body).to_string(),
+    );
+    match state.queue.enqueue(&delivery) {
+        Ok(EnqueueResult::Enqueued) => {
+            info!("[{}] Queued {} event", delivery_id, event_name);
+            StatusCode::ACCEPTED
+        }
+        Ok(EnqueueResult::Duplicate) => {
+            info!(
+                "[{}] Ignoring duplicate {} delivery",
+                delivery_id, event_name
+            );
+            StatusCode::OK
+        }
+        Err(error) => {
+            error!(
+                "[{}] Failed to queue {} event: {:?}",
+                delivery_id, event_name, error
+            );
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
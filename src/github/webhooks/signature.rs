@@ -0,0 +1,83 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::configuration::constants::github::GITHUB_WEBHOOK_SIGNATURE_PREFIX;
+
+/// Checks an `X-Hub-Signature-256` header value against the HMAC-SHA256 of `payload`.
+///
+/// The comparison is constant-time so response timing doesn't leak how much of a forged signature
+/// was correct. An empty secret never verifies, since anyone can sign with it.
+pub fn verify_signature(secret: &str, payload: &[u8], signature_header: &str) -> bool {
+    if secret.is_empty() {
+        return false;
+    }
+    let Some(hex_signature) = signature_header.strip_prefix(GITHUB_WEBHOOK_SIGNATURE_PREFIX) else {
+        return false;
+    };
+    let Ok(signature) = hex::decode(hex_signature) else {
+        return false;
+    };
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret.as_bytes()) else {
+        return false;
+    };
+
+    mac.update(payload);
+    mac.verify_slice(&signature).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "It's a Secret to Everybody";
+    const PAYLOAD: &[u8] = b"Hello, World!";
+    /// The example from GitHub's "Validating webhook deliveries" documentation.
+    const SIGNATURE: &str =
+        "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17";
+
+    #[test]
+    fn valid_signature_verifies() {
+        assert!(verify_signature(SECRET, PAYLOAD, SIGNATURE));
+    }
+
+    #[test]
+    fn wrong_digest_is_rejected() {
+        let wrong_signature: String = format!("{}0", &SIGNATURE[..SIGNATURE.len() - 1]);
+
+        assert!(!verify_signature(SECRET, PAYLOAD, &wrong_signature));
+        assert!(!verify_signature(SECRET, b"Hello, World?", SIGNATURE));
+        assert!(!verify_signature("another secret", PAYLOAD, SIGNATURE));
+    }
+
+    #[test]
+    fn signature_without_prefix_is_rejected() {
+        let hex_signature: &str = SIGNATURE.strip_prefix("sha256=").unwrap();
+
+        assert!(!verify_signature(SECRET, PAYLOAD, hex_signature));
+        assert!(!verify_signature(
+            SECRET,
+            PAYLOAD,
+            &format!("sha1={}", hex_signature)
+        ));
+    }
+
+    #[test]
+    fn non_hex_signature_is_rejected() {
+        assert!(!verify_signature(SECRET, PAYLOAD, "sha256=not-a-digest"));
+        assert!(!verify_signature(SECRET, PAYLOAD, "sha256="));
+        assert!(!verify_signature(SECRET, PAYLOAD, ""));
+    }
+
+    #[test]
+    fn empty_secret_never_verifies() {
+        let mut mac: Hmac<Sha256> = Hmac::new_from_slice(b"").unwrap();
+        mac.update(PAYLOAD);
+        let signature: String = format!(
+            "{}{}",
+            GITHUB_WEBHOOK_SIGNATURE_PREFIX,
+            hex::encode(mac.finalize().into_bytes())
+        );
+
+        assert!(!verify_signature("", PAYLOAD, &signature));
+    }
+}
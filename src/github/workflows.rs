@@ -0,0 +1,614 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+use log::{debug, info};
+use serde::Serialize;
+use time::OffsetDateTime;
+
+use crate::{
+    configuration::constants::github::{
+        GITHUB_ARTIFACTS_API_ROUTE, GITHUB_DEFAULT_PER_PAGE, GITHUB_JOBS_API_ROUTE,
+        GITHUB_LOGS_API_ROUTE, GITHUB_MAX_PER_PAGE, GITHUB_WORKFLOWS_API_ROUTE,
+        GITHUB_WORKFLOW_ARTIFACTS_API_ROUTE, GITHUB_WORKFLOW_DISPATCHES_API_ROUTE,
+        GITHUB_WORKFLOW_DISPATCH_DISCOVERY_TIMEOUT_SECONDS, GITHUB_WORKFLOW_DISPATCH_EVENT,
+        GITHUB_WORKFLOW_RUNS_API_ROUTE, GITHUB_WORKFLOW_RUN_POLL_INTERVAL_SECONDS,
+        GITHUB_ZIP_API_ROUTE,
+    },
+    file_system::extract_zip_archive,
+    http::HttpTransport,
+};
+
+use super::{
+    error::GitHubError,
+    github_app::{repository_route, Authentication, GitHubApp},
+    models::{
+        Artifact, Artifacts, WorkflowJob, WorkflowJobs, WorkflowRun, WorkflowRunConclusion,
+        WorkflowRunStatus, WorkflowRuns,
+    },
+    pagination::{PaginationOptions, Paginator},
+};
+
+/// Body of `POST /repos/{owner}/{repo}/actions/workflows/{workflow_id}/dispatches`.
+#[derive(Debug, Clone, Serialize)]
+struct WorkflowDispatch<'a> {
+    #[serde(rename = "ref")]
+    git_ref: &'a str,
+    inputs: &'a BTreeMap<String, String>,
+}
+
+/// Last reported status of a job or step, to log only what changed between polls.
+type ProgressState = (WorkflowRunStatus, Option<WorkflowRunConclusion>);
+
+impl<T: HttpTransport> GitHubApp<T> {
+    /// Triggers a `workflow_dispatch` run of `workflow` (its ID or file name, such as
+    /// `deploy.yml`) on `git_ref`. GitHub doesn't return the run it creates.
+    pub fn dispatch_workflow(
+        &self,
+        repository: &str,
+        workflow: &str,
+        git_ref: &str,
+        inputs: &BTreeMap<String, String>,
+    ) -> Result<(), GitHubError> {
+        self.send_json_without_response(
+            Authentication::Installation,
+            "POST",
+            &format!(
+                "{}{}",
+                workflow_route(repository, workflow),
+                GITHUB_WORKFLOW_DISPATCHES_API_ROUTE
+            ),
+            &WorkflowDispatch { git_ref, inputs },
+        )
+    }
+
+    /// Lists runs of `workflow`, newest first, optionally limited to a branch and trigger event.
+    pub fn list_workflow_runs(
+        &self,
+        repository: &str,
+        workflow: &str,
+        branch: Option<&str>,
+        event: Option<&str>,
+        options: PaginationOptions,
+    ) -> Paginator<'_, T, WorkflowRuns, WorkflowRun> {
+        let mut query: Vec<String> = vec![];
+        if let Some(branch) = branch {
+            query.push(format!("branch={}", urlencoding::encode(branch)));
+        }
+        if let Some(event) = event {
+            query.push(format!("event={}", urlencoding::encode(event)));
+        }
+        let route: String = match query.is_empty() {
+            true => format!("{}/runs", workflow_route(repository, workflow)),
+            false => format!(
+                "{}/runs?{}",
+                workflow_route(repository, workflow),
+                query.join("&")
+            ),
+        };
+        Paginator::new(self, Authentication::Installation, &route, options)
+    }
+
+    pub fn get_workflow_run(
+        &self,
+        repository: &str,
+        run_id: u64,
+    ) -> Result<WorkflowRun, GitHubError> {
+        self.get(Authentication::Installation, &run_route(repository, run_id))
+    }
+
+    /// Lists the jobs of a run's latest attempt.
+    pub fn list_workflow_run_jobs(
+        &self,
+        repository: &str,
+        run_id: u64,
+        options: PaginationOptions,
+    ) -> Paginator<'_, T, WorkflowJobs, WorkflowJob> {
+        Paginator::new(
+            self,
+            Authentication::Installation,
+            &format!(
+                "{}{}?filter=latest",
+                run_route(repository, run_id),
+                GITHUB_JOBS_API_ROUTE
+            ),
+            options,
+        )
+    }
+
+    pub fn list_workflow_run_artifacts(
+        &self,
+        repository: &str,
+        run_id: u64,
+        options: PaginationOptions,
+    ) -> Paginator<'_, T, Artifacts, Artifact> {
+        Paginator::new(
+            self,
+            Authentication::Installation,
+            &format!(
+                "{}{}",
+                run_route(repository, run_id),
+                GITHUB_ARTIFACTS_API_ROUTE
+            ),
+            options,
+        )
+    }
+
+    /// Downloads the logs of every job in a run as a zip archive.
+    pub fn download_workflow_run_logs(
+        &self,
+        repository: &str,
+        run_id: u64,
+    ) -> Result<Vec<u8>, GitHubError> {
+        self.get_bytes(
+            Authentication::Installation,
+            &format!("{}{}", run_route(repository, run_id), GITHUB_LOGS_API_ROUTE),
+        )
+    }
+
+    /// Downloads an artifact as a zip archive.
+    pub fn download_artifact(
+        &self,
+        repository: &str,
+        artifact_id: u64,
+    ) -> Result<Vec<u8>, GitHubError> {
+        self.get_bytes(
+            Authentication::Installation,
+            &format!(
+                "{}{}/{}{}",
+                repository_route(repository),
+                GITHUB_WORKFLOW_ARTIFACTS_API_ROUTE,
+                artifact_id,
+                GITHUB_ZIP_API_ROUTE
+            ),
+        )
+    }
+
+    /// Dispatches `workflow` on `git_ref` and returns the run it created.
+    ///
+    /// GitHub doesn't say which run a dispatch created, so runs of the workflow that existed
+    /// beforehand are remembered and the newest run that wasn't among them is taken.
+    pub fn dispatch_workflow_run(
+        &self,
+        repository: &str,
+        workflow: &str,
+        git_ref: &str,
+        inputs: &BTreeMap<String, String>,
+    ) -> Result<WorkflowRun> {
+        let existing_run_ids: HashSet<u64> = self
+            .recent_dispatched_runs(repository, workflow, git_ref)?
+            .iter()
+            .map(|run| run.id)
+            .collect();
+        let dispatched_at: OffsetDateTime = OffsetDateTime::now_utc();
+        self.dispatch_workflow(repository, workflow, git_ref, inputs)?;
+        info!("Dispatched {} on {} in {}", workflow, git_ref, repository);
+
+        let started: Instant = Instant::now();
+        loop {
+            let run: Option<WorkflowRun> = self
+                .recent_dispatched_runs(repository, workflow, git_ref)?
+                .into_iter()
+                .filter(|run| !existing_run_ids.contains(&run.id))
+                // Allow for clock skew between this machine and GitHub.
+                .filter(|run| run.created_at >= dispatched_at - Duration::from_secs(60))
+                .max_by_key(|run| run.created_at);
+            if let Some(run) = run {
+                info!("Dispatched run {} - {}", run.id, run.html_url);
+                return Ok(run);
+            }
+
+            if started.elapsed()
+                >= Duration::from_secs(GITHUB_WORKFLOW_DISPATCH_DISCOVERY_TIMEOUT_SECONDS)
+            {
+                return Err(anyhow!(
+                    "Dispatched {} on {} in {}, but no run appeared within {} seconds",
+                    workflow,
+                    git_ref,
+                    repository,
+                    GITHUB_WORKFLOW_DISPATCH_DISCOVERY_TIMEOUT_SECONDS
+                ));
+            }
+            debug!("Waiting for the dispatched run of {} to appear", workflow);
+            std::thread::sleep(Duration::from_secs(
+                GITHUB_WORKFLOW_RUN_POLL_INTERVAL_SECONDS,
+            ));
+        }
+    }
+
+    /// Polls a run until it completes, logging each job and step as its status changes.
+    /// Fails when `timeout` passes first. The returned run's conclusion tells whether it passed.
+    pub fn watch_workflow_run(
+        &self,
+        repository: &str,
+        run_id: u64,
+        timeout: Duration,
+    ) -> Result<WorkflowRun> {
+        let started: Instant = Instant::now();
+        let mut reported: HashMap<String, ProgressState> = HashMap::new();
+
+        loop {
+            let run: WorkflowRun = self.get_workflow_run(repository, run_id)?;
+            let options: PaginationOptions = PaginationOptions {
+                per_page: GITHUB_MAX_PER_PAGE,
+                max_pages: None,
+            };
+            for job in self.list_workflow_run_jobs(repository, run_id, options) {
+                let job: WorkflowJob = job?;
+                report_progress(&mut reported, job.name.clone(), job.status, job.conclusion);
+                for step in &job.steps {
+                    report_progress(
+                        &mut reported,
+                        format!("{} / {}", job.name, step.name),
+                        step.status,
+                        step.conclusion,
+                    );
+                }
+            }
+
+            if run.status == Some(WorkflowRunStatus::Completed) {
+                info!(
+                    "Run {} completed: {:?} - {}",
+                    run.id,
+                    run.conclusion.unwrap_or(WorkflowRunConclusion::Unknown),
+                    run.html_url
+                );
+                return Ok(run);
+            }
+
+            if started.elapsed() >= timeout {
+                return Err(anyhow!(
+                    "Timed out after {} seconds waiting for run {} in {} - {}",
+                    timeout.as_secs(),
+                    run.id,
+                    repository,
+                    run.html_url
+                ));
+            }
+            std::thread::sleep(Duration::from_secs(
+                GITHUB_WORKFLOW_RUN_POLL_INTERVAL_SECONDS,
+            ));
+        }
+    }
+
+    /// Downloads and extracts a run's logs into `directory`.
+    pub fn save_workflow_run_logs(
+        &self,
+        repository: &str,
+        run_id: u64,
+        directory: &Path,
+    ) -> Result<usize> {
+        let archive: Vec<u8> = self.download_workflow_run_logs(repository, run_id)?;
+        extract_zip_archive(&archive, directory)
+    }
+
+    /// Downloads and extracts every unexpired artifact of a run into its own directory under
+    /// `directory`, named after the artifact. Returns how many artifacts were saved.
+    pub fn save_workflow_run_artifacts(
+        &self,
+        repository: &str,
+        run_id: u64,
+        directory: &Path,
+    ) -> Result<usize> {
+        let options: PaginationOptions = PaginationOptions {
+            per_page: GITHUB_MAX_PER_PAGE,
+            max_pages: None,
+        };
+        let mut saved: usize = 0;
+        for artifact in self.list_workflow_run_artifacts(repository, run_id, options) {
+            let artifact: Artifact = artifact?;
+            if artifact.expired {
+                info!("Skipping expired artifact {}", artifact.name);
+                continue;
+            }
+            if artifact.name.contains(['/', '\\']) || artifact.name.starts_with('.') {
+                return Err(anyhow!(
+                    "Refusing to save artifact with an unsafe name: {:?}",
+                    artifact.name
+                ));
+            }
+
+            info!(
+                "Downloading artifact {} ({} bytes)",
+                artifact.name, artifact.size_in_bytes
+            );
+            let archive: Vec<u8> = self.download_artifact(repository, artifact.id)?;
+            extract_zip_archive(&archive, &directory.join(&artifact.name))?;
+            saved += 1;
+        }
+
+        Ok(saved)
+    }
+
+    /// The first page of dispatched runs of `workflow` on `git_ref`.
+    fn recent_dispatched_runs(
+        &self,
+        repository: &str,
+        workflow: &str,
+        git_ref: &str,
+    ) -> Result<Vec<WorkflowRun>, GitHubError> {
+        self.list_workflow_runs(
+            repository,
+            workflow,
+            Some(git_ref),
+            Some(GITHUB_WORKFLOW_DISPATCH_EVENT),
+            PaginationOptions {
+                per_page: GITHUB_DEFAULT_PER_PAGE,
+                max_pages: Some(1),
+            },
+        )
+        .collect()
+    }
+}
+
+/// Fails unless the completed `run` concluded successfully.
+pub fn ensure_workflow_run_succeeded(run: &WorkflowRun) -> Result<()> {
+    match run.conclusion {
+        Some(WorkflowRunConclusion::Success) => Ok(()),
+        conclusion => Err(anyhow!(
+            "Run {} of {} did not succeed: {:?} - {}",
+            run.id,
+            run.name.as_deref().unwrap_or("workflow"),
+            conclusion.unwrap_or(WorkflowRunConclusion::Unknown),
+            run.html_url
+        )),
+    }
+}
+
+/// Logs the status of the job or step `name` unless it was already reported. Returns whether it
+/// was logged.
+fn report_progress(
+    reported: &mut HashMap<String, ProgressState>,
+    name: String,
+    status: WorkflowRunStatus,
+    conclusion: Option<WorkflowRunConclusion>,
+) -> bool {
+    let state: ProgressState = (status, conclusion);
+    if reported.get(&name) == Some(&state) {
+        return false;
+    }
+    match conclusion {
+        Some(conclusion) => info!("{}: {:?}", name, conclusion),
+        None => info!("{}: {:?}", name, status),
+    }
+    reported.insert(name, state);
+    true
+}
+
+fn workflow_route(repository: &str, workflow: &str) -> String {
+    format!(
+        "{}{}/{}",
+        repository_route(repository),
+        GITHUB_WORKFLOWS_API_ROUTE,
+        urlencoding::encode(workflow)
+    )
+}
+
+fn run_route(repository: &str, run_id: u64) -> String {
+    format!(
+        "{}{}/{}",
+        repository_route(repository),
+        GITHUB_WORKFLOW_RUNS_API_ROUTE,
+        run_id
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use time::format_description::well_known::Rfc3339;
+
+    use super::*;
+    use crate::{
+        github::test_support::{access_token_body, access_token_url, test_github_app, test_url},
+        http::{fake::FakeTransport, HttpResponse},
+    };
+
+    const REPOSITORY: &str = "chronilore/loremaster";
+    const WORKFLOW: &str = "deploy.yml";
+
+    fn transport_with_token() -> FakeTransport {
+        FakeTransport::new().respond_to(
+            "POST",
+            &access_token_url(),
+            HttpResponse::new(
+                201,
+                &access_token_body(
+                    "ghs_workflows",
+                    OffsetDateTime::now_utc() + time::Duration::hours(1),
+                ),
+            ),
+        )
+    }
+
+    fn run_json(id: u64, created_at: OffsetDateTime, status: &str) -> serde_json::Value {
+        let created_at: String = created_at.format(&Rfc3339).unwrap();
+        serde_json::json!({
+            "id": id,
+            "name": "Deploy",
+            "workflow_id": 42,
+            "head_branch": "main",
+            "head_sha": "0123456789abcdef0123456789abcdef01234567",
+            "run_number": id,
+            "run_attempt": 1,
+            "event": "workflow_dispatch",
+            "status": status,
+            "conclusion": null,
+            "html_url": format!("https://github.example.com/{}/actions/runs/{}", REPOSITORY, id),
+            "created_at": created_at,
+            "updated_at": created_at,
+        })
+    }
+
+    fn runs_response(runs: Vec<serde_json::Value>) -> HttpResponse {
+        HttpResponse::new(
+            200,
+            &serde_json::json!({ "total_count": runs.len(), "workflow_runs": runs }).to_string(),
+        )
+    }
+
+    fn runs_url() -> String {
+        test_url(&format!(
+            "/repos/{}/actions/workflows/{}/runs?branch=main&event={}&per_page={}",
+            REPOSITORY, WORKFLOW, GITHUB_WORKFLOW_DISPATCH_EVENT, GITHUB_DEFAULT_PER_PAGE
+        ))
+    }
+
+    #[test]
+    fn dispatch_picks_the_run_that_did_not_exist_before() {
+        let now: OffsetDateTime = OffsetDateTime::now_utc();
+        // Run 7 is still queued from an earlier dispatch and is newer by GitHub's clock.
+        let earlier_run: serde_json::Value =
+            run_json(7, now + time::Duration::seconds(30), "queued");
+        let github_app: GitHubApp<FakeTransport> = test_github_app(
+            transport_with_token()
+                .respond_to("GET", &runs_url(), runs_response(vec![earlier_run.clone()]))
+                .respond_to(
+                    "POST",
+                    &test_url(&format!(
+                        "/repos/{}/actions/workflows/{}/dispatches",
+                        REPOSITORY, WORKFLOW
+                    )),
+                    HttpResponse::new(204, ""),
+                )
+                .respond_to(
+                    "GET",
+                    &runs_url(),
+                    runs_response(vec![earlier_run, run_json(8, now, "queued")]),
+                ),
+        );
+
+        let run: WorkflowRun = github_app
+            .dispatch_workflow_run(REPOSITORY, WORKFLOW, "main", &BTreeMap::new())
+            .unwrap();
+
+        assert_eq!(run.id, 8);
+        let dispatch: serde_json::Value = github_app
+            .transport()
+            .requests()
+            .into_iter()
+            .find(|request| request.method == "POST" && request.url.ends_with("/dispatches"))
+            .map(|request| serde_json::from_slice(&request.body.unwrap_or_default()).unwrap())
+            .unwrap();
+        assert_eq!(dispatch, serde_json::json!({ "ref": "main", "inputs": {} }));
+    }
+
+    #[test]
+    fn status_changes_are_reported_once() {
+        let mut reported: HashMap<String, ProgressState> = HashMap::new();
+        let mut report = |status: WorkflowRunStatus, conclusion: Option<WorkflowRunConclusion>| {
+            report_progress(
+                &mut reported,
+                String::from("build / test"),
+                status,
+                conclusion,
+            )
+        };
+
+        assert!(report(WorkflowRunStatus::Queued, None));
+        assert!(!report(WorkflowRunStatus::Queued, None));
+        assert!(report(WorkflowRunStatus::InProgress, None));
+        assert!(!report(WorkflowRunStatus::InProgress, None));
+        assert!(report(
+            WorkflowRunStatus::Completed,
+            Some(WorkflowRunConclusion::Success)
+        ));
+        assert!(!report(
+            WorkflowRunStatus::Completed,
+            Some(WorkflowRunConclusion::Success)
+        ));
+    }
+
+    #[test]
+    fn jobs_and_steps_are_tracked_separately() {
+        let mut reported: HashMap<String, ProgressState> = HashMap::new();
+
+        assert!(report_progress(
+            &mut reported,
+            String::from("build"),
+            WorkflowRunStatus::InProgress,
+            None
+        ));
+        assert!(report_progress(
+            &mut reported,
+            String::from("build / checkout"),
+            WorkflowRunStatus::InProgress,
+            None
+        ));
+        assert!(!report_progress(
+            &mut reported,
+            String::from("build"),
+            WorkflowRunStatus::InProgress,
+            None
+        ));
+    }
+
+    #[test]
+    fn watch_returns_the_completed_run() {
+        let mut completed: serde_json::Value = run_json(8, OffsetDateTime::now_utc(), "completed");
+        completed["conclusion"] = serde_json::json!("failure");
+        let github_app: GitHubApp<FakeTransport> = test_github_app(
+            transport_with_token()
+                .respond_to(
+                    "GET",
+                    &test_url(&format!("/repos/{}/actions/runs/8", REPOSITORY)),
+                    HttpResponse::new(200, &completed.to_string()),
+                )
+                .respond_to(
+                    "GET",
+                    &test_url(&format!(
+                        "/repos/{}/actions/runs/8/jobs?filter=latest&per_page={}",
+                        REPOSITORY, GITHUB_MAX_PER_PAGE
+                    )),
+                    HttpResponse::new(200, r#"{"total_count":0,"jobs":[]}"#),
+                ),
+        );
+
+        let run: WorkflowRun = github_app
+            .watch_workflow_run(REPOSITORY, 8, Duration::from_secs(60))
+            .unwrap();
+
+        assert_eq!(run.conclusion, Some(WorkflowRunConclusion::Failure));
+        assert!(ensure_workflow_run_succeeded(&run).is_err());
+    }
+
+    #[test]
+    fn artifacts_with_unsafe_names_are_refused() {
+        let directory: std::path::PathBuf = std::env::temp_dir().join(format!(
+            "lorerunner-workflows-{}-unsafe-artifacts",
+            std::process::id()
+        ));
+        let artifacts_url: String = test_url(&format!(
+            "/repos/{}/actions/runs/8/artifacts?per_page={}",
+            REPOSITORY, GITHUB_MAX_PER_PAGE
+        ));
+
+        for name in ["../escape", "nested/site", "nested\\site", ".hidden"] {
+            let artifacts: String = serde_json::json!({
+                "total_count": 1,
+                "artifacts": [
+                    { "id": 3, "name": name, "size_in_bytes": 10, "expired": false },
+                ],
+            })
+            .to_string();
+            let github_app: GitHubApp<FakeTransport> =
+                test_github_app(transport_with_token().respond_to(
+                    "GET",
+                    &artifacts_url,
+                    HttpResponse::new(200, &artifacts),
+                ));
+
+            assert!(github_app
+                .save_workflow_run_artifacts(REPOSITORY, 8, &directory)
+                .is_err());
+            assert!(github_app
+                .transport()
+                .requests()
+                .iter()
+                .all(|request| !request.url.ends_with("/zip")));
+        }
+        assert!(!directory.exists());
+    }
+}
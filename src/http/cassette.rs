@@ -0,0 +1,339 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use anyhow::Result;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    configuration::constants::{
+        github::{GITHUB_ACCESS_TOKENS_API_ROUTE, GITHUB_APP_INSTALLATIONS_API_ROUTE},
+        http::CASSETTE_REDACTED_VALUE,
+    },
+    file_system::get_file_content_as_string,
+};
+
+use super::{HttpRequest, HttpResponse, HttpTransport, TransportError};
+
+/// A single request/response pair stored in a cassette file.
+///
+/// Request headers are never written and installation tokens in responses are redacted, so tokens
+/// don't end up in version control.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    pub method: String,
+    pub url: String,
+    pub request_body: Option<CassetteBody>,
+    pub status: u16,
+    pub status_text: String,
+    pub response_headers: Vec<(String, String)>,
+    pub response_body: CassetteBody,
+}
+
+/// A request or response body as stored in a cassette.
+///
+/// UTF-8 bodies, which covers the JSON the GitHub API speaks, are kept as readable text. Anything
+/// else, such as release assets and zip archives, is base64 encoded so it replays byte for byte.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CassetteBody {
+    Text(String),
+    Base64(String),
+}
+
+impl CassetteBody {
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        match std::str::from_utf8(bytes) {
+            Ok(text) => CassetteBody::Text(text.to_string()),
+            Err(_) => CassetteBody::Base64(STANDARD.encode(bytes)),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, TransportError> {
+        match self {
+            CassetteBody::Text(text) => Ok(text.as_bytes().to_vec()),
+            CassetteBody::Base64(encoded) => Ok(STANDARD.decode(encoded)?),
+        }
+    }
+}
+
+impl Interaction {
+    /// Whether this interaction answers `request`. Bodies are only compared when
+    /// `match_request_bodies` is set, since sealed secrets and timestamps make them differ
+    /// between runs.
+    fn matches(&self, request: &HttpRequest, match_request_bodies: bool) -> bool {
+        self.method.eq_ignore_ascii_case(&request.method)
+            && self.url == request.url
+            && (!match_request_bodies || self.request_body == request_body(request))
+    }
+
+    fn to_response(&self) -> Result<HttpResponse, TransportError> {
+        Ok(HttpResponse {
+            status: self.status,
+            status_text: self.status_text.clone(),
+            headers: self.response_headers.clone(),
+            body: self.response_body.to_bytes()?,
+        })
+    }
+}
+
+enum CassetteMode {
+    /// Forward requests to the inner transport and append every interaction to the cassette.
+    Record(Box<dyn HttpTransport>),
+    /// Serve interactions from the cassette without touching the network.
+    Replay,
+}
+
+/// Record/replay transport that lets the GitHub integration run offline against canned responses.
+pub struct CassetteTransport {
+    path: PathBuf,
+    mode: CassetteMode,
+    interactions: Mutex<Vec<Interaction>>,
+    replayed: Mutex<Vec<bool>>,
+    match_request_bodies: bool,
+}
+
+impl CassetteTransport {
+    /// Starts a new cassette at `path`, overwriting any existing recording once the first
+    /// interaction completes.
+    pub fn record(path: &Path, inner: impl HttpTransport + 'static) -> Self {
+        info!("Recording HTTP interactions to {}", path.display());
+        CassetteTransport {
+            path: path.to_path_buf(),
+            mode: CassetteMode::Record(Box::new(inner)),
+            interactions: Mutex::new(vec![]),
+            replayed: Mutex::new(vec![]),
+            match_request_bodies: false,
+        }
+    }
+
+    pub fn replay(path: &Path) -> Result<Self> {
+        info!("Replaying HTTP interactions from {}", path.display());
+        let interactions: Vec<Interaction> = ron::from_str(&get_file_content_as_string(
+            path.to_string_lossy().to_string(),
+        )?)?;
+
+        Ok(CassetteTransport {
+            path: path.to_path_buf(),
+            mode: CassetteMode::Replay,
+            replayed: Mutex::new(vec![false; interactions.len()]),
+            interactions: Mutex::new(interactions),
+            match_request_bodies: false,
+        })
+    }
+
+    /// Only replay an interaction for a request whose body matches the recorded one.
+    pub fn match_request_bodies(mut self, match_request_bodies: bool) -> Self {
+        self.match_request_bodies = match_request_bodies;
+        self
+    }
+
+    fn save(&self, interactions: &[Interaction]) -> Result<(), TransportError> {
+        let content: String =
+            ron::ser::to_string_pretty(interactions, ron::ser::PrettyConfig::default())?;
+        std::fs::write(&self.path, content)?;
+        Ok(())
+    }
+}
+
+impl HttpTransport for CassetteTransport {
+    fn send(&self, request: &HttpRequest) -> Result<HttpResponse, TransportError> {
+        let mut interactions = self
+            .interactions
+            .lock()
+            .map_err(|_| "Cassette interaction lock was poisoned")?;
+
+        match &self.mode {
+            CassetteMode::Record(inner) => {
+                let response: HttpResponse = inner.send(request)?;
+                interactions.push(Interaction {
+                    method: request.method.clone(),
+                    url: request.url.clone(),
+                    request_body: request_body(request),
+                    status: response.status,
+                    status_text: response.status_text.clone(),
+                    response_headers: response.headers.clone(),
+                    response_body: redact_response_body(request, &response),
+                });
+                self.save(&interactions)?;
+                debug!("Recorded [{}][{}]", request.method, request.url);
+
+                Ok(response)
+            }
+            CassetteMode::Replay => {
+                let mut replayed = self
+                    .replayed
+                    .lock()
+                    .map_err(|_| "Cassette replay lock was poisoned")?;
+                let Some(index) =
+                    interactions
+                        .iter()
+                        .enumerate()
+                        .position(|(index, interaction)| {
+                            !replayed[index]
+                                && interaction.matches(request, self.match_request_bodies)
+                        })
+                else {
+                    return Err(format!(
+                        "Cassette {} has no unplayed interaction for [{}][{}]",
+                        self.path.display(),
+                        request.method,
+                        request.url
+                    )
+                    .into());
+                };
+                replayed[index] = true;
+                debug!("Replayed [{}][{}]", request.method, request.url);
+
+                interactions[index].to_response()
+            }
+        }
+    }
+}
+
+/// The response body to save, with the token blanked out when `request` exchanged the app's
+/// JSON web token for an installation token.
+fn redact_response_body(request: &HttpRequest, response: &HttpResponse) -> CassetteBody {
+    let is_token_exchange: bool = request.method.eq_ignore_ascii_case("POST")
+        && request.url.contains(GITHUB_APP_INSTALLATIONS_API_ROUTE)
+        && request.url.ends_with(GITHUB_ACCESS_TOKENS_API_ROUTE);
+    if !is_token_exchange {
+        return CassetteBody::from_bytes(&response.body);
+    }
+
+    match serde_json::from_slice::<serde_json::Value>(&response.body) {
+        Ok(serde_json::Value::Object(mut body)) => {
+            if body.contains_key("token") {
+                body.insert(
+                    String::from("token"),
+                    serde_json::Value::from(CASSETTE_REDACTED_VALUE),
+                );
+            }
+            CassetteBody::Text(serde_json::Value::Object(body).to_string())
+        }
+        // Error responses carry no token.
+        _ => CassetteBody::from_bytes(&response.body),
+    }
+}
+
+fn request_body(request: &HttpRequest) -> Option<CassetteBody> {
+    request
+        .body
+        .as_ref()
+        .map(|body| CassetteBody::from_bytes(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::fake::FakeTransport;
+
+    const RELEASES_URL: &str = "https://api.github.com/repos/chronilore/loremaster/releases";
+    const ASSET_URL: &str = "https://api.github.com/repos/chronilore/loremaster/releases/assets/1";
+    const TOKEN_URL: &str = "https://api.github.com/app/installations/5678/access_tokens";
+    const ZIP_BYTES: [u8; 6] = [0x50, 0x4b, 0x03, 0x04, 0xff, 0xfe];
+
+    fn cassette_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "lorerunner-cassette-{}-{}.ron",
+            std::process::id(),
+            name
+        ))
+    }
+
+    fn record(path: &Path) {
+        let mut binary_response: HttpResponse = HttpResponse::new(200, "");
+        binary_response.body = ZIP_BYTES.to_vec();
+        let transport: CassetteTransport = CassetteTransport::record(
+            path,
+            FakeTransport::new()
+                .respond_to(
+                    "POST",
+                    TOKEN_URL,
+                    HttpResponse::new(
+                        201,
+                        r#"{"token":"ghs_secret","expires_at":"2030-01-01T00:00:00Z"}"#,
+                    ),
+                )
+                .respond_to(
+                    "POST",
+                    RELEASES_URL,
+                    HttpResponse::new(201, r#"{"id":1}"#).with_header("ETag", "\"1\""),
+                )
+                .respond_to("GET", ASSET_URL, binary_response),
+        );
+
+        transport
+            .send(&HttpRequest::new("POST", TOKEN_URL))
+            .unwrap();
+        transport
+            .send(&HttpRequest::new("POST", RELEASES_URL).body(br#"{"tag_name":"v1"}"#.to_vec()))
+            .unwrap();
+        transport.send(&HttpRequest::new("GET", ASSET_URL)).unwrap();
+    }
+
+    #[test]
+    fn recorded_interactions_replay_offline() {
+        let path: PathBuf = cassette_path("round-trip");
+        record(&path);
+
+        let replay: CassetteTransport = CassetteTransport::replay(&path).unwrap();
+        let release: HttpResponse = replay
+            .send(&HttpRequest::new("POST", RELEASES_URL).body(br#"{"tag_name":"v1"}"#.to_vec()))
+            .unwrap();
+        let asset: HttpResponse = replay.send(&HttpRequest::new("GET", ASSET_URL)).unwrap();
+        let token: HttpResponse = replay.send(&HttpRequest::new("POST", TOKEN_URL)).unwrap();
+        let replayed_twice = replay.send(&HttpRequest::new("GET", ASSET_URL));
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(release.status, 201);
+        assert_eq!(release.body_as_string(), r#"{"id":1}"#);
+        assert_eq!(release.header("etag"), Some("\"1\""));
+        assert_eq!(asset.body, ZIP_BYTES.to_vec());
+        assert!(!token.body_as_string().contains("ghs_secret"));
+        assert!(token.body_as_string().contains(CASSETTE_REDACTED_VALUE));
+        assert!(replayed_twice.is_err());
+    }
+
+    #[test]
+    fn binary_bodies_are_stored_as_base64() {
+        let path: PathBuf = cassette_path("base64");
+        record(&path);
+
+        let interactions: Vec<Interaction> =
+            ron::from_str(&get_file_content_as_string(path.to_string_lossy().to_string()).unwrap())
+                .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            interactions[1].request_body,
+            Some(CassetteBody::Text(String::from(r#"{"tag_name":"v1"}"#)))
+        );
+        assert_eq!(
+            interactions[2].response_body,
+            CassetteBody::Base64(STANDARD.encode(ZIP_BYTES))
+        );
+    }
+
+    #[test]
+    fn request_bodies_only_match_when_opted_in() {
+        let path: PathBuf = cassette_path("bodies");
+        record(&path);
+        let changed_body: HttpRequest =
+            HttpRequest::new("POST", RELEASES_URL).body(br#"{"tag_name":"v2"}"#.to_vec());
+
+        let by_url = CassetteTransport::replay(&path)
+            .unwrap()
+            .send(&changed_body);
+        let by_body = CassetteTransport::replay(&path)
+            .unwrap()
+            .match_request_bodies(true)
+            .send(&changed_body);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(by_url.is_ok());
+        assert!(by_body.is_err());
+    }
+}
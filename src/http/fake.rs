@@ -0,0 +1,69 @@
+use std::sync::Mutex;
+
+use super::{HttpRequest, HttpResponse, HttpTransport, TransportError};
+
+struct CannedResponse {
+    method: String,
+    url: String,
+    response: HttpResponse,
+}
+
+/// In-process transport that serves canned responses and remembers every request it received.
+///
+/// Each canned response is served once, in the order it was registered for its method and URL.
+/// Requests without a matching response fail like an unreachable host would.
+#[derive(Default)]
+pub struct FakeTransport {
+    responses: Mutex<Vec<CannedResponse>>,
+    requests: Mutex<Vec<HttpRequest>>,
+}
+
+impl FakeTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn respond_to(self, method: &str, url: &str, response: HttpResponse) -> Self {
+        self.responses
+            .lock()
+            .expect("Fake transport response lock was poisoned")
+            .push(CannedResponse {
+                method: method.to_string(),
+                url: url.to_string(),
+                response,
+            });
+        self
+    }
+
+    pub fn requests(&self) -> Vec<HttpRequest> {
+        self.requests
+            .lock()
+            .expect("Fake transport request lock was poisoned")
+            .clone()
+    }
+}
+
+impl HttpTransport for FakeTransport {
+    fn send(&self, request: &HttpRequest) -> Result<HttpResponse, TransportError> {
+        self.requests
+            .lock()
+            .map_err(|_| "Fake transport request lock was poisoned")?
+            .push(request.clone());
+
+        let mut responses = self
+            .responses
+            .lock()
+            .map_err(|_| "Fake transport response lock was poisoned")?;
+        let Some(index) = responses.iter().position(|canned| {
+            canned.method.eq_ignore_ascii_case(&request.method) && canned.url == request.url
+        }) else {
+            return Err(format!(
+                "No canned response for [{}][{}]",
+                request.method, request.url
+            )
+            .into());
+        };
+
+        Ok(responses.remove(index).response)
+    }
+}
@@ -1,3 +1,134 @@
+use serde::{Deserialize, Serialize};
+
+pub mod cassette;
+pub mod fake;
+pub mod ureq_transport;
+
+pub type TransportError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Sends HTTP requests on behalf of an API client.
+///
+/// Implementations return every response GitHub (or a stand-in) produces, including non-success
+/// statuses. `Err` is reserved for requests that never received a response.
+pub trait HttpTransport: Send + Sync {
+    fn send(&self, request: &HttpRequest) -> Result<HttpResponse, TransportError>;
+}
+
+impl HttpTransport for Box<dyn HttpTransport> {
+    fn send(&self, request: &HttpRequest) -> Result<HttpResponse, TransportError> {
+        self.as_ref().send(request)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<Vec<u8>>,
+}
+
+impl HttpRequest {
+    pub fn new(method: &str, url: &str) -> Self {
+        HttpRequest {
+            method: method.to_string(),
+            url: url.to_string(),
+            headers: vec![],
+            body: None,
+        }
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn body(mut self, body: Vec<u8>) -> Self {
+        self.body = Some(body);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub status_text: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    pub fn new(status: u16, body: &str) -> Self {
+        HttpResponse {
+            status,
+            status_text: String::new(),
+            headers: vec![],
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Returns the first header named `name`, compared case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header_name, _)| header_name.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    pub fn body_as_string(&self) -> String {
+        String::from_utf8_lossy(&self.body).to_string()
+    }
+}
+
+/// Joins an API base URL and a route, keeping any path prefix on the base such as `/api/v3`.
+pub fn join_url(base_url: &str, route: &str) -> String {
+    let base_url: &str = base_url.trim_end_matches('/');
+    match route.is_empty() || route.starts_with('/') {
+        true => format!("{}{}", base_url, route),
+        false => format!("{}/{}", base_url, route),
+    }
+}
+
 pub fn format_authorization_bearer_token(token: &str) -> String {
     format!("Bearer {}", token)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn join_url_keeps_the_enterprise_server_path_prefix() {
+        for base_url in [
+            "https://github.example.com/api/v3",
+            "https://github.example.com/api/v3/",
+        ] {
+            assert_eq!(
+                join_url(base_url, "/app/installations"),
+                "https://github.example.com/api/v3/app/installations"
+            );
+            assert_eq!(
+                join_url(base_url, "app/installations"),
+                "https://github.example.com/api/v3/app/installations"
+            );
+            assert_eq!(join_url(base_url, ""), "https://github.example.com/api/v3");
+        }
+    }
+
+    #[test]
+    fn join_url_on_a_bare_host() {
+        assert_eq!(
+            join_url("https://api.github.com/", "/rate_limit"),
+            "https://api.github.com/rate_limit"
+        );
+    }
+}
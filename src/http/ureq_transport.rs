@@ -0,0 +1,66 @@
+use std::io::Read;
+
+use super::{HttpRequest, HttpResponse, HttpTransport, TransportError};
+
+/// Sends requests over the network with a shared `ureq::Agent`.
+pub struct UreqTransport {
+    agent: ureq::Agent,
+}
+
+impl UreqTransport {
+    pub fn new() -> Self {
+        UreqTransport {
+            agent: ureq::Agent::new(),
+        }
+    }
+}
+
+impl Default for UreqTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HttpTransport for UreqTransport {
+    fn send(&self, request: &HttpRequest) -> Result<HttpResponse, TransportError> {
+        let mut ureq_request: ureq::Request = self.agent.request(&request.method, &request.url);
+        for (name, value) in &request.headers {
+            ureq_request = ureq_request.set(name, value);
+        }
+
+        let result = match &request.body {
+            Some(body) => ureq_request.send_bytes(body),
+            None => ureq_request.call(),
+        };
+
+        // ureq reports 4xx/5xx as errors, but callers decide what a status code means.
+        let response: ureq::Response = match result {
+            Ok(response) => response,
+            Err(ureq::Error::Status(_, response)) => response,
+            Err(ureq::Error::Transport(transport)) => return Err(Box::new(transport)),
+        };
+
+        let status: u16 = response.status();
+        let status_text: String = response.status_text().to_string();
+        let headers: Vec<(String, String)> = response
+            .headers_names()
+            .into_iter()
+            .flat_map(|name| {
+                response
+                    .all(&name)
+                    .into_iter()
+                    .map(|value| (name.clone(), value.to_string()))
+                    .collect::<Vec<(String, String)>>()
+            })
+            .collect();
+        let mut body: Vec<u8> = vec![];
+        response.into_reader().read_to_end(&mut body)?;
+
+        Ok(HttpResponse {
+            status,
+            status_text,
+            headers,
+            body,
+        })
+    }
+}
@@ -1,35 +1,79 @@
 use crate::configuration::constants::project_file_paths::{
     FRONTEND_PATH, FRONTEND_PKG_PATH, GITHUB_PAGES_REPOSITORY_PATH,
 };
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use log::info;
 
+use crate::{
+    configuration::{
+        constants::github::GITHUB_PAGES_WORKFLOW_BUILD_TYPE, Configuration, SitePublishMethod,
+    },
+    github::{deployments::validate_repository, github_app::GitHubApp},
+    http::HttpTransport,
+};
+
 use crate::configuration::constants::command_line::{
     PERSEUS_COMMAND, PERSEUS_DEPLOY_COMMAND, PERSEUS_DEPLOY_EXPORT_FLAG,
 };
+use crate::file_system::copy_directory_to;
 use std::{
-    fs::ReadDir,
+    fmt,
     path::Path,
     process::{Command, Output},
+    time::Duration,
 };
 
-pub fn deploy_loremaster_static_site() -> Result<()> {
-    let source_path: &Path = Path::new(FRONTEND_PATH);
-    let target_path: &Path = Path::new(GITHUB_PAGES_REPOSITORY_PATH);
-    let pkg_path: &Path = Path::new(FRONTEND_PKG_PATH);
+/// Everything `perseus deploy -e` printed while exporting a site that failed to build, so its
+/// diagnostics can be reported.
+#[derive(Debug)]
+pub struct SiteExportError {
+    pub output: String,
+}
 
-    if !target_path.exists() {
-        panic!("Target directory doesn't exist!");
+impl fmt::Display for SiteExportError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            formatter,
+            "Failed to deploy frontend files: {}",
+            self.output
+        )
     }
+}
 
-    if !source_path.exists() {
-        panic!("Target directory doesn't exist!");
+impl std::error::Error for SiteExportError {}
+
+/// Deploys the site the way `configuration.site_publish_method` says, recording `source_ref` in
+/// the pages commit when publishing through the Git Data API.
+///
+/// Returns the build output of the export. A failed build is a `SiteExportError`.
+pub fn deploy_site<T: HttpTransport>(
+    github_app: &GitHubApp<T>,
+    configuration: &Configuration,
+    source_ref: Option<&str>,
+) -> Result<String> {
+    match configuration.site_publish_method {
+        SitePublishMethod::LocalRepository => deploy_loremaster_static_site(),
+        SitePublishMethod::GitDataApi => {
+            publish_loremaster_static_site(github_app, configuration, source_ref)
+        }
     }
+}
+
+/// Runs the Perseus static export, leaving the site in `FRONTEND_PKG_PATH`. Returns what the
+/// build printed, or a `SiteExportError` holding it when the build fails.
+pub fn export_loremaster_static_site() -> Result<String> {
+    let source_path: &Path = Path::new(FRONTEND_PATH);
 
-    std::env::set_current_dir(source_path)?;
+    if !source_path.exists() {
+        return Err(anyhow!(
+            "Frontend directory doesn't exist: {}",
+            source_path.display()
+        ));
+    }
 
     let mut binding = Command::new(PERSEUS_COMMAND);
     let deploy_command: &mut Command = binding
+        .current_dir(source_path)
         .arg(PERSEUS_DEPLOY_COMMAND)
         .arg(PERSEUS_DEPLOY_EXPORT_FLAG);
 
@@ -37,58 +81,138 @@ pub fn deploy_loremaster_static_site() -> Result<()> {
 
     let deploy_output: Output = deploy_command.output()?;
 
+    // Cargo prints its diagnostics on stderr.
+    let output: String = format!(
+        "{}{}",
+        String::from_utf8_lossy(&deploy_output.stdout),
+        String::from_utf8_lossy(&deploy_output.stderr)
+    );
     match deploy_output.status.success() {
-        true => info!("output: {}", String::from_utf8(deploy_output.stdout)?),
-        false => {
-            info!("{}", String::from_utf8(deploy_output.stderr)?);
-            panic!("Failed to deploy frontend files. Exiting...");
+        true => {
+            info!("output: {}", output);
+            Ok(output)
         }
+        false => Err(SiteExportError { output }.into()),
     }
+}
 
-    if !source_path.read_dir().is_ok_and(|mut reader: ReadDir| {
-        reader.any(|item| {
-            item.is_ok_and(|entry| {
-                entry.file_name().eq_ignore_ascii_case("pkg") && entry.path().is_dir()
-            })
-        })
-    }) {
-        panic!("pkg thing directory doesn't exist! Exiting...");
-    }
+/// Exports the site and commits it to the pages branch through the GitHub Git Data API, so no
+/// local clone of the pages repository is needed.
+pub fn publish_loremaster_static_site<T: HttpTransport>(
+    github_app: &GitHubApp<T>,
+    configuration: &Configuration,
+    source_ref: Option<&str>,
+) -> Result<String> {
+    let repository: &str = configuration.get_site_pages_repository();
+    validate_repository(repository)?;
+
+    let build_output: String = export_loremaster_static_site()?;
+
+    let message: String = match source_ref {
+        Some(source_ref) => format!(
+            "Deploy loremaster static site from {}@{}",
+            configuration.site_repository, source_ref
+        ),
+        None => String::from("Deploy loremaster static site"),
+    };
+    // The pages repository may live in another organization than the site repository.
+    let github_app: GitHubApp<T> = github_app.for_repository(repository)?;
+    let Some(commit) = github_app.publish_directory(
+        repository,
+        &configuration.site_pages_branch,
+        Path::new(FRONTEND_PKG_PATH),
+        &configuration.site_pages_preserved_paths,
+        &message,
+    )?
+    else {
+        info!("Pages branch already matches the export, no Pages build to wait for");
+        return Ok(build_output);
+    };
+
+    wait_for_pages_build(&github_app, configuration, &commit.sha)?;
+
+    Ok(build_output)
+}
 
-    if !pkg_path.exists() {
-        panic!("pkg directory doesn't exist! Exiting...");
+/// Waits for GitHub Pages to build `commit_sha`, so a failed build fails the deploy.
+fn wait_for_pages_build<T: HttpTransport>(
+    github_app: &GitHubApp<T>,
+    configuration: &Configuration,
+    commit_sha: &str,
+) -> Result<()> {
+    if configuration.site_pages_build_timeout_seconds == 0 {
+        return Ok(());
     }
 
-    let pkg_read_directory = pkg_path.read_dir()?;
-    for possible_entry in pkg_read_directory {
-        let Ok(entry) = possible_entry else {
-            continue;
-        };
-
-        let directory_path_string = target_path.to_str().unwrap();
-        if let Some(current_file_name) = entry.file_name().to_str() {
-            let new_file_path = format!("{}/{}", directory_path_string, current_file_name);
-
-            let new_path = Path::new(new_file_path.as_str());
-
+    let repository: &str = configuration.get_site_pages_repository();
+    match github_app.get_pages(repository)? {
+        None => {
+            return Err(anyhow!(
+                "GitHub Pages is not enabled for {}. Enable it to serve the published site.",
+                repository
+            ))
+        }
+        // Actions-deployed sites don't report through the Pages builds API.
+        Some(pages) if pages.build_type.as_deref() == Some(GITHUB_PAGES_WORKFLOW_BUILD_TYPE) => {
             info!(
-                "moving \n\t{} \n\tto \n\t{} \n\tIs new: {}",
-                entry.path().to_str().unwrap(),
-                new_file_path,
-                !new_path.exists()
+                "{} deploys Pages through GitHub Actions, not waiting for a Pages build",
+                repository
             );
-
-            // std::fs::copy(entry.path(), new_path)?;
+            return Ok(());
         }
+        Some(_) => {}
     }
 
-    // let ls_output: Output = Command::new(LIST_COMMAND)
-    //     .arg(LIST_COMMAND_ALL_FLAG)
-    //     .output()?;
+    github_app.wait_for_pages_build(
+        repository,
+        Duration::from_secs(configuration.site_pages_build_timeout_seconds),
+        |build| build.commit.as_deref() == Some(commit_sha),
+    )?;
 
-    // match ls_output.status.success() {
-    //     true => println!("{}", String::from_utf8(ls_output.stdout)?),
-    //     false => println!("{}", String::from_utf8(ls_output.stderr)?),
-    // }
     Ok(())
 }
+
+/// Exports the site and copies it into the pages repository checked out at
+/// `GITHUB_PAGES_REPOSITORY_PATH`, replacing files with the same names. Returns the build output
+/// of the export.
+pub fn deploy_loremaster_static_site() -> Result<String> {
+    let target_path: &Path = Path::new(GITHUB_PAGES_REPOSITORY_PATH);
+    let pkg_path: &Path = Path::new(FRONTEND_PKG_PATH);
+
+    if GITHUB_PAGES_REPOSITORY_PATH.is_empty() {
+        return Err(anyhow!(
+            "No local GitHub Pages repository is set. Set GITHUB_PAGES_REPOSITORY_PATH or publish with SitePublishMethod::GitDataApi."
+        ));
+    }
+    if !target_path.is_dir() {
+        return Err(anyhow!(
+            "GitHub Pages repository directory doesn't exist: {}",
+            target_path.display()
+        ));
+    }
+
+    let build_output: String = export_loremaster_static_site()?;
+
+    if !pkg_path.is_dir() {
+        return Err(anyhow!(
+            "Perseus export directory doesn't exist: {}",
+            pkg_path.display()
+        ));
+    }
+
+    let copied: usize = copy_directory_to(pkg_path, target_path)?;
+    if copied == 0 {
+        return Err(anyhow!(
+            "Perseus export in {} is empty, nothing to deploy",
+            pkg_path.display()
+        ));
+    }
+    info!(
+        "Copied {} file(s) from {} to {}",
+        copied,
+        pkg_path.display(),
+        target_path.display()
+    );
+
+    Ok(build_output)
+}